@@ -0,0 +1,133 @@
+use std::cmp::min;
+use std::time::{Duration, Instant};
+use meta_signals::MetaSignal;
+
+/// Shaping of the activity of a behavior before it is published, e.g. to keep a
+/// [`crate::modules::maximum_fusion::MaximumFusion`] from switching back and forth between
+/// behaviors whose target ratings flip. Set with [`crate::modules::behavior_module::BehaviorModule::with_activity_shaping`].
+///
+/// Shaping never lets the activity exceed what stimulation and inhibition allow,
+/// so inhibiting a behavior still stops it immediately.
+#[derive(Clone, Debug, Default)]
+pub struct ActivityShaping {
+    /// Thresholds to switch on and off.
+    hysteresis: Option<(MetaSignal, MetaSignal)>,
+    /// Maximum change per second.
+    rise_rate: Option<f64>,
+    fall_rate: Option<f64>,
+    min_on_time: Option<Duration>,
+
+    /// Last published activity.
+    activity: MetaSignal,
+    /// Whether the activity passed the upper threshold of the hysteresis and not yet the lower one.
+    on: bool,
+    last_update: Option<Instant>,
+    active_since: Option<Instant>,
+}
+
+impl ActivityShaping {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The activity is LOW until it reaches `on`. From then on it is passed through
+    /// until it falls below `off`, which makes it LOW again until it reaches `on`.
+    pub fn with_hysteresis(mut self, on: MetaSignal, off: MetaSignal) -> Self {
+        self.hysteresis = Some((on, off));
+        self
+    }
+
+    /// Limits the increase of the activity to `rate` per second.
+    pub fn with_rise_rate(mut self, rate: f64) -> Self {
+        self.rise_rate = Some(rate);
+        self
+    }
+
+    /// Limits the decrease of the activity to `rate` per second.
+    pub fn with_fall_rate(mut self, rate: f64) -> Self {
+        self.fall_rate = Some(rate);
+        self
+    }
+
+    /// Keeps the last activity for at least `min_on_time` after the behavior became active,
+    /// instead of switching to LOW.
+    pub fn with_min_on_time(mut self, min_on_time: Duration) -> Self {
+        self.min_on_time = Some(min_on_time);
+        self
+    }
+
+    /// Shapes the `activity` computed at `now`, at most `limit`, the activity stimulation and inhibition allow.
+    /// Rates apply to the time since the last call, so they follow the actual cycle time.
+    pub fn shape(&mut self, activity: MetaSignal, limit: MetaSignal, now: Instant) -> MetaSignal {
+        let mut shaped = activity;
+        if let Some((on, off)) = self.hysteresis {
+            self.on = if self.on { activity >= off } else { activity >= on };
+            if !self.on {
+                shaped = MetaSignal::LOW;
+            }
+        }
+        if let Some(min_on_time) = self.min_on_time
+            && let Some(active_since) = self.active_since
+            && shaped == MetaSignal::LOW
+            && now - active_since < min_on_time
+        {
+            shaped = self.activity;
+        }
+
+        let elapsed = self.last_update.map_or(0.0, |last_update| (now - last_update).as_secs_f64());
+        if shaped > self.activity && let Some(rate) = self.rise_rate {
+            shaped = min(shaped, MetaSignal::new(*self.activity + rate * elapsed));
+        } else if shaped < self.activity && let Some(rate) = self.fall_rate {
+            shaped = std::cmp::max(shaped, MetaSignal::new(*self.activity - rate * elapsed));
+        }
+        shaped = min(shaped, limit);
+
+        self.active_since = match shaped > MetaSignal::LOW {
+            true => Some(self.active_since.unwrap_or(now)),
+            false => None,
+        };
+        self.activity = shaped;
+        self.last_update = Some(now);
+        shaped
+    }
+
+    /// Forgets the time of the last update, e.g. after the module was paused.
+    pub(crate) fn reset_time(&mut self) {
+        self.last_update = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use meta_signals::MetaSignal;
+    use super::ActivityShaping;
+
+    #[test]
+    fn activity_shaping() {
+        let start = Instant::now();
+        let ms = |ms| start + Duration::from_millis(ms);
+        let m = MetaSignal::new;
+
+        let mut hysteresis = ActivityShaping::new().with_hysteresis(m(0.6), m(0.4));
+        let shaped: Vec<_> = [0.5, 0.7, 0.5, 0.3, 0.5].into_iter().enumerate()
+            .map(|(cycle, activity)| *hysteresis.shape(m(activity), MetaSignal::HIGH, ms(cycle as u64 * 10)))
+            .collect();
+        assert_eq!(shaped, [0.0, 0.7, 0.5, 0.0, 0.0]);
+
+        // The rate follows the time between the updates, not a nominal cycle time.
+        let mut rate = ActivityShaping::new().with_rise_rate(2.0).with_fall_rate(5.0);
+        assert_eq!(rate.shape(MetaSignal::HIGH, MetaSignal::HIGH, ms(0)), MetaSignal::LOW);
+        assert_eq!(rate.shape(MetaSignal::HIGH, MetaSignal::HIGH, ms(125)), m(0.25));
+        assert_eq!(rate.shape(MetaSignal::HIGH, MetaSignal::HIGH, ms(375)), m(0.75));
+        assert_eq!(rate.shape(MetaSignal::LOW, MetaSignal::HIGH, ms(475)), m(0.25));
+        // Inhibition is not delayed.
+        assert_eq!(rate.shape(MetaSignal::HIGH, MetaSignal::LOW, ms(500)), MetaSignal::LOW);
+
+        let mut on_time = ActivityShaping::new().with_min_on_time(Duration::from_millis(100));
+        assert_eq!(on_time.shape(m(0.5), MetaSignal::HIGH, ms(0)), m(0.5));
+        assert_eq!(on_time.shape(MetaSignal::LOW, MetaSignal::HIGH, ms(50)), m(0.5));
+        assert_eq!(on_time.shape(MetaSignal::LOW, MetaSignal::HIGH, ms(100)), MetaSignal::LOW);
+        assert_eq!(on_time.shape(MetaSignal::LOW, MetaSignal::LOW, ms(110)), MetaSignal::LOW);
+    }
+}
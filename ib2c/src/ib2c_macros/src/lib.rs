@@ -2,7 +2,9 @@ use proc_macro::TokenStream;
 use quote::quote;
 use syn::{parse_macro_input, DeriveInput, Data, Fields};
 
-#[proc_macro_derive(IB2CMetaSignals)]
+/// Implements `IB2CMetaSignals` with the fields named like its methods, or by delegating
+/// to a field marked `#[meta_signals]` that implements it.
+#[proc_macro_derive(IB2CMetaSignals, attributes(meta_signals))]
 pub fn derive_ib2c_meta_signals(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
@@ -12,42 +14,62 @@ pub fn derive_ib2c_meta_signals(input: TokenStream) -> TokenStream {
 
     let mut methods = Vec::new();
 
-    if let Data::Struct(data) = &input.data {
-        if let Fields::Named(fields) = &data.fields {
-            for field in &fields.named {
-                if let Some(field_ident) = &field.ident {
-                    let name = field_ident.to_string();
-                    match name.as_str() {
-                        "stimulation" => {
-                            methods.push(quote! {
-                                fn stimulation(&mut self) -> &mut ReceivePort<MetaSignal> {
-                                    &mut self.stimulation
-                                }
-                            });
-                        }
-                        "inhibition" => {
-                            methods.push(quote! {
-                                fn inhibition(&mut self) -> &mut ReceivePort<MetaSignal> {
-                                    &mut self.inhibition
-                                }
-                            });
-                        }
-                        "activity" => {
-                            methods.push(quote! {
-                                fn activity(&mut self) -> &mut SendPort<MetaSignal> {
-                                    &mut self.activity
-                                }
-                            });
-                        }
-                        "target_rating" => {
-                            methods.push(quote! {
-                                fn target_rating(&mut self) -> &mut SendPort<MetaSignal> {
-                                    &mut self.target_rating
-                                }
-                            });
-                        }
-                        _ => {}
+    if let Data::Struct(data) = &input.data
+        && let Fields::Named(fields) = &data.fields
+    {
+        for field in &fields.named {
+            if let Some(field_ident) = &field.ident
+                && field.attrs.iter().any(|attr| attr.path().is_ident("meta_signals"))
+            {
+                methods = delegated_meta_signals(field_ident);
+                break;
+            }
+            if let Some(field_ident) = &field.ident {
+                let name = field_ident.to_string();
+                match name.as_str() {
+                    "stimulations" => {
+                        methods.push(quote! {
+                            fn stimulations(&mut self) -> &mut Vec<ReceivePort<MetaSignal>> {
+                                &mut self.stimulations
+                            }
+                        });
+                    }
+                    "inhibitions" => {
+                        methods.push(quote! {
+                            fn inhibitions(&mut self) -> &mut Vec<ReceivePort<MetaSignal>> {
+                                &mut self.inhibitions
+                            }
+                        });
+                    }
+                    "stimulation_combination" => {
+                        methods.push(quote! {
+                            fn stimulation_combination(&mut self) -> &mut StimulationCombination {
+                                &mut self.stimulation_combination
+                            }
+                        });
+                    }
+                    "activation" => {
+                        methods.push(quote! {
+                            fn activation(&mut self) -> &mut Activation {
+                                &mut self.activation
+                            }
+                        });
+                    }
+                    "activity" => {
+                        methods.push(quote! {
+                            fn activity(&mut self) -> &mut SendPort<MetaSignal> {
+                                &mut self.activity
+                            }
+                        });
                     }
+                    "target_rating" => {
+                        methods.push(quote! {
+                            fn target_rating(&mut self) -> &mut SendPort<MetaSignal> {
+                                &mut self.target_rating
+                            }
+                        });
+                    }
+                    _ => {}
                 }
             }
         }
@@ -62,4 +84,130 @@ pub fn derive_ib2c_meta_signals(input: TokenStream) -> TokenStream {
     };
 
     expanded.into()
-}
\ No newline at end of file
+}
+
+/// The methods of `IB2CMetaSignals` forwarded to the field `field`.
+fn delegated_meta_signals(field: &syn::Ident) -> Vec<proc_macro2::TokenStream> {
+    vec![
+        quote! {
+            fn stimulations(&mut self) -> &mut Vec<ReceivePort<MetaSignal>> {
+                IB2CMetaSignals::stimulations(&mut self.#field)
+            }
+        },
+        quote! {
+            fn inhibitions(&mut self) -> &mut Vec<ReceivePort<MetaSignal>> {
+                IB2CMetaSignals::inhibitions(&mut self.#field)
+            }
+        },
+        quote! {
+            fn stimulation_combination(&mut self) -> &mut StimulationCombination {
+                IB2CMetaSignals::stimulation_combination(&mut self.#field)
+            }
+        },
+        quote! {
+            fn activation(&mut self) -> &mut Activation {
+                IB2CMetaSignals::activation(&mut self.#field)
+            }
+        },
+        quote! {
+            fn activity(&mut self) -> &mut SendPort<MetaSignal> {
+                IB2CMetaSignals::activity(&mut self.#field)
+            }
+        },
+        quote! {
+            fn target_rating(&mut self) -> &mut SendPort<MetaSignal> {
+                IB2CMetaSignals::target_rating(&mut self.#field)
+            }
+        },
+    ]
+}
+
+/// Returns the data type if `ty` is a `SendPort<T>`.
+fn send_port_data(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "SendPort" {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => match args.args.first()? {
+            syn::GenericArgument::Type(data) => Some(data),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Implements `FusionChannels` for a struct of `SendPort`s and generates the inputs of a
+/// `ChannelFusion`, a struct named like the channels with an `Inputs` suffix holding one
+/// `Vec<ReceivePort<T>>` per channel. Channels are blended with the fusion weights,
+/// channels marked `#[select]` take the value of the input with the highest weight.
+#[proc_macro_derive(FusionChannels, attributes(select))]
+pub fn derive_fusion_channels(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let ident = &input.ident;
+    let vis = &input.vis;
+    let inputs_ident = quote::format_ident!("{}Inputs", ident);
+    if !input.generics.params.is_empty() {
+        return syn::Error::new_spanned(&input.generics, "FusionChannels can not be derived for generic structs")
+            .to_compile_error().into();
+    }
+    let Data::Struct(data) = &input.data else {
+        return syn::Error::new_spanned(ident, "FusionChannels can only be derived for structs").to_compile_error().into();
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return syn::Error::new_spanned(ident, "FusionChannels requires named fields").to_compile_error().into();
+    };
+
+    let mut input_fields = Vec::new();
+    let mut connections = Vec::new();
+    let mut fusions = Vec::new();
+    for field in &fields.named {
+        let name = field.ident.as_ref().unwrap();
+        let Some(data) = send_port_data(&field.ty) else {
+            return syn::Error::new_spanned(&field.ty, "channels must be `SendPort`s").to_compile_error().into();
+        };
+        let fuse = if field.attrs.iter().any(|attr| attr.path().is_ident("select")) {
+            quote!(::ib2c::modules::channel_fusion::select)
+        } else {
+            quote!(::ib2c::modules::channel_fusion::blend)
+        };
+        input_fields.push(quote! {
+            pub #name: Vec<::ports::prelude::ReceivePort<#data>>
+        });
+        connections.push(quote! {
+            let port = ::ports::prelude::ReceivePort::default();
+            port.connect_from(&source.#name).expect("a new input is neither connected nor read from");
+            inputs.#name.push(port);
+        });
+        fusions.push(quote! {
+            if let Some(data) = #fuse(inputs.#name.iter().map(|port| port.get_data()), weights) {
+                self.#name.send(data);
+            }
+        });
+    }
+
+    let expanded = quote! {
+        /// Inputs of a `ChannelFusion`, generated by the `FusionChannels` derive.
+        #[derive(::ports::prelude::PortMethods, Default)]
+        #vis struct #inputs_ident {
+            #(#input_fields,)*
+        }
+
+        impl ::ib2c::modules::channel_fusion::FusionChannels for #ident {
+            type Inputs = #inputs_ident;
+
+            fn add_inputs(inputs: &mut Self::Inputs, source: &Self) {
+                #(#connections)*
+            }
+
+            fn fuse(&mut self, inputs: &Self::Inputs, weights: &::ib2c::modules::channel_fusion::FusionWeights) {
+                #(#fusions)*
+            }
+        }
+    };
+
+    expanded.into()
+}
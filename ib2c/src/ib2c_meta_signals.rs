@@ -1,9 +1,185 @@
+use std::cmp::min;
+use std::fmt;
+use std::sync::Arc;
 use meta_signals::MetaSignal;
-use ports::prelude::{ReceivePort, SendPort};
+use ports::prelude::{PortSource, ReceivePort, SendPort};
+use scheduling::{Module, ModuleBuilder};
 
-pub trait IB2CMetaSignals<> {
-    fn stimulation(&mut self) -> &mut ReceivePort<MetaSignal>;
-    fn inhibition(&mut self) -> &mut ReceivePort<MetaSignal>;
+/// How the stimulations of a behavior are combined, inhibitions are always combined by maximum.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum StimulationCombination {
+    /// Stimulated as much as the strongest stimulation, i.e. by any of them.
+    #[default]
+    Maximum,
+    /// Stimulated as much as the weakest stimulation, i.e. only by all of them.
+    Minimum,
+    /// Product of the stimulations, weakening the behavior with every partial stimulation.
+    Product,
+}
+
+impl StimulationCombination {
+    /// Combines the stimulations, HIGH if there are none.
+    pub fn combine(&self, stimulations: &[ReceivePort<MetaSignal>]) -> MetaSignal {
+        let values = stimulations.iter().map(|port| *port.get_data());
+        match self {
+            StimulationCombination::Maximum => values.max(),
+            StimulationCombination::Minimum => values.min(),
+            StimulationCombination::Product => values.reduce(|a, b| a * b),
+        }.unwrap_or(MetaSignal::HIGH)
+    }
+}
+
+/// Meta signal ports of IB2C modules. A module may be stimulated and inhibited by any number of
+/// other modules, it is not stimulated or inhibited if nothing is connected.
+pub trait IB2CMetaSignals {
+    fn stimulations(&mut self) -> &mut Vec<ReceivePort<MetaSignal>>;
+    fn inhibitions(&mut self) -> &mut Vec<ReceivePort<MetaSignal>>;
+    fn stimulation_combination(&mut self) -> &mut StimulationCombination;
+    fn activation(&mut self) -> &mut Activation;
     fn activity(&mut self) -> &mut SendPort<MetaSignal>;
     fn target_rating(&mut self) -> &mut SendPort<MetaSignal>;
-}
\ No newline at end of file
+
+    /// Adds a stimulation from `source`, e.g. the activity of another behavior.
+    fn stimulate_by(&mut self, source: &impl PortSource<MetaSignal>) {
+        self.stimulations().push(input_from(MetaSignal::HIGH, source));
+    }
+
+    /// Adds an inhibition from `source`, e.g. the activity of a behavior with a higher priority.
+    fn inhibit_by(&mut self, source: &impl PortSource<MetaSignal>) {
+        self.inhibitions().push(input_from(MetaSignal::LOW, source));
+    }
+
+    /// Sets how the stimulations are combined, [`StimulationCombination::Maximum`] by default.
+    fn set_stimulation_combination(&mut self, combination: StimulationCombination) {
+        *self.stimulation_combination() = combination;
+    }
+
+    /// Sets how the activity is computed, [`Activation::Minimum`] by default.
+    fn set_activation(&mut self, activation: Activation) {
+        *self.activation() = activation;
+    }
+}
+
+/// Lets modules be wired while wrapped in their builder, e.g. `builder.inhibit_by(&other.activity)`.
+impl<M: IB2CMetaSignals + Module + Send + 'static> IB2CMetaSignals for ModuleBuilder<M> {
+    fn stimulations(&mut self) -> &mut Vec<ReceivePort<MetaSignal>> {
+        (**self).stimulations()
+    }
+
+    fn inhibitions(&mut self) -> &mut Vec<ReceivePort<MetaSignal>> {
+        (**self).inhibitions()
+    }
+
+    fn stimulation_combination(&mut self) -> &mut StimulationCombination {
+        (**self).stimulation_combination()
+    }
+
+    fn activation(&mut self) -> &mut Activation {
+        (**self).activation()
+    }
+
+    fn activity(&mut self) -> &mut SendPort<MetaSignal> {
+        (**self).activity()
+    }
+
+    fn target_rating(&mut self) -> &mut SendPort<MetaSignal> {
+        (**self).target_rating()
+    }
+}
+
+/// A new input with `data` connected to `source`, e.g. to the activity of a behavior.
+pub(crate) fn input_from<T>(data: T, source: &impl PortSource<T>) -> ReceivePort<T> {
+    let port = ReceivePort::new(data);
+    port.connect_from(source).expect("a new input is neither connected nor read from");
+    port
+}
+
+/// Computes a custom activity from stimulation, inhibition and target rating, see [`Activation::custom`].
+type ActivationFn = Arc<dyn Fn(MetaSignal, MetaSignal, MetaSignal) -> MetaSignal + Send + Sync>;
+
+/// How the activity of an IB2C module follows from its combined stimulation `s`,
+/// combined inhibition `i` and target rating `r`.
+#[derive(Clone, Default)]
+pub enum Activation {
+    /// `min(s, HIGH - i, r)`.
+    #[default]
+    Minimum,
+    /// `s · (HIGH - i) · r`.
+    Product,
+    /// Like [`Activation::Minimum`], but LOW while below the threshold.
+    Threshold(MetaSignal),
+    /// Computed by a function of `(s, i, r)`.
+    Custom(ActivationFn),
+}
+
+impl Activation {
+    pub fn custom(activation: impl Fn(MetaSignal, MetaSignal, MetaSignal) -> MetaSignal + Send + Sync + 'static) -> Self {
+        Activation::Custom(Arc::new(activation))
+    }
+
+    pub fn activity(&self, stimulation: MetaSignal, inhibition: MetaSignal, target_rating: MetaSignal) -> MetaSignal {
+        match self {
+            Activation::Minimum => min(min(stimulation, MetaSignal::HIGH - inhibition), target_rating),
+            Activation::Product => stimulation * (MetaSignal::HIGH - inhibition) * target_rating,
+            Activation::Threshold(threshold) => {
+                let activity = Activation::Minimum.activity(stimulation, inhibition, target_rating);
+                if activity < *threshold { MetaSignal::LOW } else { activity }
+            }
+            Activation::Custom(activation) => activation(stimulation, inhibition, target_rating),
+        }
+    }
+}
+
+impl fmt::Debug for Activation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Activation::Minimum => write!(f, "Minimum"),
+            Activation::Product => write!(f, "Product"),
+            Activation::Threshold(threshold) => f.debug_tuple("Threshold").field(threshold).finish(),
+            Activation::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+/// The activity of a module with the given meta signal inputs and target rating.
+/// Inhibitions are combined by maximum.
+pub(crate) fn activity(
+    stimulations: &[ReceivePort<MetaSignal>],
+    inhibitions: &[ReceivePort<MetaSignal>],
+    combination: StimulationCombination,
+    activation: &Activation,
+    target_rating: MetaSignal,
+) -> MetaSignal {
+    let stimulation = combination.combine(stimulations);
+    let inhibition = inhibitions.iter().map(|port| *port.get_data()).max().unwrap_or(MetaSignal::LOW);
+    activation.activity(stimulation, inhibition, target_rating)
+}
+
+#[cfg(test)]
+mod tests {
+    use meta_signals::MetaSignal;
+    use ports::prelude::{ReceivePort, SendPort};
+    use super::{activity, Activation, StimulationCombination};
+
+    #[test]
+    fn activation_functions() {
+        let [s, i, r] = [0.5, 0.25, 0.75].map(MetaSignal::new);
+        assert_eq!(Activation::Minimum.activity(s, i, r), MetaSignal::new(0.5));
+        assert_eq!(Activation::Product.activity(s, i, r), MetaSignal::new(0.5 * 0.75 * 0.75));
+        assert_eq!(Activation::Threshold(MetaSignal::new(0.5)).activity(s, i, r), MetaSignal::new(0.5));
+        assert_eq!(Activation::Threshold(MetaSignal::new(0.6)).activity(s, i, r), MetaSignal::LOW);
+        let custom = Activation::custom(|s, _, r| s * r);
+        assert_eq!(custom.activity(s, i, r), MetaSignal::new(0.375));
+
+        // Inhibitions are combined before the activation function is applied.
+        let inhibitions: Vec<_> = [0.25, 0.5].into_iter().map(|value| {
+            let source = SendPort::new(MetaSignal::new(value));
+            let mut port = ReceivePort::new(MetaSignal::LOW);
+            port.connect_from(&source).unwrap();
+            port.update();
+            port
+        }).collect();
+        assert_eq!(activity(&[], &inhibitions, StimulationCombination::Maximum, &Activation::Product, r),
+            MetaSignal::new(0.375));
+    }
+}
@@ -1,2 +1,9 @@
+// Lets the derive macros refer to `::ib2c` inside this crate.
+extern crate self as ib2c;
+
 pub mod modules;
-mod ib2c_meta_signals;
\ No newline at end of file
+mod ib2c_meta_signals;
+mod activity_shaping;
+
+pub use activity_shaping::ActivityShaping;
+pub use ib2c_meta_signals::{Activation, IB2CMetaSignals, StimulationCombination};
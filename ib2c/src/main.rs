@@ -5,22 +5,22 @@ use meta_signals::MetaSignal;
 use ib2c::modules;
 use ib2c::modules::basic_group::BasicGroupTrait;
 use ib2c::modules::basic_module::{BasicModule, BasicModuleTrait};
-use ib2c::modules::behavior_group::{BehaviorGroup, BehaviorGroupTrait};
+use ib2c::modules::behavior_group::{BehaviorGroup, BehaviorGroupTrait, GroupMetaSignals};
 use ib2c::modules::behavior_module::BehaviorModule;
-use ib2c::modules::general_fusion::GeneralFusionTrait;
+use ib2c::modules::general_fusion::{FusionInhibition, GeneralFusionTrait};
 use ib2c::modules::maximum_fusion::MaximumFusion;
-use scheduling::{spawns, GroupBuilder, ModuleBuilder, SpawnMode};
+use scheduling::{connect, spawns, GroupBuilder, ModuleBuilder, SpawnMode};
 use modules::behavior_module::BehaviorModuleTrait;
 use ports::prelude::*;
 
 fn main() {
     let group = GroupBuilder::new(TestGroup::new(), SpawnMode::NewThread);
-    group.spawn();
+    let _runtime = group.spawn();
 
     loop { park() }
 }
 
-#[derive(Default)]
+#[derive(PortMethods, Default)]
 struct TestGroup {
     pub out_data: SendPort<i32>,
 }
@@ -28,12 +28,12 @@ struct TestGroup {
 impl BasicGroupTrait for TestGroup {
     #[spawns]
     fn init(&mut self, builder: &mut GroupBuilder) {
-        let module_1 = ModuleBuilder::new(
+        let mut module_1 = ModuleBuilder::new(
             TestModule::new(),
             Duration::from_millis(700),
             SpawnMode::GroupThread
         );
-        let module_2 = ModuleBuilder::new(
+        let mut module_2 = ModuleBuilder::new(
             Oscillator::new(),
             Duration::from_millis(500),
             SpawnMode::GroupThread
@@ -44,37 +44,40 @@ impl BasicGroupTrait for TestGroup {
             Duration::from_millis(10),
             SpawnMode::GroupThread
         );
-        maximum_fusion.add_module(&module_2.out_data, &module_2.activity);
-        maximum_fusion.add_module(&module_1.out_data, &module_1.activity);
+        maximum_fusion.add_behavior(&mut module_2, |behavior| &behavior.out_data, FusionInhibition::Own);
+        maximum_fusion.add_behavior(&mut module_1, |behavior| &behavior.out_data, FusionInhibition::Own);
 
         let print_module = ModuleBuilder::new(
             PrintModule::new(),
             Duration::from_millis(300),
             SpawnMode::GroupThread
         );
-        print_module.in_data.connect_to_source(&maximum_fusion.output_port);
+        connect!(builder, maximum_fusion.output_port => print_module.in_data).unwrap();
+        builder.export(&maximum_fusion.output_port, &self.out_data).unwrap();
 
-        let expensive_modules = GroupBuilder::new(
+        let _expensive_modules = GroupBuilder::new(
             TenModulesGroup::new(),
-            SpawnMode::NewThread
-        );
+            SpawnMode::ThreadPool
+        ).with_cycle_time(Duration::from_millis(100));
+    }
+
+    fn interface(&self) -> Vec<PortEntry> {
+        self.ports()
     }
 }
 
 #[derive(Default)]
+#[allow(dead_code)]
 struct TenModulesGroup {
     pub in_data: ReceivePort<i32>,
 }
 
 impl BehaviorGroupTrait for TenModulesGroup {
-    #[spawns]
-    fn init(group: &mut BehaviorGroup<Self>, builder: &mut GroupBuilder) {
+    fn init(group: &mut BehaviorGroup<Self>, _builder: &mut GroupBuilder) {
+        // The activity of the group is the maximum activity of its behaviors.
+        group.set_meta_signals(GroupMetaSignals::Maximum);
         for _ in 0..10 {
-            let module = ModuleBuilder::new(
-                FibModule::new(),
-                Duration::from_millis(100),
-                SpawnMode::GroupThread
-            );
+            group.add_behavior(ModuleBuilder::inherit(FibModule::new()));
         }
     }
 }
@@ -136,7 +139,7 @@ struct PrintModule<T> {
     pub in_data: ReceivePort<T>
 }
 
-impl<T: Debug + Default> BasicModuleTrait for PrintModule<T> {
+impl<T: Debug + Default + Send + Sync + 'static> BasicModuleTrait for PrintModule<T> {
     fn update(module: &mut BasicModule<Self>) {
         let data = module.in_data.get_data();
         let timestamp = module.in_data.get_timestamp();
@@ -151,7 +154,7 @@ struct FibModule {
     param: u64,
 }
 
-impl BasicModuleTrait for FibModule {
+impl BehaviorModuleTrait for FibModule {
     fn init() -> Self {
         FibModule {
             param: 42,
@@ -159,12 +162,16 @@ impl BasicModuleTrait for FibModule {
         }
     }
 
-    fn update(module: &mut BasicModule<Self>) {
+    fn transfer(module: &mut BehaviorModule<Self>) {
         // module.param += 1;
         let fib = FibModule::fib(module.param);
         module.out_result.send(fib);
         println!("Calculated Fib");
     }
+
+    fn target_rating(_module: &BehaviorModule<Self>) -> MetaSignal {
+        MetaSignal::HIGH
+    }
 }
 
 impl FibModule {
@@ -1,8 +1,14 @@
 use derive_more::{Deref, DerefMut};
-use scheduling::{Group, GroupBuilder};
+use ports::prelude::PortEntry;
+use scheduling::{short_type_name, Group, GroupBuilder};
 
 pub trait BasicGroupTrait: Default {
     fn init(&mut self, builder: &mut GroupBuilder) where Self: Sized;
+
+    /// Input and output ports of the group, see [`scheduling::Group::interface`].
+    fn interface(&self) -> Vec<PortEntry> {
+        Vec::new()
+    }
     
     fn new() -> BasicGroup<Self> where Self: Sized {
         BasicGroup {
@@ -21,4 +27,12 @@ impl<G: BasicGroupTrait> Group for BasicGroup<G> {
         println!("Initializing BasicGroup");
         self.inner.init(builder);
     }
+
+    fn interface(&self) -> Vec<PortEntry> {
+        self.inner.interface()
+    }
+
+    fn default_name() -> String {
+        short_type_name::<G>()
+    }
 }
@@ -1,17 +1,16 @@
-use std::time::Duration;
 use derive_more::{Deref, DerefMut};
-use scheduling::Module;
-use ports::prelude::PortMethods;
+use scheduling::{short_type_name, Module, TimerQueue, Timers};
+use ports::prelude::{PortEntry, PortMethods, PortReflection};
 
 /// A basic scheduling, the update method will be called periodically.
-pub trait BasicModuleTrait: PortMethods + Default {
+pub trait BasicModuleTrait: PortMethods + PortReflection + Default {
     /// Initialize the basic scheduling (optional).
     fn init() -> Self where Self: Sized {
         Self::default()
     }
 
     /// Called periodically. Use this to update internal state
-    /// and read from or write to ports.
+    /// and read from or write to ports. Due [`BasicModule::timers`] fire right before.
     fn update(module: &mut BasicModule<Self>);
 
     /// Create a new basic module. Should be wrapped in a [`scheduling::ModuleBuilder::new`] to be added to a ThreadContainer.
@@ -24,7 +23,11 @@ pub trait BasicModuleTrait: PortMethods + Default {
 /// Used by [`BasicModuleTrait`] to create a basic scheduling.
 #[derive(Deref, DerefMut)]
 pub struct BasicModule<M: BasicModuleTrait> {
+    #[deref] #[deref_mut]
     inner: M,
+
+    /// One-shot and periodic timers, fired by the container of the module at their deadlines.
+    pub timers: Timers<BasicModule<M>>,
 }
 
 impl<M: BasicModuleTrait> Module for BasicModule<M> {
@@ -32,12 +35,35 @@ impl<M: BasicModuleTrait> Module for BasicModule<M> {
         self.inner.update_ports();
         M::update(self);
     }
+
+    fn timers(&mut self) -> Option<&mut dyn TimerQueue> {
+        Some(&mut self.timers)
+    }
+
+    fn fire_timers(&mut self) {
+        Timers::fire(self, |module| &mut module.timers);
+    }
+
+    fn default_name() -> String {
+        short_type_name::<M>()
+    }
+
+    fn interface(&self) -> Vec<PortEntry> {
+        self.inner.ports()
+    }
+}
+
+impl<M: BasicModuleTrait> PortReflection for BasicModule<M> {
+    fn ports(&self) -> Vec<PortEntry> {
+        self.inner.ports()
+    }
 }
 
 impl<M: BasicModuleTrait> BasicModule<M> {
     fn new(inner: M) -> Self {
         BasicModule {
             inner,
+            timers: Timers::new(),
         }
     }
 }
\ No newline at end of file
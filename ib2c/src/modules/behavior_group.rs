@@ -1,32 +1,104 @@
+use std::cmp::min;
 use derive_more::{Deref, DerefMut};
 use ib2c_macros::IB2CMetaSignals;
 use meta_signals::MetaSignal;
-use ports::prelude::{ReceivePort, SendPort};
-use scheduling::{Group, GroupBuilder};
-use crate::ib2c_meta_signals::IB2CMetaSignals;
+use ports::port_reflection::probe::{Probe, ViaDeserialize, ViaSerialize};
+use ports::prelude::*;
+use scheduling::{short_type_name, Group, GroupBuilder, Module, ModuleBuilder};
+use crate::ib2c_meta_signals::{activity, Activation, IB2CMetaSignals, StimulationCombination};
+use crate::modules::coordination::Members;
 
 pub trait BehaviorGroupTrait: Default {
     fn init(group: &mut BehaviorGroup<Self>, builder: &mut GroupBuilder);
 
+    /// Input and output ports of the group besides its meta signals, see [`scheduling::Group::interface`].
+    fn interface(&self) -> Vec<PortEntry> {
+        Vec::new()
+    }
+
     fn new() -> BehaviorGroup<Self> {
         BehaviorGroup::new(Self::default())
     }
 }
 
+/// How a [`BehaviorGroup`] computes its activity and target rating.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum GroupMetaSignals {
+    /// Passed through from the module set with [`BehaviorGroup::set_characteristic_module`].
+    #[default]
+    Characteristic,
+    /// Maximum activity and target rating of the behaviors added with [`BehaviorGroup::add_behavior`].
+    Maximum,
+    /// Average activity and target rating of the behaviors added with [`BehaviorGroup::add_behavior`].
+    Average,
+}
+
+/// A group with the meta signals of a behavior. The activity and target rating of the group are
+/// taken from a characteristic module or derived from its behaviors, see [`GroupMetaSignals`].
+/// Spawning fails with [`scheduling::SpawnError::UnconnectedPort`] if neither is set up,
+/// and with [`scheduling::SpawnError::InvalidGroup`] if both are.
+///
+/// Stimulations and inhibitions must be added before the group is passed to [`GroupBuilder::new`].
+/// They only gate the characteristic module and the behaviors added with [`BehaviorGroup::add_behavior`],
+/// not modules added to the [`GroupBuilder`] directly.
 #[derive(Deref, DerefMut, IB2CMetaSignals)]
 pub struct BehaviorGroup<G: BehaviorGroupTrait> {
     #[deref] #[deref_mut]
     inner: G,
-    pub stimulation: ReceivePort<MetaSignal>,
-    pub inhibition: ReceivePort<MetaSignal>,
+    /// Gate the behaviors added with [`BehaviorGroup::add_behavior`] and are forwarded to the characteristic module.
+    pub stimulations: Vec<ReceivePort<MetaSignal>>,
+    /// Gate the behaviors added with [`BehaviorGroup::add_behavior`] and are forwarded to the characteristic module.
+    pub inhibitions: Vec<ReceivePort<MetaSignal>>,
+    pub stimulation_combination: StimulationCombination,
+    pub activation: Activation,
     pub activity: SendPort<MetaSignal>,
     pub target_rating: SendPort<MetaSignal>,
+
+    meta_signals: GroupMetaSignals,
+    behaviors: Members,
+    gate: GroupGate,
 }
 
 impl<G: BehaviorGroupTrait> Group for BehaviorGroup<G> {
     fn init(&mut self, builder: &mut GroupBuilder) {
         println!("Initializing BasicGroup");
         G::init(self, builder);
+        if self.behaviors.activities.is_empty() && self.meta_signals == GroupMetaSignals::Characteristic {
+            return;
+        }
+
+        let mut gate = std::mem::take(&mut self.gate);
+        gate.stimulations = self.stimulations.clone();
+        gate.inhibitions = self.inhibitions.clone();
+        gate.stimulation_combination = self.stimulation_combination;
+        gate.activation = self.activation.clone();
+        gate.activities = self.behaviors.activities.clone();
+        gate.target_ratings = self.behaviors.target_ratings.clone();
+        gate.meta_signals = self.meta_signals;
+        if self.meta_signals != GroupMetaSignals::Characteristic {
+            let forwarded = self.activity.forward_from(&gate.activity)
+                .and_then(|_| self.target_rating.forward_from(&gate.target_rating));
+            if forwarded.is_err() {
+                builder.reject("derived meta signals can not be combined with a characteristic module");
+            }
+        }
+        self.behaviors.init(builder);
+        let gate = match self.behaviors.cycle_time {
+            Some(cycle_time) => ModuleBuilder::inherit(gate).with_cycle_time(cycle_time),
+            None => ModuleBuilder::inherit(gate),
+        };
+        builder.add_module(gate.with_name("MetaSignals"));
+    }
+
+    fn interface(&self) -> Vec<PortEntry> {
+        let mut interface = self.inner.interface();
+        interface.push(meta_signal_entry("activity", &self.activity));
+        interface.push(meta_signal_entry("target_rating", &self.target_rating));
+        interface
+    }
+
+    fn default_name() -> String {
+        short_type_name::<G>()
     }
 }
 
@@ -34,32 +106,209 @@ impl<G: BehaviorGroupTrait> BehaviorGroup<G> {
     pub fn new(inner: G) -> Self {
         BehaviorGroup {
             inner,
-            stimulation: ReceivePort::new(MetaSignal::HIGH),
-            inhibition: ReceivePort::new(MetaSignal::LOW),
+            stimulations: Vec::new(),
+            inhibitions: Vec::new(),
+            stimulation_combination: StimulationCombination::default(),
+            activation: Activation::default(),
             activity: SendPort::new(MetaSignal::LOW),
             target_rating: SendPort::new(MetaSignal::LOW),
+            meta_signals: GroupMetaSignals::default(),
+            behaviors: Members::default(),
+            gate: GroupGate::default(),
         }
     }
 
-    pub fn set_characteristic_module<M: IB2CMetaSignals>(&mut self, module: &mut M) {
-        module.stimulation().connect_to_source(&self.stimulation);
-        module.inhibition().connect_to_source(&self.inhibition);
-        self.activity.connect_to_source(module.activity());
-        self.target_rating.connect_to_source(module.target_rating());
+    /// Passes the meta signals of the group to and from `module`, see [`GroupMetaSignals::Characteristic`].
+    /// Fails if the group already has a characteristic module.
+    pub fn set_characteristic_module<M: IB2CMetaSignals>(&mut self, module: &mut M) -> Result<(), PortError> {
+        self.activity.forward_from(module.activity())?;
+        self.target_rating.forward_from(module.target_rating())?;
+        for stimulation in &self.stimulations {
+            module.stimulate_by(stimulation);
+        }
+        for inhibition in &self.inhibitions {
+            module.inhibit_by(inhibition);
+        }
+        module.set_stimulation_combination(self.stimulation_combination);
+        module.set_activation(self.activation.clone());
+        Ok(())
     }
+
+    /// Sets how the activity and target rating of the group are computed,
+    /// [`GroupMetaSignals::Characteristic`] by default. Must be set before the group is initialized.
+    pub fn set_meta_signals(&mut self, meta_signals: GroupMetaSignals) {
+        self.meta_signals = meta_signals;
+    }
+
+    /// Adds a behavior to the group, added to the builder after [`BehaviorGroupTrait::init`].
+    /// The behavior only acts as far as the group is stimulated and not inhibited,
+    /// and its meta signals are used for the meta signals of the group, see [`GroupMetaSignals`].
+    pub fn add_behavior<M>(&mut self, mut behavior: ModuleBuilder<M>)
+    where
+        M: IB2CMetaSignals + Module + Send + 'static,
+    {
+        behavior.inhibit_by(&self.gate.inhibition);
+        self.behaviors.push(behavior);
+    }
+}
+
+/// Lists a meta signal of the group in its interface, so spawning fails if it is not connected.
+fn meta_signal_entry(name: &str, port: &SendPort<MetaSignal>) -> PortEntry {
+    let probe = &Probe::<MetaSignal>::new();
+    PortEntry::new(name, DynPort::output::<MetaSignal>(port, probe.serializer(), probe.deserializer()))
+}
+
+/// Gates the behaviors of a [`BehaviorGroup`] by the stimulations and inhibitions of the group
+/// and derives the meta signals of the group from its behaviors. Added to the group as `MetaSignals`.
+#[derive(PortMethods, Default)]
+struct GroupGate {
+    pub stimulations: Vec<ReceivePort<MetaSignal>>,
+    pub inhibitions: Vec<ReceivePort<MetaSignal>>,
+    stimulation_combination: StimulationCombination,
+    activation: Activation,
+    pub activities: Vec<ReceivePort<MetaSignal>>,
+    pub target_ratings: Vec<ReceivePort<MetaSignal>>,
+    /// Inhibits the behaviors as far as the group may not act.
+    pub inhibition: SendPort<MetaSignal>,
+    pub activity: SendPort<MetaSignal>,
+    pub target_rating: SendPort<MetaSignal>,
+    meta_signals: GroupMetaSignals,
 }
 
-// impl<G: BehaviorGroupTrait> IB2CMetaSignals for BehaviorGroup<G> {
-//     fn stimulation(&mut self) -> &mut ReceivePort<MetaSignal> {
-//         &mut self.stimulation
-//     }
-//     fn inhibition(&mut self) -> &mut ReceivePort<MetaSignal> {
-//         &mut self.inhibition
-//     }
-//     fn activity(&mut self) -> &mut SendPort<MetaSignal> {
-//         &mut self.activity
-//     }
-//     fn target_rating(&mut self) -> &mut SendPort<MetaSignal> {
-//         &mut self.target_rating
-//     }
-// }
+impl Module for GroupGate {
+    fn update(&mut self) {
+        self.update_ports();
+        let potential = activity(&self.stimulations, &self.inhibitions, self.stimulation_combination, &self.activation,
+            MetaSignal::HIGH);
+        self.inhibition.send(MetaSignal::HIGH - potential);
+
+        if self.meta_signals == GroupMetaSignals::Characteristic {
+            return;
+        }
+        let combine = |ports: &[ReceivePort<MetaSignal>]| {
+            let values = ports.iter().map(|port| *port.get_data());
+            match self.meta_signals {
+                GroupMetaSignals::Average => (!ports.is_empty())
+                    .then(|| MetaSignal::new(values.map(|value| *value).sum::<f64>() / ports.len() as f64)),
+                _ => values.max(),
+            }.unwrap_or(MetaSignal::LOW)
+        };
+        let activity = combine(&self.activities);
+        let target_rating = combine(&self.target_ratings);
+        self.activity.send(min(activity, potential));
+        self.target_rating.send(target_rating);
+    }
+
+    fn interface(&self) -> Vec<PortEntry> {
+        self.ports()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use ports::prelude::*;
+    use meta_signals::MetaSignal;
+    use scheduling::{GroupBuilder, ModuleBuilder, Runtime, SpawnError, SpawnMode};
+    use crate::IB2CMetaSignals;
+    use crate::modules::behavior_module::{BehaviorModule, BehaviorModuleTrait};
+    use super::{BehaviorGroup, BehaviorGroupTrait, GroupMetaSignals};
+
+    #[derive(PortMethods, Default)]
+    struct Constant {
+        pub rating: ParameterPort<f64>,
+    }
+
+    impl BehaviorModuleTrait for Constant {
+        fn transfer(_module: &mut BehaviorModule<Self>) {}
+
+        fn target_rating(module: &BehaviorModule<Self>) -> MetaSignal {
+            MetaSignal::new(*module.rating.get_data())
+        }
+    }
+
+    #[derive(Default)]
+    struct Pair;
+
+    impl BehaviorGroupTrait for Pair {
+        fn init(group: &mut BehaviorGroup<Self>, _builder: &mut GroupBuilder) {
+            group.set_meta_signals(GroupMetaSignals::Maximum);
+            for (rating, name) in [(0.25, "low"), (0.75, "high")] {
+                let mut behavior = Constant::new();
+                behavior.rating = ParameterPort::new(rating);
+                group.add_behavior(ModuleBuilder::new(behavior, Duration::from_millis(1), SpawnMode::GroupThread)
+                    .with_name(name));
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct Unrated;
+
+    impl BehaviorGroupTrait for Unrated {
+        fn init(_group: &mut BehaviorGroup<Self>, _builder: &mut GroupBuilder) {}
+    }
+
+    #[derive(Default)]
+    struct Conflicting;
+
+    impl BehaviorGroupTrait for Conflicting {
+        fn init(group: &mut BehaviorGroup<Self>, builder: &mut GroupBuilder) {
+            group.set_meta_signals(GroupMetaSignals::Maximum);
+            group.add_behavior(ModuleBuilder::inherit(Constant::new()));
+            let mut characteristic = ModuleBuilder::inherit(Constant::new());
+            group.set_characteristic_module(&mut characteristic).unwrap();
+            builder.add_module(characteristic);
+        }
+    }
+
+    /// Activities of the group and of its behaviors and the target rating of the group.
+    fn meta_signals(runtime: &Runtime, activity: &mut ReceivePort<MetaSignal>, target_rating: &mut ReceivePort<MetaSignal>) -> Vec<String> {
+        activity.update();
+        target_rating.update();
+        let snapshot = runtime.snapshot();
+        let mut signals = vec![activity.get_data().to_string(), target_rating.get_data().to_string()];
+        signals.extend(["low", "high"].map(|name| {
+            snapshot.module(&format!("pair/{name}")).unwrap().port("activity").unwrap().value.clone().unwrap()
+        }));
+        signals
+    }
+
+    #[test]
+    fn derived_meta_signals() {
+        let mut stop = SendPort::new(MetaSignal::LOW);
+        let mut group = Pair::new();
+        group.inhibit_by(&stop);
+        let group = GroupBuilder::new(group, SpawnMode::NewThread).with_name("pair");
+        let mut activity = ReceivePort::new(MetaSignal::LOW);
+        activity.connect_from(&group.activity).unwrap();
+        let mut target_rating = ReceivePort::new(MetaSignal::LOW);
+        target_rating.connect_from(&group.target_rating).unwrap();
+        let mut builder = GroupBuilder::empty();
+        builder.add_group(group);
+        let runtime = builder.spawn();
+
+        for expected in [["0.75", "0.75", "0.25", "0.75"], ["0", "0.75", "0", "0"]] {
+            let start = Instant::now();
+            while meta_signals(&runtime, &mut activity, &mut target_rating) != expected {
+                assert!(start.elapsed() < Duration::from_secs(2), "{:?}",
+                    meta_signals(&runtime, &mut activity, &mut target_rating));
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            // Inhibiting the group inhibits all of its behaviors.
+            stop.send(MetaSignal::HIGH);
+        }
+        runtime.shutdown();
+
+        let unrated: GroupBuilder = GroupBuilder::new(Unrated::new(), SpawnMode::NewThread).into();
+        let error = unrated.try_spawn().err().unwrap();
+        assert_eq!(error, SpawnError::UnconnectedPort { group: "Unrated".to_string(), port: "activity".to_string() });
+
+        let conflicting: GroupBuilder = GroupBuilder::new(Conflicting::new(), SpawnMode::NewThread).into();
+        let error = conflicting.with_cycle_time(Duration::from_millis(1)).try_spawn().err().unwrap();
+        assert_eq!(error, SpawnError::InvalidGroup {
+            group: "Conflicting".to_string(),
+            reason: "derived meta signals can not be combined with a characteristic module".to_string(),
+        });
+    }
+}
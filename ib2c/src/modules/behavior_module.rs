@@ -1,30 +1,54 @@
-use std::cmp::min;
 use derive_more::{Deref, DerefMut};
 use ib2c_macros::IB2CMetaSignals;
-use scheduling::Module;
+use std::sync::Arc;
+use scheduling::{short_type_name, Clock, Module, TimerQueue, Timers};
 use ports::prelude::*;
 use meta_signals::MetaSignal;
-use crate::ib2c_meta_signals::IB2CMetaSignals;
+use crate::activity_shaping::ActivityShaping;
+use crate::ib2c_meta_signals::{activity, Activation, IB2CMetaSignals, StimulationCombination};
 
 /// An IB2C behavior scheduling with stimulation, inhibition, activity and target_rating ports.
 /// The transfer and target_rating functions are called periodically. Transfer will always be called before target_rating.
 /// transfer is used to update the internal state while target_rating expresses how much the behavior wants to be active.
 /// The activity of the behavior is calculated using stimulation, inhibition and target_rating.
-/// The activity is the minimum op potential and target_rating. Where potential is the minimum of stimulation
-/// and (HIGH - inhibition).
-pub trait BehaviorModuleTrait: PortMethods + Default {
+/// By default, the activity is the minimum op potential and target_rating. Where potential is the minimum of stimulation
+/// and (HIGH - inhibition), see [`IB2CMetaSignals::set_activation`] for other activation functions.
+/// Multiple inhibitions are combined by maximum, multiple stimulations
+/// as set by [`IB2CMetaSignals::set_stimulation_combination`].
+pub trait BehaviorModuleTrait: PortMethods + PortReflection + Default {
     /// Initialize the behavior scheduling (optional).
     fn init() -> Self where Self: Sized {
         Self::default()
     }
 
     /// Called periodically. Use this to update internal state
-    /// and read from or write to ports of your scheduling.
+    /// and read from or write to ports of your scheduling. Due [`BehaviorModule::timers`] fire right before.
     fn transfer(module: &mut BehaviorModule<Self>);
 
     /// Return the target rating of the behavior scheduling used to calculate the activity.
     fn target_rating(module: &BehaviorModule<Self>) -> MetaSignal;
 
+    /// The activity sent by the behavior before activity shaping. By default `activity`,
+    /// computed from the target rating, stimulation and inhibition.
+    fn activity(_module: &BehaviorModule<Self>, activity: MetaSignal) -> MetaSignal {
+        activity
+    }
+
+    /// Called with the clock of the container, see [`Module::set_clock`]. The timers already use it.
+    fn set_clock(_module: &mut BehaviorModule<Self>, _clock: &Arc<dyn Clock>) {}
+
+    /// Called once before the first update, see [`Module::start`].
+    fn start(_module: &mut BehaviorModule<Self>) {}
+
+    /// Called once when the behavior is removed, see [`Module::stop`].
+    fn stop(_module: &mut BehaviorModule<Self>) {}
+
+    /// Called when the behavior is paused, see [`Module::pause`].
+    fn pause(_module: &mut BehaviorModule<Self>) {}
+
+    /// Called when the behavior is resumed, see [`Module::resume`].
+    fn resume(_module: &mut BehaviorModule<Self>) {}
+
     /// Create a new behavior module. Should be wrapped in a [`scheduling::ModuleBuilder::new`] to be added to a ThreadContainer.
     fn new() -> BehaviorModule<Self> where Self: Sized {
         BehaviorModule::new(Self::init())
@@ -36,39 +60,170 @@ pub trait BehaviorModuleTrait: PortMethods + Default {
 #[derive(PortMethods, Default, Deref, DerefMut, IB2CMetaSignals)]
 pub struct BehaviorModule<M: BehaviorModuleTrait> {
     #[deref] #[deref_mut]
+    #[nested_ports]
     inner: M,
     
-    pub stimulation: ReceivePort<MetaSignal>,
-    pub inhibition: ReceivePort<MetaSignal>,
+    /// Added with [`IB2CMetaSignals::stimulate_by`], HIGH if empty.
+    pub stimulations: Vec<ReceivePort<MetaSignal>>,
+    /// Added with [`IB2CMetaSignals::inhibit_by`], LOW if empty.
+    pub inhibitions: Vec<ReceivePort<MetaSignal>>,
+    pub stimulation_combination: StimulationCombination,
+    pub activation: Activation,
     pub activity: SendPort<MetaSignal>,
     pub target_rating: SendPort<MetaSignal>,
+
+    /// One-shot and periodic timers, fired by the container of the module at their deadlines.
+    pub timers: Timers<BehaviorModule<M>>,
+    /// Applied to the activity before it is sent, see [`BehaviorModule::with_activity_shaping`].
+    pub activity_shaping: Option<ActivityShaping>,
 }
 
 impl<M: BehaviorModuleTrait> Module for BehaviorModule<M> {
     fn update(&mut self) {
-        self.inner.update_ports();
         self.update_ports();
-
         M::transfer(self);
         let target = M::target_rating(self);
-        let stimulation = *self.stimulation.get_data();
-        let inhibition = *self.inhibition.get_data();
-
-        let potential = min(stimulation, MetaSignal::HIGH - inhibition);
-        let activity = min(potential, target);
+        let potential = |target| activity(&self.stimulations, &self.inhibitions, self.stimulation_combination,
+            &self.activation, target);
+        let (activity, limit) = (M::activity(self, potential(target)), potential(MetaSignal::HIGH));
+        let now = self.timers.clock().now();
+        let activity = match &mut self.activity_shaping {
+            // Shaping may delay the activity but never exceeds what stimulation and inhibition allow.
+            Some(shaping) => shaping.shape(activity, limit, now),
+            None => activity,
+        };
         self.activity.send(activity);
         self.target_rating.send(target);
     }
+
+    fn set_clock(&mut self, clock: &Arc<dyn Clock>) {
+        self.timers.set_clock(clock.clone());
+        M::set_clock(self, clock);
+    }
+
+    fn timers(&mut self) -> Option<&mut dyn TimerQueue> {
+        Some(&mut self.timers)
+    }
+
+    fn fire_timers(&mut self) {
+        Timers::fire(self, |module| &mut module.timers);
+    }
+
+    fn start(&mut self) {
+        M::start(self);
+    }
+
+    fn stop(&mut self) {
+        M::stop(self);
+    }
+
+    fn pause(&mut self) {
+        M::pause(self);
+    }
+
+    fn resume(&mut self) {
+        // Time spent paused does not count towards rates and minimum on-time.
+        if let Some(shaping) = &mut self.activity_shaping {
+            shaping.reset_time();
+        }
+        M::resume(self);
+    }
+
+    fn default_name() -> String {
+        short_type_name::<M>()
+    }
+
+    fn interface(&self) -> Vec<PortEntry> {
+        self.ports()
+    }
 }
 
 impl<M: BehaviorModuleTrait> BehaviorModule<M> {
     fn new(inner: M) -> Self {
         BehaviorModule {
             inner,
-            stimulation: ReceivePort::new(MetaSignal::HIGH),
-            inhibition: ReceivePort::new(MetaSignal::LOW),
+            stimulations: Vec::new(),
+            inhibitions: Vec::new(),
+            stimulation_combination: StimulationCombination::default(),
+            activation: Activation::default(),
             activity: SendPort::new(MetaSignal::LOW),
             target_rating: SendPort::new(MetaSignal::LOW),
+            timers: Timers::new(),
+            activity_shaping: None,
+        }
+    }
+
+    /// Shapes the activity before it is sent, e.g. with hysteresis to stop a fusion from switching
+    /// back and forth between behaviors, see [`ActivityShaping`].
+    pub fn with_activity_shaping(mut self, shaping: ActivityShaping) -> Self {
+        self.activity_shaping = Some(shaping);
+        self
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use ports::prelude::*;
+    use scheduling::{Clock, ManualClock, Module};
+    use meta_signals::MetaSignal;
+    use crate::{ActivityShaping, IB2CMetaSignals, StimulationCombination};
+    use super::{BehaviorModule, BehaviorModuleTrait};
+
+    #[derive(PortMethods, Default)]
+    struct Drive { }
+
+    impl BehaviorModuleTrait for Drive {
+        fn transfer(_module: &mut BehaviorModule<Self>) {}
+
+        fn target_rating(_module: &BehaviorModule<Self>) -> MetaSignal {
+            MetaSignal::HIGH
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn multiple_stimulations_and_inhibitions() {
+        let mut drive = Drive::new();
+        drive.update();
+        assert_eq!(*drive.activity.get_last_data(), MetaSignal::HIGH);
+
+        let mut avoid = SendPort::new(MetaSignal::new(0.25));
+        let mut stop = SendPort::new(MetaSignal::new(0.5));
+        drive.inhibit_by(&avoid);
+        drive.inhibit_by(&stop);
+        drive.update();
+        assert_eq!(*drive.activity.get_last_data(), MetaSignal::new(0.5));
+        stop.send(MetaSignal::LOW);
+        avoid.send(MetaSignal::new(0.125));
+        drive.update();
+        assert_eq!(*drive.activity.get_last_data(), MetaSignal::new(0.875));
+
+        let mut operator = SendPort::new(MetaSignal::new(0.5));
+        let planner = SendPort::new(MetaSignal::new(0.25));
+        drive.stimulate_by(&operator);
+        drive.stimulate_by(&planner);
+        drive.update();
+        assert_eq!(*drive.activity.get_last_data(), MetaSignal::new(0.5));
+        drive.set_stimulation_combination(StimulationCombination::Minimum);
+        drive.update();
+        assert_eq!(*drive.activity.get_last_data(), MetaSignal::new(0.25));
+        drive.set_stimulation_combination(StimulationCombination::Product);
+        operator.send(MetaSignal::HIGH);
+        drive.update();
+        assert_eq!(*drive.activity.get_last_data(), MetaSignal::new(0.25));
+        assert_eq!(drive.ports().iter().map(|port| port.name.as_str()).collect::<Vec<_>>(),
+            ["stimulations[0]", "stimulations[1]", "inhibitions[0]", "inhibitions[1]", "activity", "target_rating"]);
+    }
+
+    #[test]
+    fn shaping_follows_the_container_clock() {
+        let clock = ManualClock::new();
+        let mut drive = Drive::new().with_activity_shaping(ActivityShaping::new().with_rise_rate(2.0));
+        drive.set_clock(&(Arc::new(clock.clone()) as Arc<dyn Clock>));
+        drive.update();
+        assert_eq!(*drive.activity.get_last_data(), MetaSignal::LOW);
+        clock.advance(Duration::from_millis(250));
+        drive.update();
+        assert_eq!(*drive.activity.get_last_data(), MetaSignal::new(0.5));
+    }
+}
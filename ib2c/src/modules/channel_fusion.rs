@@ -0,0 +1,212 @@
+use derive_more::{Deref, DerefMut};
+use ib2c_macros::IB2CMetaSignals;
+use scheduling::{short_type_name, Module};
+use ports::prelude::*;
+use meta_signals::MetaSignal;
+use crate::ib2c_meta_signals::{Activation, IB2CMetaSignals, StimulationCombination};
+use crate::modules::general_fusion::{FusionInhibition, FusionMetaSignals, FusionRating, WeightedData};
+
+/// Weights of the inputs of a fusion given their activities and target ratings.
+/// A blended value is `Σ wᵢ·uᵢ / normalization` over the inputs with a positive weight.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FusionWeights {
+    pub weights: Vec<f64>,
+    pub normalization: f64,
+    pub rating: FusionRating,
+}
+
+/// How a fusion weights its inputs, so all channels of a [`ChannelFusion`] are fused alike.
+pub trait FusionStrategy {
+    fn weights(activities: &[MetaSignal], target_ratings: &[MetaSignal]) -> FusionWeights;
+}
+
+/// The target ratings weighted by the activities, `Σ aᵢ·rᵢ / Σ aᵢ`, LOW if no input is active.
+pub fn weighted_target_rating(activities: &[MetaSignal], target_ratings: &[MetaSignal]) -> MetaSignal {
+    let total: f64 = activities.iter().map(|activity| **activity).sum();
+    if total <= 0.0 {
+        return MetaSignal::LOW;
+    }
+    let weighted: f64 = activities.iter().zip(target_ratings).map(|(activity, rating)| **activity * **rating).sum();
+    MetaSignal::new(weighted / total)
+}
+
+/// Blends values with the weights, `None` if no weight is positive.
+pub fn blend<'a, D: WeightedData + 'a>(values: impl Iterator<Item = &'a D>, weights: &FusionWeights) -> Option<D> {
+    let sum = values.zip(weights.weights.iter())
+        .filter(|(_, weight)| **weight > 0.0)
+        .map(|(value, weight)| value.clone() * *weight)
+        .reduce(|sum, weighted| sum + weighted)?;
+    Some(sum * (1.0 / weights.normalization))
+}
+
+/// The value with the highest positive weight, the first one if several have it.
+/// Used for channels that can not be blended, e.g. flags.
+pub fn select<'a, D: Clone + 'a>(values: impl Iterator<Item = &'a D>, weights: &FusionWeights) -> Option<D> {
+    let mut selected: Option<(&D, f64)> = None;
+    for (value, weight) in values.zip(weights.weights.iter().copied()) {
+        if weight > selected.map_or(0.0, |(_, max)| max) {
+            selected = Some((value, weight));
+        }
+    }
+    selected.map(|(value, _)| value.clone())
+}
+
+/// Output channels of a [`ChannelFusion`], a struct of `SendPort`s deriving `FusionChannels`,
+/// e.g. the linear and angular velocity and a heading flag of motion behaviors:
+///
+/// ```ignore
+/// #[derive(PortMethods, FusionChannels, Default)]
+/// pub struct Motion {
+///     pub linear: SendPort<f64>,
+///     pub angular: SendPort<f64>,
+///     #[select]
+///     pub absolute_heading: SendPort<bool>,
+/// }
+/// ```
+///
+/// Behaviors hold the channels as `#[nested_ports]` field.
+pub trait FusionChannels: PortMethods + PortReflection + Default {
+    /// One `Vec<ReceivePort<T>>` per channel, generated as `MotionInputs` for `Motion`.
+    type Inputs: PortMethods + PortReflection + Default;
+
+    /// Adds an input per channel connected to the channels of `source`.
+    fn add_inputs(inputs: &mut Self::Inputs, source: &Self);
+
+    /// Sends the fused inputs, channels without an input with a positive weight keep their last value.
+    fn fuse(&mut self, inputs: &Self::Inputs, weights: &FusionWeights);
+}
+
+/// A fusion of several data channels per behavior, weighted alike by the [`FusionStrategy`] `M`,
+/// so e.g. a maximum fusion can not pick the linear velocity of one behavior and the angular
+/// velocity of another.
+#[derive(PortMethods, Default, Deref, DerefMut, IB2CMetaSignals)]
+pub struct ChannelFusion<M, C>
+where
+    M: FusionStrategy + PortMethods + PortReflection + Default,
+    C: FusionChannels,
+{
+    #[deref] #[deref_mut]
+    #[nested_ports]
+    inner: M,
+
+    #[nested_ports]
+    #[meta_signals]
+    pub meta_signals: FusionMetaSignals,
+
+    #[nested_ports]
+    pub inputs: C::Inputs,
+    #[nested_ports]
+    pub outputs: C,
+}
+
+impl<M, C> Module for ChannelFusion<M, C>
+where
+    M: FusionStrategy + PortMethods + PortReflection + Default,
+    C: FusionChannels,
+{
+    fn update(&mut self) {
+        self.update_ports();
+
+        let (activities, target_ratings) = self.meta_signals.inputs();
+        let weights = M::weights(&activities, &target_ratings);
+        self.outputs.fuse(&self.inputs, &weights);
+        self.meta_signals.send(weights.rating);
+    }
+
+    fn default_name() -> String {
+        short_type_name::<M>()
+    }
+
+    fn interface(&self) -> Vec<PortEntry> {
+        self.ports()
+    }
+}
+
+impl<M, C> ChannelFusion<M, C>
+where
+    M: FusionStrategy + PortMethods + PortReflection + Default,
+    C: FusionChannels,
+{
+    /// Create a new channel fusion module. Should be wrapped in a [`scheduling::ModuleBuilder::new`] to be added to a ThreadContainer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a new scheduling to the fusion with its channels, rated by its activity.
+    pub fn add_module(&mut self, channels: &C, activity_port: &impl PortSource<MetaSignal>) {
+        C::add_inputs(&mut self.inputs, channels);
+        self.meta_signals.add_module(activity_port);
+    }
+
+    /// Add a behavior to the fusion with the channels selected by `channels`, e.g. `|behavior| &behavior.motion`.
+    /// Connects the activity and target rating of the behavior, see [`crate::modules::general_fusion::GeneralFusion::add_behavior`].
+    pub fn add_behavior<B: IB2CMetaSignals>(
+        &mut self,
+        behavior: &mut B,
+        channels: impl FnOnce(&B) -> &C,
+        inhibition: FusionInhibition,
+    ) {
+        C::add_inputs(&mut self.inputs, channels(behavior));
+        self.meta_signals.add_behavior(behavior, inhibition);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ports::prelude::*;
+    use scheduling::Module;
+    use meta_signals::MetaSignal;
+    use ib2c_macros::FusionChannels;
+    use crate::modules::maximum_fusion::MaximumFusion;
+    use crate::modules::weighted_average_fusion::WeightedAverageFusion;
+    use super::{ChannelFusion, FusionStrategy};
+
+    #[derive(PortMethods, FusionChannels, Default)]
+    pub struct Motion {
+        pub linear: SendPort<f64>,
+        pub angular: SendPort<f64>,
+        #[select]
+        pub absolute_heading: SendPort<bool>,
+    }
+
+    /// Fuses two behaviors and returns the outputs of the fusion and its target rating.
+    fn fuse<M>(activities: [f64; 2]) -> ((f64, f64, bool), MetaSignal)
+    where
+        M: FusionStrategy + PortMethods + PortReflection + Default,
+    {
+        let mut fusion = ChannelFusion::<M, Motion>::new();
+        let mut behaviors = Vec::new();
+        for (index, activity) in activities.into_iter().enumerate() {
+            let mut motion = Motion::default();
+            motion.linear.send(index as f64 + 1.0);
+            motion.angular.send(-(index as f64) * 2.0);
+            motion.absolute_heading.send(index == 1);
+            let mut activity_port = SendPort::new(MetaSignal::LOW);
+            activity_port.send(MetaSignal::new(activity));
+            fusion.add_module(&motion, &activity_port);
+            behaviors.push((motion, activity_port));
+        }
+        fusion.update();
+        let outputs = &fusion.outputs;
+        ((*outputs.linear.get_last_data(), *outputs.angular.get_last_data(), *outputs.absolute_heading.get_last_data()),
+            *fusion.meta_signals.target_rating.get_last_data())
+    }
+
+    #[test]
+    fn channel_fusion() {
+        // All channels follow the same winner.
+        assert_eq!(fuse::<MaximumFusion>([0.25, 0.75]), ((2.0, -2.0, true), MetaSignal::new(0.75)));
+        assert_eq!(fuse::<MaximumFusion>([0.75, 0.25]), ((1.0, 0.0, false), MetaSignal::new(0.75)));
+        // Velocities are blended, the flag is taken from the most active behavior.
+        let ((linear, angular, absolute_heading), _) = fuse::<WeightedAverageFusion>([0.25, 0.75]);
+        assert!((linear - 1.75).abs() < 1e-9 && (angular + 1.5).abs() < 1e-9);
+        assert!(absolute_heading);
+        // Without activity, the outputs keep their defaults.
+        assert_eq!(fuse::<WeightedAverageFusion>([0.0, 0.0]), ((0.0, 0.0, false), MetaSignal::LOW));
+
+        let mut fusion = ChannelFusion::<MaximumFusion, Motion>::new();
+        fusion.add_module(&Motion::default(), &SendPort::new(MetaSignal::LOW));
+        let names: Vec<_> = fusion.ports().into_iter().map(|port| port.name).collect();
+        assert!(names.contains(&"linear[0]".to_string()) && names.contains(&"absolute_heading".to_string()), "{names:?}");
+    }
+}
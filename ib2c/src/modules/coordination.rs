@@ -0,0 +1,292 @@
+use std::time::Duration;
+use ports::prelude::*;
+use meta_signals::MetaSignal;
+use scheduling::{Group, GroupBuilder, Module, ModuleBuilder};
+use crate::ib2c_meta_signals::{activity, input_from, Activation, IB2CMetaSignals, StimulationCombination};
+
+/// Adds a behavior to the group of a coordination pattern.
+type AddModule = Box<dyn FnOnce(&mut GroupBuilder)>;
+
+/// Behaviors of a coordination group, added to the group in `init`.
+#[derive(Default)]
+pub(crate) struct Members {
+    add: Vec<AddModule>,
+    pub(crate) activities: Vec<ReceivePort<MetaSignal>>,
+    pub(crate) target_ratings: Vec<ReceivePort<MetaSignal>>,
+    /// Shortest cycle time of the behaviors.
+    pub(crate) cycle_time: Option<Duration>,
+}
+
+impl Members {
+    pub(crate) fn push<M>(&mut self, mut behavior: ModuleBuilder<M>)
+    where
+        M: IB2CMetaSignals + Module + Send + 'static,
+    {
+        self.activities.push(input_from(MetaSignal::LOW, behavior.activity()));
+        self.target_ratings.push(input_from(MetaSignal::LOW, behavior.target_rating()));
+        self.cycle_time = match (self.cycle_time, behavior.cycle_time) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.add.push(Box::new(move |builder| builder.add_module(behavior)));
+    }
+
+    pub(crate) fn init(&mut self, builder: &mut GroupBuilder) {
+        for add in std::mem::take(&mut self.add) {
+            add(builder);
+        }
+    }
+}
+
+/// Hierarchical inhibition: every behavior is inhibited by the activities of all behaviors
+/// added before it, so a behavior only acts as far as no behavior with a higher priority does.
+///
+/// ```ignore
+/// let mut chain = PriorityChain::new();
+/// chain.add_module(emergency_stop);
+/// chain.add_module(avoid_obstacles);
+/// chain.add_module(follow_path);
+/// builder.add_group(GroupBuilder::new(chain, SpawnMode::GroupThread));
+/// ```
+#[derive(Default)]
+pub struct PriorityChain {
+    members: Members,
+}
+
+impl PriorityChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a behavior with a lower priority than the behaviors added before.
+    pub fn add_module<M>(&mut self, mut behavior: ModuleBuilder<M>)
+    where
+        M: IB2CMetaSignals + Module + Send + 'static,
+    {
+        for activity in &self.members.activities {
+            behavior.inhibit_by(activity);
+        }
+        self.members.push(behavior);
+    }
+}
+
+impl Group for PriorityChain {
+    fn init(&mut self, builder: &mut GroupBuilder) {
+        self.members.init(builder);
+    }
+}
+
+/// Sequential activation: every behavior is inhibited by the target ratings of all behaviors
+/// added before it, so a step only starts once the previous steps rate themselves LOW,
+/// e.g. once docking finished, charging starts.
+#[derive(Default)]
+pub struct Sequence {
+    members: Members,
+}
+
+impl Sequence {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a behavior as the next step of the sequence.
+    pub fn add_module<M>(&mut self, mut behavior: ModuleBuilder<M>)
+    where
+        M: IB2CMetaSignals + Module + Send + 'static,
+    {
+        for target_rating in &self.members.target_ratings {
+            behavior.inhibit_by(target_rating);
+        }
+        self.members.push(behavior);
+    }
+}
+
+impl Group for Sequence {
+    fn init(&mut self, builder: &mut GroupBuilder) {
+        self.members.init(builder);
+    }
+}
+
+/// Winner takes all: the behavior with the highest potential activity inhibits all others,
+/// the one added first if several have it. The potential activity is the target rating gated by
+/// the stimulations and inhibitions the behavior had when it was added, so a highly rated
+/// behavior that may not act does not block the others. The inhibitions are sent by a `Competition` module
+/// in the group, running with the shortest cycle time of the behaviors or the cycle time of the group.
+#[derive(Default)]
+pub struct Competition {
+    members: Members,
+    arbiter: Arbiter,
+}
+
+impl Competition {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a behavior competing with all other behaviors.
+    pub fn add_module<M>(&mut self, mut behavior: ModuleBuilder<M>)
+    where
+        M: IB2CMetaSignals + Module + Send + 'static,
+    {
+        self.arbiter.gates.push(Gate {
+            stimulations: behavior.stimulations().clone(),
+            inhibitions: behavior.inhibitions().clone(),
+            stimulation_combination: *behavior.stimulation_combination(),
+            activation: behavior.activation().clone(),
+        });
+        self.arbiter.inhibitions.push(SendPort::new(MetaSignal::LOW));
+        behavior.inhibit_by(self.arbiter.inhibitions.last().unwrap());
+        self.members.push(behavior);
+    }
+}
+
+impl Group for Competition {
+    fn init(&mut self, builder: &mut GroupBuilder) {
+        let mut arbiter = std::mem::take(&mut self.arbiter);
+        arbiter.target_ratings = self.members.target_ratings.clone();
+        self.members.init(builder);
+        let arbiter = match self.members.cycle_time {
+            Some(cycle_time) => ModuleBuilder::inherit(arbiter).with_cycle_time(cycle_time),
+            None => ModuleBuilder::inherit(arbiter),
+        };
+        builder.add_module(arbiter.with_name("Competition"));
+    }
+}
+
+/// Inhibits all behaviors of a [`Competition`] but the one with the highest potential activity.
+#[derive(PortMethods, Default)]
+struct Arbiter {
+    pub target_ratings: Vec<ReceivePort<MetaSignal>>,
+    pub inhibitions: Vec<SendPort<MetaSignal>>,
+    /// Meta signal inputs of the behaviors besides the inhibitions sent by the arbiter.
+    gates: Vec<Gate>,
+}
+
+/// Stimulations and inhibitions of a competing behavior.
+struct Gate {
+    stimulations: Vec<ReceivePort<MetaSignal>>,
+    inhibitions: Vec<ReceivePort<MetaSignal>>,
+    stimulation_combination: StimulationCombination,
+    activation: Activation,
+}
+
+impl Gate {
+    /// The activity of the behavior with `target_rating` if the arbiter does not inhibit it.
+    fn potential(&mut self, target_rating: MetaSignal) -> MetaSignal {
+        self.stimulations.iter_mut().chain(&mut self.inhibitions).for_each(ReceivePort::update);
+        activity(&self.stimulations, &self.inhibitions, self.stimulation_combination, &self.activation, target_rating)
+    }
+}
+
+impl Module for Arbiter {
+    fn update(&mut self) {
+        self.update_ports();
+        let winner = self.target_ratings.iter()
+            .zip(&mut self.gates)
+            .map(|(target_rating, gate)| gate.potential(*target_rating.get_data()))
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, potential)| *potential)
+            .map(|(index, _)| index);
+        for (index, inhibition) in self.inhibitions.iter_mut().enumerate() {
+            inhibition.send(if Some(index) == winner { MetaSignal::LOW } else { MetaSignal::HIGH });
+        }
+    }
+
+    fn interface(&self) -> Vec<PortEntry> {
+        self.ports()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use ports::prelude::*;
+    use meta_signals::MetaSignal;
+    use scheduling::{Group, GroupBuilder, ModuleBuilder, Runtime, SpawnMode};
+    use crate::IB2CMetaSignals;
+    use crate::modules::behavior_module::{BehaviorModule, BehaviorModuleTrait};
+    use super::{Competition, PriorityChain, Sequence};
+
+    #[derive(PortMethods, Default)]
+    struct Constant {
+        pub rating: ParameterPort<f64>,
+    }
+
+    impl BehaviorModuleTrait for Constant {
+        fn transfer(_module: &mut BehaviorModule<Self>) {}
+
+        fn target_rating(module: &BehaviorModule<Self>) -> MetaSignal {
+            MetaSignal::new(*module.rating.get_data())
+        }
+    }
+
+    fn behaviors(ratings: [f64; 3]) -> impl Iterator<Item = ModuleBuilder<BehaviorModule<Constant>>> {
+        ratings.into_iter().zip(["a", "b", "c"]).map(|(rating, name)| {
+            let mut behavior = Constant::new();
+            behavior.rating = ParameterPort::new(rating);
+            ModuleBuilder::new(behavior, Duration::from_millis(1), SpawnMode::GroupThread).with_name(name)
+        })
+    }
+
+    fn activities(runtime: &Runtime, group: &str) -> Vec<String> {
+        let snapshot = runtime.snapshot();
+        ["a", "b", "c"].iter()
+            .map(|name| snapshot.module(&format!("{group}/{name}")).unwrap().port("activity").unwrap().value.clone().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn coordination_patterns() {
+        let mut chain = PriorityChain::new();
+        let mut sequence = Sequence::new();
+        let mut competition = Competition::new();
+        behaviors([0.25, 1.0, 0.5]).for_each(|behavior| chain.add_module(behavior));
+        behaviors([0.0, 1.0, 1.0]).for_each(|behavior| sequence.add_module(behavior));
+        behaviors([0.5, 0.75, 0.75]).for_each(|behavior| competition.add_module(behavior));
+        fn group(group: impl Group, name: &str) -> GroupBuilder {
+            GroupBuilder::new(group, SpawnMode::NewThread).with_name(name).into()
+        }
+        let mut builder = GroupBuilder::empty();
+        builder.add_group(group(chain, "chain"));
+        builder.add_group(group(sequence, "sequence"));
+        builder.add_group(group(competition, "competition"));
+        let runtime = builder.spawn();
+
+        let expected = [
+            ("chain", ["0.25", "0.75", "0.25"]),
+            ("sequence", ["0", "1", "0"]),
+            ("competition", ["0", "0.75", "0"]),
+        ];
+        let start = Instant::now();
+        while expected.iter().any(|(group, expected)| activities(&runtime, group) != expected) {
+            assert!(start.elapsed() < Duration::from_secs(2), "{:?}",
+                expected.map(|(group, _)| activities(&runtime, group)));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        runtime.shutdown();
+    }
+
+    #[test]
+    fn competition_ranks_by_potential_activity() {
+        let unstimulated = SendPort::new(MetaSignal::LOW);
+        let mut competition = Competition::new();
+        for mut behavior in behaviors([0.5, 1.0, 0.75]) {
+            if behavior.name == "b" {
+                behavior.stimulate_by(&unstimulated);
+            }
+            competition.add_module(behavior);
+        }
+        let mut builder = GroupBuilder::empty();
+        builder.add_group(GroupBuilder::new(competition, SpawnMode::NewThread).with_name("competition"));
+        let runtime = builder.spawn();
+
+        // `b` has the highest target rating but may not act, so `c` wins.
+        let start = Instant::now();
+        while activities(&runtime, "competition") != ["0", "0", "0.75"] {
+            assert!(start.elapsed() < Duration::from_secs(2), "{:?}", activities(&runtime, "competition"));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        runtime.shutdown();
+    }
+}
@@ -1,13 +1,14 @@
+use std::ops::{Add, Mul};
 use derive_more::{Deref, DerefMut};
 use ib2c_macros::IB2CMetaSignals;
-use scheduling::Module;
+use scheduling::{short_type_name, Module};
 use ports::prelude::*;
 use meta_signals::MetaSignal;
-use crate::ib2c_meta_signals::IB2CMetaSignals;
+use crate::ib2c_meta_signals::{activity, input_from, Activation, IB2CMetaSignals, StimulationCombination};
 
 /// A general fusion scheduling that can fuse multiple data inputs based on their activity levels.
 /// The fusion strategy is defined by implementing this trait.
-pub trait GeneralFusionTrait<D: Default>: PortMethods + Default {
+pub trait GeneralFusionTrait<D: Default>: PortMethods + PortReflection + Default {
     /// Initialize the fusion scheduling (optional).
     fn init() -> Self where Self: Sized {
         Self::default()
@@ -15,8 +16,8 @@ pub trait GeneralFusionTrait<D: Default>: PortMethods + Default {
 
     /// Fuse the inputs by connection a data port to the output port
     /// or by publishing a values to the output port.
-    /// Return the target rating of the fusion.
-    fn fuse(module: &mut GeneralFusion<Self, D>) -> MetaSignal;
+    /// Return the activity and target rating derived from the inputs.
+    fn fuse(module: &mut GeneralFusion<Self, D>) -> FusionRating;
 
     /// Create a new general fusion module. Should be wrapped in a [`scheduling::ModuleBuilder::new`] to be added to a ThreadContainer.
     fn new() -> GeneralFusion<Self, D>
@@ -27,6 +28,30 @@ pub trait GeneralFusionTrait<D: Default>: PortMethods + Default {
     }
 }
 
+/// Activity and target rating of a fusion derived from its inputs, before the stimulations
+/// and inhibitions of the fusion apply. LOW for both if no input is active.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct FusionRating {
+    pub activity: MetaSignal,
+    pub target_rating: MetaSignal,
+}
+
+/// Data that can be blended by weighted fusions, e.g. `f64` or `SiValue` types.
+pub trait WeightedData: Default + Clone + Add<Output = Self> + Mul<f64, Output = Self> {}
+
+impl<D> WeightedData for D where D: Default + Clone + Add<Output = D> + Mul<f64, Output = D> {}
+
+/// Whether a behavior added to a fusion with [`GeneralFusion::add_behavior`] is also inhibited
+/// by the inhibitions of the fusion, so inhibiting the fusion stops its behaviors as well.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum FusionInhibition {
+    /// The behavior is only inhibited by its own inhibitions.
+    #[default]
+    Own,
+    /// The behavior is also inhibited by all inhibitions added to the fusion so far.
+    Inherited,
+}
+
 /// Inner structure of a general fusion scheduling.
 /// Used by the [`GeneralFusionTrait`] to create a fusion scheduling.
 #[derive(PortMethods, Deref, DerefMut, IB2CMetaSignals)]
@@ -36,40 +61,35 @@ where
     D: Default,
 {
     #[deref] #[deref_mut]
+    #[nested_ports]
     inner: M,
 
-    pub stimulation: ReceivePort<MetaSignal>,
-    pub inhibition: ReceivePort<MetaSignal>,
-    pub activity: SendPort<MetaSignal>,
-    pub target_rating: SendPort<MetaSignal>,
+    #[nested_ports]
+    #[meta_signals]
+    pub meta_signals: FusionMetaSignals,
 
     pub data_ports: Vec<ReceivePort<D>>,
-    pub activity_ports: Vec<ReceivePort<MetaSignal>>,
     pub output_port: SendPort<D>,
 }
 
 impl<M, D> Module for GeneralFusion<M, D>
 where
     M: GeneralFusionTrait<D>,
-    D: Default,
+    D: Default + Send + Sync + 'static,
 {
     fn update(&mut self) {
         self.update_ports();
-        self.inner.update_ports();
-        for port in &mut self.data_ports {
-            port.update();
-        }
-        for port in &mut self.activity_ports {
-            port.update();
-        }
 
-        let target = M::fuse(self);
-        let stimulation = *self.stimulation.get_data();
-        let inhibition = *self.inhibition.get_data();
-        let potential = std::cmp::min(stimulation, MetaSignal::HIGH - inhibition);
-        let activity = std::cmp::min(potential, target);
-        self.activity.send(activity);
-        self.target_rating.send(target);
+        let rating = M::fuse(self);
+        self.meta_signals.send(rating);
+    }
+
+    fn default_name() -> String {
+        short_type_name::<M>()
+    }
+
+    fn interface(&self) -> Vec<PortEntry> {
+        self.ports()
     }
 }
 
@@ -81,23 +101,172 @@ where
     fn new(inner: M) -> Self {
         GeneralFusion {
             inner,
-            stimulation: ReceivePort::new(MetaSignal::HIGH),
-            inhibition: ReceivePort::new(MetaSignal::LOW),
-            activity: SendPort::new(MetaSignal::LOW),
-            target_rating: SendPort::new(MetaSignal::LOW),
+            meta_signals: FusionMetaSignals::default(),
             data_ports: Vec::new(),
-            activity_ports: Vec::new(),
             output_port: SendPort::new(D::default()),
         }
     }
 
-    /// Add a new scheduling to the fusion.
-    pub fn add_module(&mut self, data_port: &InnerPort<D>, activity_port: &InnerPort<MetaSignal>) {
-        let data_receive_port = ReceivePort::default();
-        data_receive_port.connect_to_source(data_port);
-        self.data_ports.push(data_receive_port);
-        let activity_receive_port = ReceivePort::default();
-        activity_receive_port.connect_to_source(activity_port);
-        self.activity_ports.push(activity_receive_port);
+    /// Add a new scheduling to the fusion, rated by its activity.
+    pub fn add_module(&mut self, data_port: &impl PortSource<D>, activity_port: &impl PortSource<MetaSignal>) {
+        self.data_ports.push(input_from(D::default(), data_port));
+        self.meta_signals.add_module(activity_port);
+    }
+
+    /// Add a behavior to the fusion with the data port selected by `data`, e.g. `|behavior| &behavior.velocity`.
+    /// Connects the activity and target rating of the behavior, so they can not be mixed up.
+    pub fn add_behavior<B: IB2CMetaSignals>(
+        &mut self,
+        behavior: &mut B,
+        data: impl FnOnce(&B) -> &SendPort<D>,
+        inhibition: FusionInhibition,
+    ) {
+        self.data_ports.push(input_from(D::default(), data(behavior)));
+        self.meta_signals.add_behavior(behavior, inhibition);
+    }
+
+    /// Add a new scheduling to the fusion from type erased ports, e.g. listed by [`PortReflection`].
+    /// It is rated by its activity like with [`GeneralFusion::add_module`].
+    pub fn add_dyn_module(&mut self, data_port: &DynPort, activity_port: &DynPort) -> Result<(), PortError>
+    where
+        D: Send + Sync + 'static,
+    {
+        let data = ReceivePort::default();
+        data.connect_from_dyn(data_port)?;
+        self.meta_signals.add_dyn_module(activity_port)?;
+        self.data_ports.push(data);
+        Ok(())
+    }
+}
+
+/// The meta signals of a fusion and of its inputs, shared by [`GeneralFusion`] and
+/// [`crate::modules::channel_fusion::ChannelFusion`].
+#[derive(PortMethods, Default, IB2CMetaSignals)]
+pub struct FusionMetaSignals {
+    /// Added with [`IB2CMetaSignals::stimulate_by`], HIGH if empty.
+    pub stimulations: Vec<ReceivePort<MetaSignal>>,
+    /// Added with [`IB2CMetaSignals::inhibit_by`], LOW if empty.
+    pub inhibitions: Vec<ReceivePort<MetaSignal>>,
+    pub stimulation_combination: StimulationCombination,
+    pub activation: Activation,
+    pub activity: SendPort<MetaSignal>,
+    pub target_rating: SendPort<MetaSignal>,
+
+    pub activity_ports: Vec<ReceivePort<MetaSignal>>,
+    /// Target ratings of the inputs. Modules added without one, e.g. with
+    /// [`GeneralFusion::add_module`], are rated by their activity.
+    pub target_rating_ports: Vec<ReceivePort<MetaSignal>>,
+}
+
+impl FusionMetaSignals {
+    /// Activities and target ratings of the inputs.
+    pub fn inputs(&self) -> (Vec<MetaSignal>, Vec<MetaSignal>) {
+        let values = |ports: &[ReceivePort<MetaSignal>]| ports.iter().map(|port| *port.get_data()).collect();
+        (values(&self.activity_ports), values(&self.target_rating_ports))
+    }
+
+    /// Sends the activity and target rating of the fusion given the rating of its inputs.
+    pub fn send(&mut self, rating: FusionRating) {
+        let activity = activity(&self.stimulations, &self.inhibitions, self.stimulation_combination, &self.activation,
+            rating.activity);
+        self.activity.send(activity);
+        self.target_rating.send(rating.target_rating);
+    }
+
+    /// Adds an input rated by its activity.
+    pub(crate) fn add_module(&mut self, activity_port: &impl PortSource<MetaSignal>) {
+        self.activity_ports.push(input_from(MetaSignal::LOW, activity_port));
+        self.target_rating_ports.push(input_from(MetaSignal::LOW, activity_port));
+    }
+
+    /// Adds the activity and target rating of a behavior and lets the fusion's inhibitions
+    /// inhibit it if requested.
+    pub(crate) fn add_behavior(&mut self, behavior: &mut impl IB2CMetaSignals, inhibition: FusionInhibition) {
+        self.activity_ports.push(input_from(MetaSignal::LOW, behavior.activity()));
+        self.target_rating_ports.push(input_from(MetaSignal::LOW, behavior.target_rating()));
+        if inhibition == FusionInhibition::Inherited {
+            for port in &self.inhibitions {
+                behavior.inhibit_by(port);
+            }
+        }
+    }
+
+    /// Adds an input rated by its activity from a type erased port.
+    fn add_dyn_module(&mut self, activity_port: &DynPort) -> Result<(), PortError> {
+        let activity = ReceivePort::default();
+        activity.connect_from_dyn(activity_port)?;
+        let target_rating = ReceivePort::default();
+        target_rating.connect_from_dyn(activity_port)?;
+        self.activity_ports.push(activity);
+        self.target_rating_ports.push(target_rating);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use ports::prelude::*;
+    use scheduling::{Module, ModuleBuilder, SpawnMode};
+    use meta_signals::MetaSignal;
+    use crate::IB2CMetaSignals;
+    use crate::modules::behavior_module::{BehaviorModule, BehaviorModuleTrait};
+    use crate::modules::channel_fusion::FusionStrategy;
+    use crate::modules::maximum_fusion::MaximumFusion;
+    use super::{FusionInhibition, FusionRating, GeneralFusionTrait};
+
+    #[derive(PortMethods, Default)]
+    struct Follow {
+        pub velocity: SendPort<f64>,
+    }
+
+    impl BehaviorModuleTrait for Follow {
+        fn transfer(module: &mut BehaviorModule<Self>) {
+            module.velocity.send(0.5);
+        }
+
+        fn target_rating(_module: &BehaviorModule<Self>) -> MetaSignal {
+            MetaSignal::new(0.75)
+        }
+    }
+
+    #[test]
+    fn add_behavior() {
+        let mut fusion = <MaximumFusion as GeneralFusionTrait<f64>>::new();
+        let mut emergency_stop = SendPort::new(MetaSignal::LOW);
+        fusion.inhibit_by(&emergency_stop);
+        let mut follow = ModuleBuilder::new(Follow::new(), Duration::from_millis(10), SpawnMode::GroupThread);
+        fusion.add_behavior(&mut follow, |behavior| &behavior.velocity, FusionInhibition::Inherited);
+
+        follow.update();
+        fusion.update();
+        assert_eq!(*fusion.meta_signals.target_rating_ports[0].get_data(), MetaSignal::new(0.75));
+        assert_eq!(*fusion.meta_signals.activity.get_last_data(), MetaSignal::new(0.75));
+
+        // Inhibiting the fusion inhibits the behavior as well.
+        emergency_stop.send(MetaSignal::HIGH);
+        follow.update();
+        fusion.update();
+        assert_eq!(*follow.activity.get_last_data(), MetaSignal::LOW);
+        assert_eq!(*fusion.meta_signals.activity.get_last_data(), MetaSignal::LOW);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn target_ratings_of_behaviors() {
+        let mut fusion = <MaximumFusion as GeneralFusionTrait<f64>>::new();
+        let operator = SendPort::new(MetaSignal::new(0.5));
+        let mut follow = Follow::new();
+        follow.stimulate_by(&operator);
+        fusion.add_behavior(&mut follow, |behavior| &behavior.velocity, FusionInhibition::Own);
+
+        // The behavior is rated higher than it may act, the fusion passes on both.
+        follow.update();
+        fusion.update();
+        assert_eq!(*fusion.meta_signals.activity.get_last_data(), MetaSignal::new(0.5));
+        assert_eq!(*fusion.meta_signals.target_rating.get_last_data(), MetaSignal::new(0.75));
+
+        let m = MetaSignal::new;
+        assert_eq!(MaximumFusion::weights(&[m(0.25), m(0.5)], &[m(1.0), m(0.75)]).rating,
+            FusionRating { activity: m(0.5), target_rating: m(0.75) });
+    }
+}
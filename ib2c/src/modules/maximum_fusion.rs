@@ -1,27 +1,50 @@
 use ports::prelude::PortMethods;
 use meta_signals::MetaSignal;
-use crate::modules::general_fusion::{GeneralFusion, GeneralFusionTrait};
+use crate::modules::channel_fusion::{FusionStrategy, FusionWeights};
+use crate::modules::general_fusion::{FusionRating, GeneralFusion, GeneralFusionTrait};
 
 #[derive(Default, PortMethods)]
 pub struct MaximumFusion { }
 
+/// Index of the input with the highest activity, the first one if several have it.
+fn winner(activities: &[MetaSignal]) -> Option<usize> {
+    activities.iter().enumerate().rev().max_by_key(|(_, activity)| **activity).map(|(index, _)| index)
+}
+
 /// A fusion scheduling that connects the output port to the data port with the highest activity.
-/// The target rating is the activity of the selected data port.
-/// If no data ports are available, the output port is not connected and the target rating is LOW.
+/// The activity and target rating are those of the selected input.
+/// If no data ports are available, the output port is not connected and both are LOW.
 impl<D: Default> GeneralFusionTrait<D> for MaximumFusion {
-    fn fuse(module: &mut GeneralFusion<Self, D>) -> MetaSignal {
-        // find the data port with the highest activity
-        let max = module.data_ports.iter()
-            .zip(module.activity_ports.iter())
-            .rev()
-            .map(|(d, a)| (d, a.get_data()))
-            .max_by_key(|(_, activity)| *activity);
+    fn fuse(module: &mut GeneralFusion<Self, D>) -> FusionRating {
+        let (activities, target_ratings) = module.meta_signals.inputs();
 
-        // connect the output port to the data port with the highest activity
-        if let Some((data_port, max_activity)) = max {
-            module.output_port.connect_to_source(data_port);
-            return *max_activity
+        // connect the output port to the data port with the highest activity,
+        // a data port reading from the output port would create a cycle and is never selected
+        if let Some(index) = winner(&activities)
+            && module.output_port.switch_source(&module.data_ports[index]).is_ok()
+        {
+            return FusionRating { activity: activities[index], target_rating: target_ratings[index] };
+        }
+        FusionRating::default()
+    }
+}
+
+/// Gives the whole weight to the input with the highest activity, the first one if several have it.
+/// The activity and target rating are those of this input.
+impl FusionStrategy for MaximumFusion {
+    fn weights(activities: &[MetaSignal], target_ratings: &[MetaSignal]) -> FusionWeights {
+        let mut weights = vec![0.0; activities.len()];
+        let winner = winner(activities);
+        if let Some(index) = winner {
+            weights[index] = 1.0;
+        }
+        FusionWeights {
+            weights,
+            normalization: 1.0,
+            rating: winner.map_or_else(FusionRating::default, |index| FusionRating {
+                activity: activities[index],
+                target_rating: target_ratings[index],
+            }),
         }
-        MetaSignal::LOW
     }
-}
\ No newline at end of file
+}
@@ -1,6 +1,11 @@
 pub mod behavior_module;
 pub mod basic_module;
 pub mod general_fusion;
+pub mod channel_fusion;
 pub mod maximum_fusion;
+pub mod weighted_average_fusion;
+pub mod weighted_sum_fusion;
 pub mod basic_group;
-pub mod behavior_group;
\ No newline at end of file
+pub mod behavior_group;
+pub mod coordination;
+pub mod state_machine_behavior;
\ No newline at end of file
@@ -0,0 +1,339 @@
+use std::cmp::min;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use derive_more::{Deref, DerefMut};
+use ports::prelude::*;
+use meta_signals::MetaSignal;
+use scheduling::{Clock, Module};
+use crate::ib2c_meta_signals::{activity, input_from, IB2CMetaSignals};
+use crate::modules::behavior_module::{BehaviorModule, BehaviorModuleTrait};
+
+/// A behavior sequencing sub-behaviors with explicit states, e.g. dock, charge and undock.
+/// The ports of `M` are available to the transition guards, see [`StateMachine::add_transition`].
+pub type StateMachineBehavior<M> = BehaviorModule<StateMachine<M>>;
+
+/// Decides whether to leave a state, see [`StateMachine::add_transition`].
+type Guard<M> = Box<dyn FnMut(&M, &States) -> bool + Send>;
+
+/// A state with its sub-behavior, stimulated only while the state is active.
+/// The sub-behavior follows the lifecycle of the state machine, e.g. it is paused with it.
+/// Its due timers fire in the cycles of the state machine, right before its update.
+struct State {
+    name: String,
+    behavior: Box<dyn Module + Send>,
+    stimulation: SendPort<MetaSignal>,
+    activity: ReceivePort<MetaSignal>,
+    target_rating: ReceivePort<MetaSignal>,
+}
+
+/// The states of a [`StateMachine`]. Lists the ports of the sub-behaviors as `state/port`.
+#[derive(Default, Deref, DerefMut)]
+struct StateList(Vec<State>);
+
+impl PortMethods for StateList {
+    /// The sub-behaviors update their own ports.
+    fn update_ports(&mut self) {}
+}
+
+impl PortReflection for StateList {
+    fn ports(&self) -> Vec<PortEntry> {
+        self.iter()
+            .flat_map(|state| state.behavior.interface().into_iter().map(|mut entry| {
+                entry.name = format!("{}/{}", state.name, entry.name);
+                entry
+            }))
+            .collect()
+    }
+}
+
+struct Transition<M> {
+    from: usize,
+    to: usize,
+    guard: Guard<M>,
+}
+
+/// The states of a [`StateMachine`] as seen by the transition guards.
+pub struct States<'a> {
+    states: &'a [State],
+    current: usize,
+    entered: Instant,
+    now: Instant,
+}
+
+impl States<'_> {
+    /// Name of the active state.
+    pub fn current(&self) -> &str {
+        &self.states[self.current].name
+    }
+
+    /// Time since the active state was entered, measured with the clock of the container.
+    pub fn time_in_state(&self) -> Duration {
+        self.now.saturating_duration_since(self.entered)
+    }
+
+    /// Activity of the sub-behavior of a state, `None` if there is no such state.
+    pub fn activity(&self, state: &str) -> Option<MetaSignal> {
+        self.find(state).map(|state| *state.activity.get_data())
+    }
+
+    /// Target rating of the sub-behavior of a state, `None` if there is no such state.
+    pub fn target_rating(&self, state: &str) -> Option<MetaSignal> {
+        self.find(state).map(|state| *state.target_rating.get_data())
+    }
+
+    fn find(&self, name: &str) -> Option<&State> {
+        self.states.iter().find(|state| state.name == name)
+    }
+}
+
+/// Inner structure of a [`StateMachineBehavior`]. States are sub-behaviors updated in every cycle
+/// of the state machine, only the active one is stimulated. The target rating and activity of the
+/// state machine are those of the active sub-behavior. The ports of the sub-behaviors are listed
+/// with the ports of the state machine, prefixed with the name of their state.
+#[derive(PortMethods, Default)]
+pub struct StateMachine<M: PortMethods + PortReflection + Default> {
+    #[nested_ports]
+    pub inner: M,
+    /// Name of the active state.
+    pub state: SendPort<String>,
+
+    #[nested_ports]
+    states: StateList,
+    transitions: Vec<Transition<M>>,
+    current: usize,
+    entered: Option<Instant>,
+}
+
+impl<M: PortMethods + PortReflection + Default> StateMachine<M> {
+    /// Adds a state with its sub-behavior, the first state added is the initial state.
+    /// The sub-behavior is owned by the state machine and must not be added to a group.
+    pub fn add_state<B>(&mut self, name: impl Into<String>, mut behavior: B)
+    where
+        B: IB2CMetaSignals + Module + Send + 'static,
+    {
+        let stimulation = SendPort::new(MetaSignal::LOW);
+        behavior.stimulate_by(&stimulation);
+        let activity = input_from(MetaSignal::LOW, behavior.activity());
+        let target_rating = input_from(MetaSignal::LOW, behavior.target_rating());
+        self.states.push(State { name: name.into(), behavior: Box::new(behavior), stimulation, activity, target_rating });
+        if self.states.len() == 1 {
+            self.state.send(self.states[0].name.clone());
+        }
+    }
+
+    /// Adds a transition taken when `guard` returns true while `from` is active, checked before
+    /// the sub-behaviors are updated. The transitions of a state are checked in the order they
+    /// were added. Panics if one of the states does not exist.
+    pub fn add_transition<F>(&mut self, from: &str, to: &str, guard: F)
+    where
+        F: FnMut(&M, &States) -> bool + Send + 'static,
+    {
+        let index = |name: &str| self.states.iter().position(|state| state.name == name)
+            .unwrap_or_else(|| panic!("state `{name}` does not exist"));
+        let transition = Transition { from: index(from), to: index(to), guard: Box::new(guard) };
+        self.transitions.push(transition);
+    }
+
+    /// Name of the active state, `None` if no state was added.
+    pub fn current_state(&self) -> Option<&str> {
+        self.states.get(self.current).map(|state| state.name.as_str())
+    }
+
+    fn transition(&mut self, now: Instant) {
+        let entered = *self.entered.get_or_insert(now);
+        let states = States { states: &self.states.0, current: self.current, entered, now };
+        let next = self.transitions.iter_mut()
+            .filter(|transition| transition.from == states.current)
+            .find_map(|transition| (transition.guard)(&self.inner, &states).then_some(transition.to));
+        if let Some(next) = next {
+            self.current = next;
+            self.entered = Some(now);
+            self.state.send(self.states[next].name.clone());
+        }
+    }
+}
+
+impl<M: PortMethods + PortReflection + Default> BehaviorModuleTrait for StateMachine<M> {
+    fn transfer(module: &mut BehaviorModule<Self>) {
+        if module.states.is_empty() {
+            return;
+        }
+        let now = module.timers.clock().now();
+        module.transition(now);
+        // The active sub-behavior may act as far as the state machine may.
+        let potential = activity(&module.stimulations, &module.inhibitions, module.stimulation_combination,
+            &module.activation, MetaSignal::HIGH);
+        let current = module.current;
+        for (index, state) in module.states.iter_mut().enumerate() {
+            state.stimulation.send(if index == current { potential } else { MetaSignal::LOW });
+            state.behavior.fire_timers();
+            state.behavior.update();
+            state.activity.update();
+            state.target_rating.update();
+        }
+    }
+
+    fn target_rating(module: &BehaviorModule<Self>) -> MetaSignal {
+        module.states.get(module.current).map_or(MetaSignal::LOW, |state| *state.target_rating.get_data())
+    }
+
+    /// The activity of the active sub-behavior, as far as the state machine may act.
+    fn activity(module: &BehaviorModule<Self>, _activity: MetaSignal) -> MetaSignal {
+        let potential = activity(&module.stimulations, &module.inhibitions, module.stimulation_combination,
+            &module.activation, MetaSignal::HIGH);
+        module.states.get(module.current).map_or(MetaSignal::LOW, |state| min(*state.activity.get_data(), potential))
+    }
+
+    fn set_clock(module: &mut BehaviorModule<Self>, clock: &Arc<dyn Clock>) {
+        module.states.iter_mut().for_each(|state| state.behavior.set_clock(clock));
+    }
+
+    fn start(module: &mut BehaviorModule<Self>) {
+        module.states.iter_mut().for_each(|state| state.behavior.start());
+    }
+
+    fn stop(module: &mut BehaviorModule<Self>) {
+        module.states.iter_mut().for_each(|state| state.behavior.stop());
+    }
+
+    fn pause(module: &mut BehaviorModule<Self>) {
+        module.states.iter_mut().for_each(|state| state.behavior.pause());
+    }
+
+    fn resume(module: &mut BehaviorModule<Self>) {
+        module.states.iter_mut().for_each(|state| state.behavior.resume());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use ports::prelude::*;
+    use scheduling::{Clock, ManualClock, Module};
+    use meta_signals::MetaSignal;
+    use crate::{Activation, IB2CMetaSignals};
+    use crate::modules::behavior_module::{BehaviorModule, BehaviorModuleTrait};
+    use super::{StateMachine, StateMachineBehavior};
+
+    #[derive(PortMethods, Default)]
+    struct Step {
+        pub rating: ParameterPort<f64>,
+        lifecycle: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    impl BehaviorModuleTrait for Step {
+        fn transfer(_module: &mut BehaviorModule<Self>) {}
+
+        fn target_rating(module: &BehaviorModule<Self>) -> MetaSignal {
+            MetaSignal::new(*module.rating.get_data())
+        }
+
+        fn start(module: &mut BehaviorModule<Self>) {
+            module.lifecycle.lock().unwrap().push("start");
+        }
+
+        fn stop(module: &mut BehaviorModule<Self>) {
+            module.lifecycle.lock().unwrap().push("stop");
+        }
+
+        fn pause(module: &mut BehaviorModule<Self>) {
+            module.lifecycle.lock().unwrap().push("pause");
+        }
+
+        fn resume(module: &mut BehaviorModule<Self>) {
+            module.lifecycle.lock().unwrap().push("resume");
+        }
+    }
+
+    fn step(rating: f64) -> BehaviorModule<Step> {
+        let mut step = Step::new();
+        step.rating = ParameterPort::new(rating);
+        step
+    }
+
+    #[derive(PortMethods, Default)]
+    struct Docking {
+        pub docked: ReceivePort<bool>,
+    }
+
+    #[test]
+    fn state_machine() {
+        let mut machine: StateMachineBehavior<Docking> = StateMachine::new();
+        machine.add_state("dock", step(0.75));
+        machine.add_state("charge", step(0.5));
+        machine.add_state("undock", step(0.25));
+        machine.add_transition("dock", "charge", |ports, _| *ports.docked.get_data());
+        machine.add_transition("charge", "undock", |_, states| states.activity("charge") == Some(MetaSignal::new(0.5)));
+        let mut docked = SendPort::new(false);
+        machine.inner.docked.connect_from(&docked).unwrap();
+
+        machine.update();
+        assert_eq!(machine.current_state(), Some("dock"));
+        assert_eq!(*machine.state.get_last_data(), "dock");
+        assert_eq!(*machine.activity.get_last_data(), MetaSignal::new(0.75));
+
+        docked.send(true);
+        machine.update();
+        assert_eq!(*machine.state.get_last_data(), "charge");
+        assert_eq!(*machine.target_rating.get_last_data(), MetaSignal::new(0.5));
+        machine.update();
+        assert_eq!(*machine.state.get_last_data(), "undock");
+        assert_eq!(*machine.activity.get_last_data(), MetaSignal::new(0.25));
+        let names: Vec<_> = machine.ports().into_iter().map(|port| port.name).collect();
+        assert!(names.contains(&"state".to_string()) && names.contains(&"docked".to_string()), "{names:?}");
+    }
+
+    #[test]
+    fn activity_and_ports_of_sub_behaviors() {
+        let mut machine: StateMachineBehavior<Docking> = StateMachine::new();
+        let mut dock = step(0.25);
+        dock.set_activation(Activation::Threshold(MetaSignal::new(0.5)));
+        machine.add_state("dock", dock);
+
+        // The sub-behavior rates itself 0.25 but does not act below its threshold.
+        machine.update();
+        assert_eq!(*machine.target_rating.get_last_data(), MetaSignal::new(0.25));
+        assert_eq!(*machine.activity.get_last_data(), MetaSignal::LOW);
+
+
+        // The inhibited state machine stimulates its sub-behavior with 0.5, which acts by its own activation.
+        let mut dock = step(0.75);
+        dock.set_activation(Activation::Product);
+        let mut machine: StateMachineBehavior<Docking> = StateMachine::new();
+        machine.add_state("dock", dock);
+        let stop = SendPort::new(MetaSignal::new(0.5));
+        machine.inhibit_by(&stop);
+        machine.update();
+        assert_eq!(*machine.activity.get_last_data(), MetaSignal::new(0.375));
+
+        let names: Vec<_> = machine.ports().into_iter().map(|port| port.name).collect();
+        assert!(names.contains(&"dock/rating".to_string()) && names.contains(&"dock/activity".to_string()), "{names:?}");
+    }
+
+    #[test]
+    fn clock_and_lifecycle() {
+        let clock = ManualClock::new();
+        let mut machine: StateMachineBehavior<Docking> = StateMachine::new();
+        let dock = step(0.75);
+        let lifecycle = Arc::clone(&dock.lifecycle);
+        machine.add_state("dock", dock);
+        machine.add_state("charge", step(0.5));
+        machine.add_transition("dock", "charge", |_, states| states.time_in_state() >= Duration::from_secs(2));
+        machine.set_clock(&(Arc::new(clock.clone()) as Arc<dyn Clock>));
+        machine.start();
+
+        machine.update();
+        clock.advance(Duration::from_secs(1));
+        machine.update();
+        assert_eq!(machine.current_state(), Some("dock"));
+        clock.advance(Duration::from_secs(1));
+        machine.update();
+        assert_eq!(machine.current_state(), Some("charge"));
+
+        machine.pause();
+        machine.resume();
+        machine.stop();
+        assert_eq!(*lifecycle.lock().unwrap(), ["start", "pause", "resume", "stop"]);
+    }
+}
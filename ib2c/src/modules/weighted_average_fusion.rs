@@ -0,0 +1,83 @@
+use ports::prelude::PortMethods;
+use meta_signals::MetaSignal;
+use crate::modules::channel_fusion::{blend, weighted_target_rating, FusionStrategy, FusionWeights};
+use crate::modules::general_fusion::{FusionRating, GeneralFusion, GeneralFusionTrait, WeightedData};
+
+#[derive(Default, PortMethods)]
+pub struct WeightedAverageFusion { }
+
+/// Weights the inputs by their activities, so the fused value is their weighted mean.
+/// The activity is the mean of the activities weighted by themselves, `Σ aᵢ² / Σ aᵢ`,
+/// so weak behaviors lower it less than an unweighted mean would.
+/// The target rating is the mean of the target ratings weighted by the activities, `Σ aᵢ·rᵢ / Σ aᵢ`.
+/// If all activities are zero, nothing is fused and both are LOW.
+impl FusionStrategy for WeightedAverageFusion {
+    fn weights(activities: &[MetaSignal], target_ratings: &[MetaSignal]) -> FusionWeights {
+        FusionWeights {
+            weights: activities.iter().map(|activity| **activity).collect(),
+            normalization: activities.iter().map(|activity| **activity).sum(),
+            rating: FusionRating {
+                activity: weighted_target_rating(activities, activities),
+                target_rating: weighted_target_rating(activities, target_ratings),
+            },
+        }
+    }
+}
+
+/// A fusion scheduling that sends the mean of the data ports weighted by their activities,
+/// blending the outputs of the behaviors instead of switching between them.
+/// If all activities are zero, the output port keeps its last value and the target rating is LOW.
+impl<D: WeightedData> GeneralFusionTrait<D> for WeightedAverageFusion {
+    fn fuse(module: &mut GeneralFusion<Self, D>) -> FusionRating {
+        let (activities, target_ratings) = module.meta_signals.inputs();
+        let weights = Self::weights(&activities, &target_ratings);
+        if let Some(data) = blend(module.data_ports.iter().map(|port| port.get_data()), &weights) {
+            module.output_port.send(data);
+        }
+        weights.rating
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ports::prelude::*;
+    use scheduling::Module;
+    use meta_signals::MetaSignal;
+    use crate::modules::channel_fusion::FusionStrategy;
+    use crate::modules::general_fusion::{FusionRating, GeneralFusionTrait};
+    use super::WeightedAverageFusion;
+
+    #[test]
+    fn weighted_average() {
+        let mut fusion = <WeightedAverageFusion as GeneralFusionTrait<f64>>::new();
+        let mut data = [SendPort::new(0.0), SendPort::new(0.0)];
+        let mut activities = [SendPort::new(MetaSignal::LOW), SendPort::new(MetaSignal::LOW)];
+        for (data, activity) in data.iter().zip(activities.iter()) {
+            fusion.add_module(data, activity);
+        }
+
+        data[0].send(1.0);
+        data[1].send(4.0);
+        fusion.update();
+        assert_eq!(*fusion.output_port.get_last_data(), 0.0);
+        assert_eq!(*fusion.meta_signals.target_rating.get_last_data(), MetaSignal::LOW);
+
+        activities[0].send(MetaSignal::new(0.5));
+        activities[1].send(MetaSignal::new(0.25));
+        fusion.update();
+        assert_eq!(*fusion.output_port.get_last_data(), 2.0);
+        assert_eq!(*fusion.meta_signals.target_rating.get_last_data(), MetaSignal::new(0.3125 / 0.75));
+        assert_eq!(*fusion.meta_signals.activity.get_last_data(), MetaSignal::new(0.3125 / 0.75));
+
+        // Without activity, the last blended value is kept.
+        activities[0].send(MetaSignal::LOW);
+        activities[1].send(MetaSignal::LOW);
+        fusion.update();
+        assert_eq!(*fusion.output_port.get_last_data(), 2.0);
+        assert_eq!(*fusion.meta_signals.activity.get_last_data(), MetaSignal::LOW);
+        // The activity weights the activities by themselves, the target rating weights the target ratings.
+        let m = MetaSignal::new;
+        assert_eq!(WeightedAverageFusion::weights(&[m(0.5), m(0.25)], &[m(0.25), m(1.0)]).rating,
+            FusionRating { activity: m(0.3125 / 0.75), target_rating: m(0.375 / 0.75) });
+    }
+}
@@ -0,0 +1,86 @@
+use ports::prelude::PortMethods;
+use meta_signals::MetaSignal;
+use crate::modules::channel_fusion::{blend, weighted_target_rating, FusionStrategy, FusionWeights};
+use crate::modules::general_fusion::{FusionRating, GeneralFusion, GeneralFusionTrait, WeightedData};
+
+#[derive(Default, PortMethods)]
+pub struct WeightedSumFusion { }
+
+/// Weights the inputs by their activities, so the fused value is `Σ aᵢ·uᵢ`.
+/// If the total activity exceeds HIGH, the sum is divided by it, so the output never exceeds
+/// the largest input. The activity is the total activity, clamped to HIGH, the target rating
+/// is the mean of the target ratings weighted by the activities, `Σ aᵢ·rᵢ / Σ aᵢ`.
+impl FusionStrategy for WeightedSumFusion {
+    fn weights(activities: &[MetaSignal], target_ratings: &[MetaSignal]) -> FusionWeights {
+        let total: f64 = activities.iter().map(|activity| **activity).sum();
+        FusionWeights {
+            weights: activities.iter().map(|activity| **activity).collect(),
+            normalization: total.max(1.0),
+            rating: FusionRating {
+                activity: MetaSignal::new(total),
+                target_rating: weighted_target_rating(activities, target_ratings),
+            },
+        }
+    }
+}
+
+/// A fusion scheduling that sends the sum of the data ports weighted by their activities.
+/// If all activities are zero, the output port keeps its last value and the target rating is LOW.
+impl<D: WeightedData> GeneralFusionTrait<D> for WeightedSumFusion {
+    fn fuse(module: &mut GeneralFusion<Self, D>) -> FusionRating {
+        let (activities, target_ratings) = module.meta_signals.inputs();
+        let weights = Self::weights(&activities, &target_ratings);
+        if let Some(data) = blend(module.data_ports.iter().map(|port| port.get_data()), &weights) {
+            module.output_port.send(data);
+        }
+        weights.rating
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ports::prelude::*;
+    use scheduling::Module;
+    use meta_signals::MetaSignal;
+    use crate::modules::general_fusion::GeneralFusionTrait;
+    use crate::modules::maximum_fusion::MaximumFusion;
+    use crate::modules::weighted_average_fusion::WeightedAverageFusion;
+    use super::WeightedSumFusion;
+
+    /// Fuses the inputs given as `(data, activity)` and returns the output and the activity.
+    fn fuse<M: GeneralFusionTrait<f64>>(inputs: &[(f64, f64)]) -> (f64, MetaSignal) {
+        let mut fusion = M::new();
+        let mut ports = Vec::new();
+        for &(data, activity) in inputs {
+            let mut data_port = SendPort::new(0.0);
+            let mut activity_port = SendPort::new(MetaSignal::LOW);
+            data_port.send(data);
+            activity_port.send(MetaSignal::new(activity));
+            fusion.add_module(&data_port, &activity_port);
+            ports.push((data_port, activity_port));
+        }
+        // Read through a connected input, the maximum fusion forwards the selected data port.
+        let mut output = ReceivePort::new(0.0);
+        output.connect_from(&fusion.output_port).unwrap();
+        fusion.update();
+        output.update();
+        (*output.get_data(), *fusion.meta_signals.activity.get_last_data())
+    }
+
+    #[test]
+    fn fusion_modes() {
+        let inputs = [(1.0, 0.5), (4.0, 0.25), (10.0, 0.0)];
+        assert_eq!(fuse::<MaximumFusion>(&inputs), (1.0, MetaSignal::new(0.5)));
+        assert_eq!(fuse::<WeightedAverageFusion>(&inputs), (2.0, MetaSignal::new(0.3125 / 0.75)));
+        assert_eq!(fuse::<WeightedSumFusion>(&inputs), (1.5, MetaSignal::new(0.75)));
+
+        // A total activity above HIGH scales the sum down to the weighted average.
+        let inputs = [(1.0, 0.75), (4.0, 0.75), (10.0, 0.5)];
+        assert_eq!(fuse::<WeightedSumFusion>(&inputs), (4.375, MetaSignal::HIGH));
+        assert_eq!(fuse::<WeightedAverageFusion>(&inputs).0, 4.375);
+
+        let inactive = [(1.0, 0.0), (4.0, 0.0)];
+        assert_eq!(fuse::<WeightedSumFusion>(&inactive), (0.0, MetaSignal::LOW));
+        assert_eq!(fuse::<WeightedAverageFusion>(&inactive), (0.0, MetaSignal::LOW));
+    }
+}
@@ -0,0 +1,164 @@
+use std::sync::Arc;
+use std::time::Duration;
+use ports::prelude::{PortDirection, PortEntry};
+use scheduling::{GroupSnapshot, PortSnapshot, Runtime};
+use serde::Serialize;
+use tiny_http::{Header, Method, Request, Response};
+use crate::{stream, Context};
+
+/// A failed request, answered with the status and `{"error": message}`.
+pub(crate) struct ApiError {
+    pub(crate) status: u16,
+    pub(crate) message: String,
+}
+
+impl ApiError {
+    pub(crate) fn new(status: u16, message: impl Into<String>) -> Self {
+        Self { status, message: message.into() }
+    }
+
+    fn not_found(what: &str, path: &str) -> Self {
+        Self::new(404, format!("{what} `{path}` not found"))
+    }
+}
+
+/// A group with the paths of its direct children.
+#[derive(Serialize)]
+struct GroupView {
+    group: GroupSnapshot,
+    groups: Vec<String>,
+    modules: Vec<String>,
+}
+
+/// Answers a request. WebSocket requests are handed to a new thread.
+pub(crate) fn handle(context: &Arc<Context>, mut request: Request) {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let path = decode(path, false);
+    let method = request.method().clone();
+    let result = match (method, path.strip_prefix("/api/")) {
+        (Method::Get, Some("stream")) => return stream::open(context, request, query),
+        (Method::Get, Some(route)) => get(&context.runtime.lock().unwrap(), route),
+        (Method::Put, Some(route)) => {
+            let mut value = String::new();
+            match request.as_reader().read_to_string(&mut value) {
+                Ok(_) => put(&context.runtime.lock().unwrap(), route, value.trim()),
+                Err(error) => Err(ApiError::new(400, error.to_string())),
+            }
+        }
+        _ => Err(ApiError::new(404, format!("no route for `{path}`"))),
+    };
+    respond(request, result);
+}
+
+pub(crate) fn respond(request: Request, result: Result<String, ApiError>) {
+    let (status, body) = match result {
+        Ok(body) => (200, body),
+        Err(error) => (error.status, serde_json::json!({ "error": error.message }).to_string()),
+    };
+    let header = Header::from_bytes("Content-Type", "application/json").unwrap();
+    let _ = request.respond(Response::from_string(body).with_status_code(status).with_header(header));
+}
+
+fn get(runtime: &Runtime, route: &str) -> Result<String, ApiError> {
+    match route.split_once('/') {
+        None if route == "snapshot" => Ok(to_json(&runtime.snapshot())),
+        None if route == "groups" => group(runtime, ""),
+        Some(("groups", path)) => group(runtime, path),
+        Some(("modules", path)) => {
+            let snapshot = runtime.snapshot();
+            let module = snapshot.module(path).ok_or_else(|| ApiError::not_found("module", path))?;
+            Ok(to_json(module))
+        }
+        Some(("ports", path)) => Ok(to_json(&PortSnapshot::from(&find_port(runtime, path)?))),
+        _ => Err(ApiError::new(404, format!("no route for `/api/{route}`"))),
+    }
+}
+
+fn group(runtime: &Runtime, path: &str) -> Result<String, ApiError> {
+    let snapshot = runtime.snapshot();
+    let group = snapshot.group(path).ok_or_else(|| ApiError::not_found("group", path))?;
+    let is_child = |child: &str| match path {
+        "" => !child.is_empty() && !child.contains('/'),
+        _ => child.strip_prefix(path).and_then(|name| name.strip_prefix('/')).is_some_and(|name| !name.contains('/')),
+    };
+    Ok(to_json(&GroupView {
+        group: group.clone(),
+        groups: snapshot.groups.iter().map(|group| group.path.clone()).filter(|path| is_child(path)).collect(),
+        modules: snapshot.modules.iter().map(|module| module.path.clone()).filter(|path| is_child(path)).collect(),
+    }))
+}
+
+/// Serializes to JSON, which can not fail for the snapshot types.
+pub(crate) fn to_json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).unwrap()
+}
+
+/// Sets a parameter or an input that is not connected. Writing to a connected input
+/// would overwrite the output it is connected to.
+fn put(runtime: &Runtime, route: &str, value: &str) -> Result<String, ApiError> {
+    let Some(("ports", path)) = route.split_once('/') else {
+        return Err(ApiError::new(404, format!("no route for `/api/{route}`")));
+    };
+    let entry = find_port(runtime, path)?;
+    match entry.direction {
+        PortDirection::Output => return Err(ApiError::new(403, format!("output `{path}` can not be set"))),
+        PortDirection::Input if entry.port.is_connected() => {
+            return Err(ApiError::new(409, format!("input `{path}` is connected")));
+        }
+        PortDirection::Input | PortDirection::Parameter => {}
+    }
+    entry.port.deserialize(value).map_err(|error| ApiError::new(400, error.to_string()))?;
+    Ok(to_json(&PortSnapshot::from(&entry)))
+}
+
+/// Finds a port by `module path.port name`.
+pub(crate) fn find_port(runtime: &Runtime, path: &str) -> Result<PortEntry, ApiError> {
+    let (module, port) = path.rsplit_once('.').ok_or_else(|| ApiError::not_found("port", path))?;
+    runtime.find_module(module)
+        .and_then(|id| runtime.module_ports(id))
+        .and_then(|ports| ports.iter().find(|entry| entry.name == port))
+        .cloned()
+        .ok_or_else(|| ApiError::not_found("port", path))
+}
+
+/// Values of a query string, e.g. all `port` values of `port=a.b&port=c.d`.
+pub(crate) fn query_values<'a>(query: &'a str, key: &'a str) -> impl Iterator<Item = String> + 'a {
+    query.split('&')
+        .filter_map(move |pair| pair.strip_prefix(key)?.strip_prefix('='))
+        .map(|value| decode(value, true))
+}
+
+/// Shortest `interval` of a stream, so a stream cannot keep a core busy.
+const MIN_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The `interval` of a query in milliseconds, at least [`MIN_INTERVAL`].
+pub(crate) fn query_interval(query: &str) -> Result<Duration, ApiError> {
+    let Some(interval) = query_values(query, "interval").next() else {
+        return Ok(Duration::from_millis(100));
+    };
+    match interval.parse().map(Duration::from_millis) {
+        Ok(interval) if interval >= MIN_INTERVAL => Ok(interval),
+        Ok(_) => Err(ApiError::new(400, format!("the interval must be at least {} ms", MIN_INTERVAL.as_millis()))),
+        Err(_) => Err(ApiError::new(400, format!("`{interval}` is not a valid interval"))),
+    }
+}
+
+/// Decodes percent-encoded characters, e.g. the brackets of `data_ports[0]`, and `+` in queries.
+fn decode(encoded: &str, query: bool) -> String {
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        let hex = tail.get(..2).and_then(|hex| std::str::from_utf8(hex).ok());
+        match (byte, hex.and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'%', Some(decoded)) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            (b'+', _) if query => bytes.push(b' '),
+            _ => bytes.push(byte),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
@@ -0,0 +1,177 @@
+//! Local HTTP server to inspect a running system, e.g. from a browser on a laptop connected to the robot.
+//!
+//! Modules are addressed by their path as in [`Runtime::find_module`] and ports by
+//! `module path.port name`, like parameters in parameter files. Values are rendered with
+//! `PortSerialize` and set with `PortDeserialize`. All responses are JSON.
+//!
+//! | Request | Response |
+//! |---|---|
+//! | `GET /api/snapshot` | the [`scheduling::RuntimeSnapshot`] |
+//! | `GET /api/groups/<group path>` | the group with the paths of its child groups and modules |
+//! | `GET /api/modules/<module path>` | the [`scheduling::ModuleSnapshot`] |
+//! | `GET /api/ports/<module path>.<port>` | the [`scheduling::PortSnapshot`] |
+//! | `PUT /api/ports/<module path>.<port>` | sets a parameter or unconnected input to the request body |
+//! | `GET /api/stream?port=<port path>&interval=<ms>` | WebSocket sending the changed values of the ports, every 100 ms by default and at most every 10 ms |
+//!
+//! ```ignore
+//! let runtime = Arc::new(Mutex::new(group.spawn()));
+//! let _server = InspectionServer::start(Arc::clone(&runtime), "127.0.0.1:8080")?;
+//! ```
+
+mod api;
+mod stream;
+
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use scheduling::Runtime;
+use tiny_http::Server;
+
+/// A running inspection server, stopped when dropped.
+pub struct InspectionServer {
+    server: Arc<Server>,
+    context: Arc<Context>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// State shared by the request handlers.
+pub(crate) struct Context {
+    pub(crate) runtime: Arc<Mutex<Runtime>>,
+    /// Cleared on drop to close the streams.
+    pub(crate) running: AtomicBool,
+    pub(crate) streams: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl InspectionServer {
+    /// Serves requests for `runtime` on `address` in a new thread.
+    /// Use a loopback address like `127.0.0.1:8080`, the server has no authentication.
+    pub fn start(runtime: Arc<Mutex<Runtime>>, address: impl ToSocketAddrs) -> io::Result<Self> {
+        let server = Arc::new(Server::http(address).map_err(io::Error::other)?);
+        let context = Arc::new(Context {
+            runtime,
+            running: AtomicBool::new(true),
+            streams: Mutex::new(Vec::new()),
+        });
+        let thread = {
+            let server = Arc::clone(&server);
+            let context = Arc::clone(&context);
+            std::thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    api::handle(&context, request);
+                }
+            })
+        };
+        Ok(Self { server, context, thread: Some(thread) })
+    }
+
+    /// The address the server is listening on, e.g. to find the port when started on port 0.
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+}
+
+impl Drop for InspectionServer {
+    fn drop(&mut self) {
+        self.context.running.store(false, Ordering::Relaxed);
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        for stream in std::mem::take(&mut *self.context.streams.lock().unwrap()) {
+            let _ = stream.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpStream};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use ports::prelude::*;
+    use scheduling::{GroupBuilder, Module, ModuleBuilder, RuntimeSnapshot, SpawnMode};
+    use tungstenite::Message;
+    use crate::InspectionServer;
+
+    #[derive(PortMethods, Default)]
+    struct Scale {
+        pub input: ReceivePort<i32>,
+        pub factor: ParameterPort<i32>,
+        pub output: SendPort<i32>,
+    }
+
+    impl Module for Scale {
+        fn update(&mut self) {
+            self.update_ports();
+            let output = self.input.get_data() * self.factor.get_data();
+            self.output.send(output);
+        }
+
+        fn interface(&self) -> Vec<PortEntry> {
+            self.ports()
+        }
+    }
+
+    /// Sends a request and returns status code and body.
+    fn request(address: SocketAddr, method: &str, path: &str, body: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "{method} {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        (status, body.to_string())
+    }
+
+    #[test]
+    fn inspect_and_set_ports() {
+        let mut group = GroupBuilder::empty().with_name("arm");
+        group.add_module(ModuleBuilder::new(Scale::default(), Duration::from_millis(2), SpawnMode::GroupThread)
+            .with_name("scale"));
+        let mut builder = GroupBuilder::empty();
+        builder.add_group(group);
+        let runtime = Arc::new(Mutex::new(builder.spawn()));
+        let server = InspectionServer::start(Arc::clone(&runtime), "127.0.0.1:0").unwrap();
+        let address = server.address().unwrap();
+
+        let (status, body) = request(address, "GET", "/api/snapshot", "");
+        assert_eq!(status, 200);
+        let snapshot: RuntimeSnapshot = serde_json::from_str(&body).unwrap();
+        assert_eq!(snapshot.module("arm/scale").unwrap().ports.len(), 3);
+        let (_, body) = request(address, "GET", "/api/groups/arm", "");
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&body).unwrap()["modules"], serde_json::json!(["arm/scale"]));
+
+        let Err(tungstenite::Error::Http(response)) = tungstenite::connect(
+            format!("ws://{address}/api/stream?port=arm/scale.output&interval=0")
+        ) else {
+            panic!("a stream with a zero interval was opened");
+        };
+        assert_eq!(response.status(), 400);
+        let (mut socket, _) = tungstenite::connect(
+            format!("ws://{address}/api/stream?port=arm/scale.output&interval=10")
+        ).unwrap();
+        assert_eq!(request(address, "PUT", "/api/ports/arm/scale.input", "3").0, 200);
+        assert_eq!(request(address, "PUT", "/api/ports/arm/scale.factor", "2").0, 200);
+        loop {
+            let Message::Text(values) = socket.read().unwrap() else { continue };
+            if values.as_str() == r#"{"arm/scale.output":"6"}"# {
+                break;
+            }
+        }
+
+        let (status, body) = request(address, "PUT", "/api/ports/arm/scale.output", "1");
+        assert_eq!((status, body.as_str()), (403, r#"{"error":"output `arm/scale.output` can not be set"}"#));
+        assert_eq!(request(address, "GET", "/api/ports/arm/scale.missing", "").0, 404);
+        assert_eq!(request(address, "PUT", "/api/ports/arm/scale.factor", "fast").0, 400);
+        let (_, body) = request(address, "GET", "/api/ports/arm/scale.factor", "");
+        assert_eq!(body, r#"{"name":"factor","direction":"Parameter","type_name":"i32","value":"2"}"#);
+
+        drop(socket);
+        drop(server);
+        Arc::into_inner(runtime).unwrap().into_inner().unwrap().shutdown();
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use scheduling::PortSnapshot;
+use tiny_http::{Header, Request, Response};
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+use crate::api::{find_port, query_interval, query_values, respond, to_json, ApiError};
+use crate::Context;
+
+/// Upgrades a request for `/api/stream` to a WebSocket sending the values of the ports in
+/// the query every `interval`. Only changed values are sent, the first message contains all.
+/// Answers with an error if the query names unknown ports or it is no WebSocket request.
+pub(crate) fn open(context: &Arc<Context>, request: Request, query: &str) {
+    let key = request.headers().iter()
+        .find(|header| header.field.equiv("Sec-WebSocket-Key"))
+        .map(|header| derive_accept_key(header.value.as_bytes()));
+    let Some(accept) = key else {
+        return respond(request, Err(ApiError::new(400, "expected a WebSocket request")));
+    };
+    let interval = match query_interval(query) {
+        Ok(interval) => interval,
+        Err(error) => return respond(request, Err(error)),
+    };
+    let ports = {
+        let runtime = context.runtime.lock().unwrap();
+        query_values(query, "port")
+            .map(|path| find_port(&runtime, &path).map(|entry| (path, entry)))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let ports = match ports {
+        Ok(ports) => ports,
+        Err(error) => return respond(request, Err(error)),
+    };
+
+    let response = Response::empty(101)
+        .with_header(Header::from_bytes("Upgrade", "websocket").unwrap())
+        .with_header(Header::from_bytes("Connection", "Upgrade").unwrap())
+        .with_header(Header::from_bytes("Sec-WebSocket-Accept", accept).unwrap());
+    let stream = request.upgrade("websocket", response);
+    let thread_context = Arc::clone(context);
+    let thread = std::thread::spawn(move || {
+        let context = thread_context;
+        let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+        let mut sent = HashMap::new();
+        while context.running.load(Ordering::Relaxed) {
+            let changed: HashMap<_, _> = ports.iter()
+                .map(|(path, entry)| (path, PortSnapshot::from(entry).value))
+                .filter(|(path, value)| sent.get(*path) != Some(value))
+                .collect();
+            if !changed.is_empty() {
+                if socket.send(Message::text(to_json(&changed))).is_err() {
+                    return;
+                }
+                sent.extend(changed.into_iter().map(|(path, value)| (path.clone(), value)));
+            }
+            std::thread::sleep(interval);
+        }
+        let _ = socket.close(None);
+    });
+    let mut streams = context.streams.lock().unwrap();
+    // Streams end when their client disconnects, only running ones are joined on drop.
+    streams.retain(|stream| !stream.is_finished());
+    streams.push(thread);
+}
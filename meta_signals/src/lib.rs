@@ -1,3 +1,4 @@
+use serialization::{PortDeserialize, PortSerialize};
 use std::{fmt::Display, ops::{Add, AddAssign, Deref, Div, DivAssign, Mul, MulAssign, Sub, SubAssign}};
 
 /// A meta-signal representing a value between 0.0 and 1.0 inclusive.
@@ -23,9 +24,14 @@ impl Deref for MetaSignal {
 }
 
 impl MetaSignal {
-    /// Creates a new MetaSignal, clamping the value between 0.0 and 1.0.
+    /// Creates a new MetaSignal, clamping the value between 0.0 and 1.0. NaN becomes LOW,
+    /// so meta signals can always be compared.
     pub fn new(value: f64) -> Self {
-        Self { value: value.clamp(0.0, 1.0) }
+        if value.is_nan() {
+            return Self::LOW;
+        }
+        // Adding zero turns -0.0 into 0.0, which the total order would sort below LOW.
+        Self { value: value.clamp(0.0, 1.0) + 0.0 }
     }
 
     pub const LOW: MetaSignal = MetaSignal { value: 0.0 };
@@ -71,23 +77,21 @@ impl Add<f64> for MetaSignal {
 impl AddAssign for MetaSignal {
     /// Adds another MetaSignal to this one, clamping the result to a maximum of 1.0.
     fn add_assign(&mut self, other: Self) {
-        self.value = f64::min(self.value + other.value, 1.0);
+        *self = MetaSignal::new(self.value + other.value);
     }
 }
 
 impl AddAssign<MetaSignal> for f64 {
     /// Adds a MetaSignal to this f64, clamping the result between 0.0 and 1.0.
     fn add_assign(&mut self, other: MetaSignal) {
-        let value = *self + other.value;
-        *self = value.clamp(0.0, 1.0);
+        *self = *MetaSignal::new(*self + other.value);
     }
 }
 
 impl AddAssign<f64> for MetaSignal {
     /// Adds a f64 to this MetaSignal, clamping the result between 0.0 and 1.0.
     fn add_assign(&mut self, other: f64) {
-        let value = self.value + other;
-        self.value = value.clamp(0.0, 1.0);
+        *self = MetaSignal::new(self.value + other);
     }
 }
 
@@ -124,23 +128,21 @@ impl Sub<f64> for MetaSignal {
 impl SubAssign for MetaSignal {
     /// Subtracts another MetaSignal from this one, clamping the result to a minimum of 0.0.
     fn sub_assign(&mut self, other: Self) {
-        self.value = f64::max(self.value - other.value, 0.0);
+        *self = MetaSignal::new(self.value - other.value);
     }
 }
 
 impl SubAssign<MetaSignal> for f64 {
     /// Subtracts a MetaSignal from this f64, clamping the result between 0.0 and 1.0.
     fn sub_assign(&mut self, other: MetaSignal) {
-        let value = *self - other.value;
-        *self = value.clamp(0.0, 1.0);
+        *self = *MetaSignal::new(*self - other.value);
     }
 }
 
 impl SubAssign<f64> for MetaSignal {
     /// Subtracts a f64 from this MetaSignal, clamping the result between 0.0 and 1.0.
     fn sub_assign(&mut self, other: f64) {
-        let value = self.value - other;
-        self.value = value.clamp(0.0, 1.0);
+        *self = MetaSignal::new(self.value - other);
     }
 }
 
@@ -173,26 +175,23 @@ impl Mul<f64> for MetaSignal {
 }
 
 impl MulAssign for MetaSignal {
-    /// Multiplies this MetaSignal by another. Bounds checking is not necessary
-    /// since both values are guaranteed to be between 0.0 and 1.0.
+    /// Multiplies this MetaSignal by another.
     fn mul_assign(&mut self, rhs: MetaSignal) {
-        self.value *= rhs.value;
+        *self = MetaSignal::new(self.value * rhs.value);
     }
 }
 
 impl MulAssign<MetaSignal> for f64 {
     /// Multiplies this f64 by a MetaSignal, clamping the result between 0.0 and 1.0.
     fn mul_assign(&mut self, rhs: MetaSignal) {
-        let value = *self * rhs.value;
-        *self = value.clamp(0.0, 1.0);
+        *self = *MetaSignal::new(*self * rhs.value);
     }
 }
 
 impl MulAssign<f64> for MetaSignal {
     /// Multiplies this MetaSignal by a f64, clamping the result between 0.0 and 1.0.
     fn mul_assign(&mut self, rhs: f64) {
-        let value = self.value * rhs;
-        self.value = value.clamp(0.0, 1.0);
+        *self = MetaSignal::new(self.value * rhs);
     }
 }
 
@@ -241,12 +240,7 @@ impl Div<f64> for MetaSignal {
 impl DivAssign for MetaSignal {
     /// Divides this MetaSignal by another, clamping the result to a maximum of 1.0.
     fn div_assign(&mut self, rhs: MetaSignal) {
-        if rhs == 0.0 {
-            self.value = 1.0;
-        } else {
-            let value = self.value / *rhs;
-            self.value = f64::min(value, 1.0);
-        }
+        *self = *self / rhs;
     }
 }
 
@@ -254,12 +248,7 @@ impl DivAssign<MetaSignal> for f64 {
     /// Divides this f64 by a MetaSignal, clamping the result between 0.0 and 1.0.
     /// Division by zero results in a value of 1.0.
     fn div_assign(&mut self, rhs: MetaSignal) {
-        if rhs == 0.0 {
-            *self = 1.0;
-        } else {
-            let value = *self / *rhs;
-            *self = value.clamp(0.0, 1.0);
-        }
+        *self = *(*self / rhs);
     }
 }
 
@@ -267,12 +256,7 @@ impl DivAssign<f64> for MetaSignal {
     /// Divides this MetaSignal by a f64, clamping the result between 0.0 and 1.0.
     /// Division by zero results in a MetaSignal of value 1.0.
     fn div_assign(&mut self, rhs: f64) {
-        if rhs == 0.0 {
-            self.value = 1.0;
-        } else {
-            let value = self.value / rhs;
-            self.value = value.clamp(0.0, 1.0);
-        }
+        *self = *self / rhs;
     }
 }
 
@@ -298,7 +282,7 @@ impl Eq for MetaSignal {}
 
 impl PartialOrd for MetaSignal {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.value.partial_cmp(&other.value)
+        Some(self.cmp(other))
     }
 }
 
@@ -316,7 +300,7 @@ impl PartialOrd<MetaSignal> for f64 {
 
 impl Ord for MetaSignal {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.value.partial_cmp(&other.value).unwrap()
+        self.value.total_cmp(&other.value)
     }
 }
 
@@ -326,9 +310,49 @@ impl Display for MetaSignal {
     }
 }
 
+impl PortSerialize for MetaSignal {
+    fn serialize(&self) -> String {
+        self.value.serialize()
+    }
+}
+
+impl PortDeserialize for MetaSignal {
+    /// Values outside of 0.0 to 1.0 are clamped, NaN and infinite values are rejected.
+    fn deserialize(data: &str) -> Option<Self> {
+        f64::deserialize(data).filter(|value| value.is_finite()).map(MetaSignal::new)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use serialization::PortDeserialize;
     use super::MetaSignal;
+
+    #[test]
+    fn not_a_number() {
+        assert_eq!(MetaSignal::new(f64::NAN), MetaSignal::LOW);
+        assert_eq!(MetaSignal::deserialize("NaN"), None);
+        assert_eq!(MetaSignal::deserialize("inf"), None);
+        assert_eq!(MetaSignal::deserialize("0.5"), Some(MetaSignal::new(0.5)));
+        assert_eq!([MetaSignal::new(f64::NAN), MetaSignal::HIGH].into_iter().max(), Some(MetaSignal::HIGH));
+        assert_eq!(MetaSignal::new(-0.0).cmp(&MetaSignal::LOW), std::cmp::Ordering::Equal);
+
+        let mut signal = MetaSignal::HIGH;
+        signal += f64::NAN;
+        assert_eq!(signal, MetaSignal::LOW);
+        signal = MetaSignal::HIGH;
+        signal *= f64::NAN;
+        assert_eq!(signal, MetaSignal::LOW);
+        signal = MetaSignal::HIGH;
+        signal /= f64::INFINITY;
+        assert_eq!(signal, MetaSignal::LOW);
+        let mut value = 0.5;
+        value -= MetaSignal::HIGH;
+        assert_eq!(value, 0.0);
+        value += MetaSignal::new(0.25);
+        assert_eq!(value, 0.25);
+    }
+
     #[test]
     fn test_meta_signal() {
         let mut a = MetaSignal::new(0.5);
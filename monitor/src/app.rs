@@ -0,0 +1,261 @@
+use std::io;
+use std::time::Duration;
+use ports::prelude::PortDirection;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Gauge, List, ListItem, ListState, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use scheduling::{GroupSnapshot, ModuleSnapshot, ModuleState, RuntimeSnapshot};
+use crate::source::SnapshotSource;
+
+/// Ports shown as bars if they carry a `MetaSignal`, with all ports of the stimulation and inhibition `Vec`s.
+const META_SIGNALS: [&str; 4] = ["stimulations", "inhibitions", "activity", "target_rating"];
+
+/// An entry of the tree, groups are followed by their children.
+enum Entry<'a> {
+    Group(&'a GroupSnapshot),
+    Module(&'a ModuleSnapshot),
+}
+
+impl Entry<'_> {
+    fn path(&self) -> &str {
+        match self {
+            Entry::Group(group) => &group.path,
+            Entry::Module(module) => &module.path,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct App {
+    snapshot: Option<RuntimeSnapshot>,
+    /// Why the last snapshot could not be taken.
+    error: Option<String>,
+    /// Index of the selected tree entry.
+    selected: usize,
+}
+
+impl App {
+    pub(crate) fn run(&mut self, terminal: &mut DefaultTerminal, source: &mut impl SnapshotSource, refresh: Duration) -> io::Result<()> {
+        loop {
+            self.update(source);
+            terminal.draw(|frame| self.render(frame))?;
+            if !event::poll(refresh)? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+                    KeyCode::Down => self.selected += 1,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Takes a new snapshot, keeping the last one if the source fails.
+    pub(crate) fn update(&mut self, source: &mut impl SnapshotSource) {
+        match source.snapshot() {
+            Ok(snapshot) => {
+                self.snapshot = Some(snapshot);
+                self.error = None;
+            }
+            Err(error) => self.error = Some(error.to_string()),
+        }
+    }
+
+    pub(crate) fn render(&mut self, frame: &mut Frame) {
+        let [main, status] = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+        let status_line = match &self.error {
+            Some(error) => Line::styled(format!("Disconnected: {error}"), Style::new().fg(Color::Red)),
+            None => Line::raw("↑/↓ select, q quit"),
+        };
+        frame.render_widget(status_line, status);
+        let Some(snapshot) = &self.snapshot else {
+            return;
+        };
+
+        let entries = tree(snapshot);
+        self.selected = self.selected.min(entries.len().saturating_sub(1));
+        let [tree_area, details] = Layout::horizontal([Constraint::Percentage(35), Constraint::Fill(1)]).areas(main);
+        let items = entries.iter().map(|entry| {
+            let path = entry.path();
+            let depth = path.split('/').filter(|name| !name.is_empty()).count();
+            let name = match path.rsplit_once('/') {
+                Some((_, name)) => name,
+                None if path.is_empty() => "system",
+                None => path,
+            };
+            let (marker, state) = match entry {
+                Entry::Group(group) => ("▾ ", group.state),
+                Entry::Module(module) => ("• ", module.state),
+            };
+            ListItem::new(format!("{}{marker}{name}", "  ".repeat(depth))).style(state_style(state))
+        });
+        let list = List::new(items)
+            .block(Block::bordered().title("Groups and modules"))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, tree_area, &mut ListState::default().with_selected(Some(self.selected)));
+
+        match entries.get(self.selected) {
+            Some(Entry::Group(group)) => render_group(frame, details, snapshot, group),
+            Some(Entry::Module(module)) => render_module(frame, details, module),
+            None => {}
+        }
+    }
+}
+
+/// Groups and modules in tree order.
+fn tree(snapshot: &RuntimeSnapshot) -> Vec<Entry<'_>> {
+    let mut entries: Vec<_> = snapshot.groups.iter().map(Entry::Group)
+        .chain(snapshot.modules.iter().map(Entry::Module))
+        .collect();
+    entries.sort_by(|a, b| {
+        let segments = |entry: &Entry| entry.path().split('/').filter(|name| !name.is_empty()).map(str::to_string).collect::<Vec<_>>();
+        segments(a).cmp(&segments(b))
+    });
+    entries
+}
+
+fn state_style(state: ModuleState) -> Style {
+    match state {
+        ModuleState::Running => Style::new(),
+        ModuleState::Paused => Style::new().fg(Color::Yellow),
+        ModuleState::Disabled => Style::new().fg(Color::DarkGray),
+        ModuleState::Failed => Style::new().fg(Color::Red),
+    }
+}
+
+fn duration(duration: Option<Duration>) -> String {
+    duration.map_or_else(|| "-".to_string(), |duration| format!("{duration:.1?}"))
+}
+
+fn render_group(frame: &mut Frame, area: Rect, snapshot: &RuntimeSnapshot, group: &GroupSnapshot) {
+    let prefix = format!("{}/", group.path);
+    let modules = snapshot.modules.iter()
+        .filter(|module| group.path.is_empty() || module.path.starts_with(&prefix))
+        .count();
+    let lines = vec![
+        Line::raw(format!("State:       {:?}", group.state)),
+        Line::raw(format!("Cycle time:  {}", duration(group.cycle_time))),
+        Line::raw(format!("Modules:     {modules}")),
+    ];
+    let title = if group.path.is_empty() { "system" } else { &group.path };
+    frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(title.to_string())), area);
+}
+
+fn render_module(frame: &mut Frame, area: Rect, module: &ModuleSnapshot) {
+    let block = Block::bordered().title(module.path.clone());
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let meta_signals: Vec<_> = META_SIGNALS.iter()
+        .flat_map(|name| module.ports.iter().filter(move |port| {
+            port.name.split_once('[').map_or(port.name.as_str(), |(field, _)| field) == *name
+        }))
+        .filter(|port| port.type_name.ends_with("MetaSignal"))
+        .collect();
+    let [timing, bars, ports] = Layout::vertical([
+        Constraint::Length(5),
+        Constraint::Length(meta_signals.len() as u16),
+        Constraint::Fill(1),
+    ]).spacing(1).areas(inner);
+
+    let statistics = module.statistics.unwrap_or_default();
+    let lines = vec![
+        Line::styled(format!("State:          {:?}", module.state), state_style(module.state)),
+        Line::raw(format!("Cycle time:     {}", duration(module.cycle_time))),
+        Line::raw(format!("Measured cycle: {}", duration(statistics.measured_cycle_time))),
+        Line::raw(format!("Update time:    {} (max {})", duration(statistics.update_time), duration(Some(statistics.max_update_time)))),
+        Line::raw(format!("Updates:        {}", statistics.updates)),
+    ];
+    frame.render_widget(Paragraph::new(lines), timing);
+
+    let rows = Layout::vertical(vec![Constraint::Length(1); meta_signals.len()]).split(bars);
+    for (port, area) in meta_signals.iter().zip(rows.iter()) {
+        let value: f64 = port.value.as_deref().and_then(|value| value.parse().ok()).unwrap_or_default();
+        let gauge = Gauge::default()
+            .ratio(value.clamp(0.0, 1.0))
+            .label(format!("{:<16}{value:.2}", port.name))
+            .gauge_style(Style::new().fg(Color::Cyan));
+        frame.render_widget(gauge, *area);
+    }
+
+    let rows = module.ports.iter().map(|port| {
+        let direction = match port.direction {
+            PortDirection::Input => "in",
+            PortDirection::Output => "out",
+            PortDirection::Parameter => "param",
+        };
+        Row::new(vec![port.name.clone(), direction.to_string(), port.value.clone().unwrap_or_else(|| "-".to_string())])
+    });
+    let table = Table::new(rows, [Constraint::Percentage(40), Constraint::Length(6), Constraint::Fill(1)])
+        .header(Row::new(["Port", "", "Value"]).style(Style::new().add_modifier(Modifier::BOLD)));
+    frame.render_widget(table, ports);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use meta_signals::MetaSignal;
+    use ports::prelude::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    use scheduling::{GroupBuilder, Module, ModuleBuilder, SpawnMode};
+    use crate::SnapshotSource;
+    use super::App;
+
+    #[derive(PortMethods, Default)]
+    struct Behavior {
+        pub activity: SendPort<MetaSignal>,
+        pub gain: ParameterPort<f64>,
+    }
+
+    impl Module for Behavior {
+        fn update(&mut self) {
+            self.activity.send(MetaSignal::new(0.5));
+        }
+
+        fn interface(&self) -> Vec<PortEntry> {
+            self.ports()
+        }
+    }
+
+    #[test]
+    fn render_module() {
+        let mut group = GroupBuilder::empty().with_name("drive");
+        group.add_module(ModuleBuilder::new(Behavior::default(), Duration::from_millis(1), SpawnMode::GroupThread)
+            .with_name("follow_wall"));
+        let mut builder = GroupBuilder::empty();
+        builder.add_group(group);
+        let mut runtime = Arc::new(Mutex::new(builder.spawn()));
+        let start = Instant::now();
+        while runtime.snapshot().unwrap().module("drive/follow_wall").unwrap().statistics.unwrap().updates == 0 {
+            assert!(start.elapsed() < Duration::from_secs(1));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        let mut app = App::default();
+        app.update(&mut runtime);
+        app.selected = 2;
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|frame| app.render(frame)).unwrap();
+        let screen: Vec<String> = terminal.backend().buffer().content.chunks(100)
+            .map(|line| line.iter().map(|cell| cell.symbol()).collect())
+            .collect();
+        let screen = screen.join("\n");
+        Arc::into_inner(runtime).unwrap().into_inner().unwrap().shutdown();
+
+        assert!(screen.contains("▾ system"), "{screen}");
+        assert!(screen.contains("    • follow_wall"), "{screen}");
+        assert!(screen.contains("Cycle time:     1.0ms"), "{screen}");
+        assert!(screen.contains("activity        0.50"), "{screen}");
+        assert!(screen.contains("gain"), "{screen}");
+    }
+}
@@ -0,0 +1,24 @@
+//! Terminal UI showing the groups and modules of a running system with their ports,
+//! IB2C meta signals and timing, e.g. over SSH on the robot.
+//!
+//! Attach in-process by running [`run`] with the runtime in an `Arc<Mutex<Runtime>>`, or start
+//! the `monitor` binary with the address of the `inspector` server of a running system:
+//!
+//! ```text
+//! monitor 127.0.0.1:8080
+//! ```
+
+mod app;
+mod source;
+
+use std::io;
+use std::time::Duration;
+pub use source::{RemoteSource, SnapshotSource};
+
+/// Shows snapshots of `source` in the terminal, refreshed every `refresh`, until `q` is pressed.
+pub fn run(mut source: impl SnapshotSource, refresh: Duration) -> io::Result<()> {
+    let mut terminal = ratatui::try_init()?;
+    let result = app::App::default().run(&mut terminal, &mut source, refresh);
+    ratatui::restore();
+    result
+}
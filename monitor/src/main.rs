@@ -0,0 +1,8 @@
+use std::time::Duration;
+use monitor::RemoteSource;
+
+/// Attaches to the inspector server at the address given as argument, `127.0.0.1:8080` by default.
+fn main() -> std::io::Result<()> {
+    let address = std::env::args().nth(1).unwrap_or_else(|| "127.0.0.1:8080".to_string());
+    monitor::run(RemoteSource::new(address), Duration::from_millis(250))
+}
@@ -0,0 +1,43 @@
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use scheduling::{Runtime, RuntimeSnapshot};
+
+/// Where the monitor gets its snapshots from.
+pub trait SnapshotSource {
+    fn snapshot(&mut self) -> io::Result<RuntimeSnapshot>;
+}
+
+/// A runtime in the same process.
+impl SnapshotSource for Arc<Mutex<Runtime>> {
+    fn snapshot(&mut self) -> io::Result<RuntimeSnapshot> {
+        Ok(self.lock().unwrap().snapshot())
+    }
+}
+
+/// The `inspector` server of a running system, e.g. `127.0.0.1:8080`.
+pub struct RemoteSource {
+    address: String,
+}
+
+impl RemoteSource {
+    pub fn new(address: impl Into<String>) -> Self {
+        Self { address: address.into() }
+    }
+}
+
+impl SnapshotSource for RemoteSource {
+    fn snapshot(&mut self) -> io::Result<RuntimeSnapshot> {
+        let mut stream = TcpStream::connect(&self.address)?;
+        write!(stream, "GET /api/snapshot HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", self.address)?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        let (head, body) = response.split_once("\r\n\r\n")
+            .ok_or_else(|| io::Error::other("incomplete response"))?;
+        let status = head.lines().next().unwrap_or_default();
+        if !status.contains(" 200 ") {
+            return Err(io::Error::other(format!("unexpected response `{status}`")));
+        }
+        serde_json::from_str(body).map_err(io::Error::other)
+    }
+}
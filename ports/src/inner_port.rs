@@ -2,6 +2,10 @@ use std::sync::{Arc, RwLock};
 use crate::port_data::PortData;
 use crate::port_type::PortType;
 
+/// Identifies a port and all handles sharing its buffer, e.g. clones and [`crate::prelude::DynPort`]s.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PortId(usize);
+
 /// Internal representation of a port.
 /// Used internally by the Ports and to connect ports together.
 /// All ports Deref to this struct to allow connection of different port types together.
@@ -59,8 +63,30 @@ impl<T> InnerPort<T> {
         }
     }
 
+    pub fn id(&self) -> PortId {
+        PortId(Arc::as_ptr(&self.port_buffer) as *const () as usize)
+    }
+
+    /// Whether this port reads from another port.
+    pub fn is_connected(&self) -> bool {
+        matches!(&*self.port_buffer.read().unwrap(), PortType::PassThrough(_))
+    }
+
+    /// Whether this port is `other` or reads from it, directly or through other ports.
+    pub(crate) fn reads_from(&self, other: &InnerPort<T>) -> bool {
+        if Arc::ptr_eq(&self.port_buffer, &other.port_buffer) {
+            return true;
+        }
+        match &*self.port_buffer.read().unwrap() {
+            PortType::Endpoint(_) => false,
+            PortType::PassThrough(source) => source.reads_from(other),
+        }
+    }
+
     /// Connects this port to a source port.
-    pub fn connect_to_source(&self, source: &InnerPort<T>) {
+    /// Unlike [`crate::prelude::ReceivePort::connect_from`], the direction of the ports
+    /// and existing connections are not checked.
+    pub(crate) fn connect_to_source(&self, source: &InnerPort<T>) {
         let mut port = self.port_buffer.write().unwrap();
         *port = PortType::PassThrough(source.clone());
     }
@@ -3,13 +3,20 @@ mod inner_port;
 mod port_type;
 mod send_port;
 mod receive_port;
+mod parameter_port;
 mod test;
 mod port_traits;
+pub mod port_reflection;
+
+// Lets the derive macros refer to `::ports` inside this crate.
+extern crate self as ports;
 
 pub mod prelude {
     pub use crate::send_port::SendPort;
-    pub use crate::receive_port::ReceivePort;
-    pub use crate::inner_port::InnerPort;
+    pub use crate::receive_port::{PortSource, ReceivePort};
+    pub use crate::parameter_port::ParameterPort;
+    pub use crate::inner_port::{InnerPort, PortId};
     pub use port_macros::PortMethods;
     pub use crate::port_traits::PortMethods;
+    pub use crate::port_reflection::{DynPort, PortDirection, PortEntry, PortError, PortReflection};
 }
\ No newline at end of file
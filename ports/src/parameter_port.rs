@@ -0,0 +1,51 @@
+use derive_more::Deref;
+use crate::inner_port::InnerPort;
+use crate::port_data::PortData;
+
+/// A port holding a tunable value of a module, e.g. a gain.
+/// Parameters are set from outside the module, e.g. from a parameter file,
+/// and read like a [`crate::prelude::ReceivePort`].
+#[derive(Deref)]
+pub struct ParameterPort<T> {
+    inner_port: InnerPort<T>,
+}
+
+impl<T> ParameterPort<T> {
+    pub fn new(data: T) -> Self {
+        Self { inner_port: InnerPort::with_default_data(PortData::new(data)) }
+    }
+
+    /// Updates the internal buffer with the latest value.
+    pub fn update(&mut self) {
+        self.inner_port.update();
+    }
+
+    /// Reads the last value from the internal buffer.
+    pub fn get_data(&self) -> &T {
+        self.inner_port.read_from_buffer().get_data()
+    }
+
+    /// Get the time the last value from the internal buffer was set.
+    pub fn get_timestamp(&self) -> std::time::Instant {
+        self.inner_port.read_from_buffer().get_timestamp()
+    }
+
+    /// Sets the value. It is visible to the module after its next update.
+    pub fn set(&self, data: T) {
+        self.inner_port.clone().write(&PortData::new(data));
+    }
+}
+
+impl<T: Default> Clone for ParameterPort<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner_port: self.inner_port.clone(),
+        }
+    }
+}
+
+impl<T: Default> Default for ParameterPort<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
@@ -1,7 +1,45 @@
 use quote::quote;
-use syn::{parse_macro_input, Fields, ItemStruct, Type};
+use syn::{parse_macro_input, parse_quote, Fields, GenericArgument, ItemStruct, PathArguments, Type, WherePredicate};
 
-#[proc_macro_derive(PortMethods)]
+/// Returns the port type name and the data type if `ty` is a port, e.g. `ReceivePort<T>`.
+fn port_type(ty: &Type) -> Option<(&syn::Ident, &Type)> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "ReceivePort" && segment.ident != "SendPort" && segment.ident != "ParameterPort" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(data) => Some((&segment.ident, data)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the element type if `ty` is a `Vec<_>`.
+fn vec_element(ty: &Type) -> Option<&Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "Vec" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(element) => Some(element),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Implements [`PortMethods`] to update all `ReceivePort` and `ParameterPort` fields, including
+/// ports in `Vec`s and fields marked `#[nested_ports]`, and `PortReflection` to list all ports.
+#[proc_macro_derive(PortMethods, attributes(nested_ports))]
 pub fn module(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(item as ItemStruct);
     let struct_name = input.ident.clone();
@@ -18,22 +56,84 @@ pub fn module(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     };
 
     let mut receive_port_updates = Vec::new();
+    let mut port_listings = Vec::new();
+    let mut reflection_bounds: Vec<WherePredicate> = Vec::new();
     for field in &fields {
         let field_name = field.ident.clone().unwrap();
 
-        if let Type::Path(type_path) = &field.ty {
-            if let Some(ident) = type_path.path.segments.last().map(|s| &s.ident) {
-                if ident == "ReceivePort" || ident == "ParameterPort" {
-                    receive_port_updates.push(quote! {
-                        self.#field_name.update();
-                    });
+        if field.attrs.iter().any(|attr| attr.path().is_ident("nested_ports")) {
+            let ty = &field.ty;
+            receive_port_updates.push(quote! {
+                self.#field_name.update_ports();
+            });
+            port_listings.push(quote! {
+                ports.extend(::ports::prelude::PortReflection::ports(&self.#field_name));
+            });
+            reflection_bounds.push(parse_quote!(#ty: ::ports::prelude::PortReflection));
+            continue;
+        }
+
+        let (port, is_vec) = match port_type(&field.ty) {
+            Some(port) => (port, false),
+            None => match vec_element(&field.ty).and_then(port_type) {
+                Some(port) => (port, true),
+                None => continue,
+            },
+        };
+        let (kind, data) = port;
+        if kind == "ReceivePort" || kind == "ParameterPort" {
+            receive_port_updates.push(if is_vec {
+                quote! {
+                    for port in &mut self.#field_name {
+                        port.update();
+                    }
                 }
-            }
+            } else {
+                quote! {
+                    self.#field_name.update();
+                }
+            });
         }
+
+        let constructor = if kind == "SendPort" {
+            quote!(output)
+        } else if kind == "ParameterPort" {
+            quote!(parameter)
+        } else {
+            quote!(input)
+        };
+        let dyn_port = quote! {
+            {
+                #[allow(unused_imports)]
+                use ::ports::port_reflection::probe::{NoDeserialize, NoSerialize, Probe, ViaDeserialize, ViaSerialize};
+                ::ports::prelude::DynPort::#constructor::<#data>(
+                    port,
+                    (&&Probe::<#data>::new()).serializer(),
+                    (&&Probe::<#data>::new()).deserializer(),
+                )
+            }
+        };
+        port_listings.push(if is_vec {
+            quote! {
+                for (index, port) in self.#field_name.iter().enumerate() {
+                    ports.push(::ports::prelude::PortEntry::new(format!("{}[{}]", stringify!(#field_name), index), #dyn_port));
+                }
+            }
+        } else {
+            quote! {
+                let port = &self.#field_name;
+                ports.push(::ports::prelude::PortEntry::new(stringify!(#field_name), #dyn_port));
+            }
+        });
+        reflection_bounds.push(parse_quote!(#data: Send + Sync + 'static));
     }
 
+    let mut reflection_generics = input.generics.clone();
+    let reflection_where = reflection_generics.make_where_clause();
+    reflection_where.predicates.extend(reflection_bounds);
+
     let expanded = quote! {
-        impl #impl_generics PortMethods for #struct_name #ty_generics
+        impl #impl_generics ::ports::prelude::PortMethods for #struct_name #ty_generics
         #where_clause
         {
             fn update_ports(&mut self) {
@@ -41,6 +141,16 @@ pub fn module(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
             }
         }
 
+        impl #impl_generics ::ports::prelude::PortReflection for #struct_name #ty_generics
+        #reflection_where
+        {
+            fn ports(&self) -> Vec<::ports::prelude::PortEntry> {
+                #[allow(unused_mut)]
+                let mut ports = Vec::new();
+                #(#port_listings)*
+                ports
+            }
+        }
     };
 
     proc_macro::TokenStream::from(expanded)
@@ -0,0 +1,305 @@
+use std::any::{type_name, Any};
+use std::fmt::{Display, Formatter};
+use crate::inner_port::{InnerPort, PortId};
+use crate::parameter_port::ParameterPort;
+use crate::port_data::PortData;
+use crate::receive_port::ReceivePort;
+use crate::send_port::SendPort;
+
+/// Lists the ports of a struct by field name.
+/// Derived together with [`crate::prelude::PortMethods`].
+pub trait PortReflection {
+    fn ports(&self) -> Vec<PortEntry>;
+}
+
+/// Direction of a listed port.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PortDirection {
+    /// A [`crate::prelude::SendPort`].
+    Output,
+    /// A [`crate::prelude::ReceivePort`].
+    Input,
+    /// A [`crate::prelude::ParameterPort`].
+    Parameter,
+}
+
+/// A named port of a struct, see [`PortReflection`].
+#[derive(Clone)]
+pub struct PortEntry {
+    pub name: String,
+    /// The direction of `port`, see [`DynPort::direction`].
+    pub direction: PortDirection,
+    pub port: DynPort,
+}
+
+impl PortEntry {
+    pub fn new(name: impl Into<String>, port: DynPort) -> Self {
+        Self { name: name.into(), direction: port.direction(), port }
+    }
+}
+
+/// Errors of type erased port operations.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PortError {
+    /// The data types of two ports differ.
+    TypeMismatch { expected: &'static str, found: &'static str },
+    /// The data type does not implement `PortSerialize`.
+    NotSerializable { type_name: &'static str },
+    /// The data type does not implement `PortDeserialize`.
+    NotDeserializable { type_name: &'static str },
+    /// `PortDeserialize` rejected the value.
+    InvalidValue { type_name: &'static str, value: String },
+    /// An input can only be connected to one source.
+    AlreadyConnected,
+    /// The source reads from the input it would be connected to.
+    Cycle,
+    /// Only inputs can be connected to a source.
+    NotAnInput,
+}
+
+impl Display for PortError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PortError::TypeMismatch { expected, found } => write!(f, "expected a port of type `{expected}`, found `{found}`"),
+            PortError::NotSerializable { type_name } => write!(f, "`{type_name}` does not implement PortSerialize"),
+            PortError::NotDeserializable { type_name } => write!(f, "`{type_name}` does not implement PortDeserialize"),
+            PortError::InvalidValue { type_name, value } => write!(f, "`{value}` is not a valid `{type_name}`"),
+            PortError::AlreadyConnected => write!(f, "the port is already connected to a source"),
+            PortError::Cycle => write!(f, "the connection would create a cycle"),
+            PortError::NotAnInput => write!(f, "only inputs can be connected to a source"),
+        }
+    }
+}
+
+impl std::error::Error for PortError {}
+
+/// A type erased handle to a port. It shares the buffer of the port it was created from,
+/// so connecting or writing through the handle affects the original port.
+pub struct DynPort {
+    port: Box<dyn AnyPort>,
+    direction: PortDirection,
+}
+
+impl DynPort {
+    /// Creates a handle to an input. `serialize` and `deserialize` are set
+    /// if the data type implements `PortSerialize` and `PortDeserialize`.
+    pub fn input<T: Send + Sync + 'static>(
+        port: &ReceivePort<T>,
+        serialize: Option<fn(&T) -> String>,
+        deserialize: Option<fn(&str) -> Option<T>>,
+    ) -> Self {
+        Self::new(port, PortDirection::Input, serialize, deserialize)
+    }
+
+    /// Creates a handle to an output, see [`DynPort::input`].
+    pub fn output<T: Send + Sync + 'static>(
+        port: &SendPort<T>,
+        serialize: Option<fn(&T) -> String>,
+        deserialize: Option<fn(&str) -> Option<T>>,
+    ) -> Self {
+        Self::new(port, PortDirection::Output, serialize, deserialize)
+    }
+
+    /// Creates a handle to a parameter, see [`DynPort::input`].
+    pub fn parameter<T: Send + Sync + 'static>(
+        port: &ParameterPort<T>,
+        serialize: Option<fn(&T) -> String>,
+        deserialize: Option<fn(&str) -> Option<T>>,
+    ) -> Self {
+        Self::new(port, PortDirection::Parameter, serialize, deserialize)
+    }
+
+    fn new<T: Send + Sync + 'static>(
+        port: &InnerPort<T>,
+        direction: PortDirection,
+        serialize: Option<fn(&T) -> String>,
+        deserialize: Option<fn(&str) -> Option<T>>,
+    ) -> Self {
+        Self { port: Box::new(TypedPort { port: port.clone(), serialize, deserialize }), direction }
+    }
+
+    /// Whether the handle was created from an output, an input or a parameter.
+    pub fn direction(&self) -> PortDirection {
+        self.direction
+    }
+
+    /// Name of the data type of the port.
+    pub fn type_name(&self) -> &'static str {
+        self.port.type_name()
+    }
+
+    pub fn id(&self) -> PortId {
+        self.port.id()
+    }
+
+    /// Whether the port reads from another port, see [`InnerPort::is_connected`].
+    pub fn is_connected(&self) -> bool {
+        self.port.is_connected()
+    }
+
+    /// Returns the port if its data type is `T`.
+    pub fn downcast<T: 'static>(&self) -> Result<&InnerPort<T>, PortError> {
+        self.port.as_any()
+            .downcast_ref::<TypedPort<T>>()
+            .map(|typed| &typed.port)
+            .ok_or(PortError::TypeMismatch { expected: type_name::<T>(), found: self.type_name() })
+    }
+
+    /// Serializes the latest data of the port.
+    pub fn serialize(&self) -> Result<String, PortError> {
+        self.port.serialize()
+    }
+
+    /// Deserializes `value` and writes it to the port.
+    pub fn deserialize(&self, value: &str) -> Result<(), PortError> {
+        self.port.deserialize(value)
+    }
+
+    /// Checks that `value` deserializes like in [`DynPort::deserialize`] without writing it.
+    pub fn validate(&self, value: &str) -> Result<(), PortError> {
+        self.port.validate(value)
+    }
+
+    /// Connects this input to `source`, like [`ReceivePort::connect_from`].
+    /// Fails if this port is not an input, is already connected or the connection would create a cycle.
+    pub fn connect_to_source(&self, source: &DynPort) -> Result<(), PortError> {
+        if self.direction != PortDirection::Input {
+            return Err(PortError::NotAnInput);
+        }
+        self.port.connect_to_source(source)
+    }
+}
+
+impl Clone for DynPort {
+    fn clone(&self) -> Self {
+        Self { port: self.port.clone_box(), direction: self.direction }
+    }
+}
+
+trait AnyPort: Send + Sync {
+    fn type_name(&self) -> &'static str;
+    fn as_any(&self) -> &dyn Any;
+    fn id(&self) -> PortId;
+    fn is_connected(&self) -> bool;
+    fn serialize(&self) -> Result<String, PortError>;
+    fn deserialize(&self, value: &str) -> Result<(), PortError>;
+    fn validate(&self, value: &str) -> Result<(), PortError>;
+    fn connect_to_source(&self, source: &DynPort) -> Result<(), PortError>;
+    fn clone_box(&self) -> Box<dyn AnyPort>;
+}
+
+struct TypedPort<T> {
+    port: InnerPort<T>,
+    serialize: Option<fn(&T) -> String>,
+    deserialize: Option<fn(&str) -> Option<T>>,
+}
+
+impl<T> TypedPort<T> {
+    fn parse(&self, value: &str) -> Result<T, PortError> {
+        let deserialize = self.deserialize.ok_or(PortError::NotDeserializable { type_name: type_name::<T>() })?;
+        deserialize(value).ok_or_else(|| PortError::InvalidValue { type_name: type_name::<T>(), value: value.to_string() })
+    }
+}
+
+impl<T: Send + Sync + 'static> AnyPort for TypedPort<T> {
+    fn type_name(&self) -> &'static str {
+        type_name::<T>()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn id(&self) -> PortId {
+        self.port.id()
+    }
+
+    fn is_connected(&self) -> bool {
+        self.port.is_connected()
+    }
+
+    fn serialize(&self) -> Result<String, PortError> {
+        let serialize = self.serialize.ok_or(PortError::NotSerializable { type_name: type_name::<T>() })?;
+        Ok(serialize(self.port.read_from_connected_port().get_data()))
+    }
+
+    fn deserialize(&self, value: &str) -> Result<(), PortError> {
+        let data = self.parse(value)?;
+        self.port.clone().write(&PortData::new(data));
+        Ok(())
+    }
+
+    fn validate(&self, value: &str) -> Result<(), PortError> {
+        self.parse(value).map(|_| ())
+    }
+
+    fn connect_to_source(&self, source: &DynPort) -> Result<(), PortError> {
+        let source = source.downcast::<T>()?;
+        if self.port.is_connected() {
+            return Err(PortError::AlreadyConnected);
+        }
+        if source.reads_from(&self.port) {
+            return Err(PortError::Cycle);
+        }
+        self.port.connect_to_source(source);
+        Ok(())
+    }
+
+    fn clone_box(&self) -> Box<dyn AnyPort> {
+        Box::new(TypedPort { port: self.port.clone(), serialize: self.serialize, deserialize: self.deserialize })
+    }
+}
+
+/// Used by the [`crate::prelude::PortMethods`] derive to find out at compile time whether
+/// a data type implements `PortSerialize` and `PortDeserialize`.
+#[doc(hidden)]
+pub mod probe {
+    use std::marker::PhantomData;
+    use serialization::{PortDeserialize, PortSerialize};
+
+    pub struct Probe<T>(PhantomData<T>);
+
+    impl<T> Probe<T> {
+        #[allow(clippy::new_without_default)]
+        pub fn new() -> Self {
+            Self(PhantomData)
+        }
+    }
+
+    pub trait ViaSerialize<T> {
+        fn serializer(&self) -> Option<fn(&T) -> String>;
+    }
+    impl<T: PortSerialize> ViaSerialize<T> for &Probe<T> {
+        fn serializer(&self) -> Option<fn(&T) -> String> {
+            Some(T::serialize)
+        }
+    }
+
+    pub trait NoSerialize<T> {
+        fn serializer(&self) -> Option<fn(&T) -> String>;
+    }
+    impl<T> NoSerialize<T> for Probe<T> {
+        fn serializer(&self) -> Option<fn(&T) -> String> {
+            None
+        }
+    }
+
+    pub trait ViaDeserialize<T> {
+        fn deserializer(&self) -> Option<fn(&str) -> Option<T>>;
+    }
+    impl<T: PortDeserialize> ViaDeserialize<T> for &Probe<T> {
+        fn deserializer(&self) -> Option<fn(&str) -> Option<T>> {
+            Some(T::deserialize)
+        }
+    }
+
+    pub trait NoDeserialize<T> {
+        fn deserializer(&self) -> Option<fn(&str) -> Option<T>>;
+    }
+    impl<T> NoDeserialize<T> for Probe<T> {
+        fn deserializer(&self) -> Option<fn(&str) -> Option<T>> {
+            None
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use derive_more::Deref;
 use crate::inner_port::InnerPort;
 use crate::port_data::PortData;
+use crate::port_reflection::{DynPort, PortError};
 
 /// A port that can receive data from a connected port.
 #[derive(Deref)]
@@ -13,6 +14,32 @@ impl<T> ReceivePort<T> {
         Self { inner_port: InnerPort::with_default_data(PortData::new(data)) }
     }
 
+    /// Connects this input to an output, or to an input forwarding data, e.g. the input of a group.
+    /// Fails if this input is already connected or the connection would create a cycle.
+    pub fn connect_from(&self, source: &impl PortSource<T>) -> Result<(), PortError> {
+        self.connect(source.source_port())
+    }
+
+    /// Like [`ReceivePort::connect_from`] for a type erased source, e.g. listed by
+    /// [`crate::prelude::PortReflection`]. Fails if the data types differ.
+    pub fn connect_from_dyn(&self, source: &DynPort) -> Result<(), PortError>
+    where
+        T: 'static,
+    {
+        self.connect(source.downcast::<T>()?)
+    }
+
+    fn connect(&self, source: &InnerPort<T>) -> Result<(), PortError> {
+        if self.inner_port.is_connected() {
+            return Err(PortError::AlreadyConnected);
+        }
+        if source.reads_from(&self.inner_port) {
+            return Err(PortError::Cycle);
+        }
+        self.inner_port.connect_to_source(source);
+        Ok(())
+    }
+
     /// Updates the internal buffer with the latest data from the connected port.
     pub fn update(&mut self) {
         self.inner_port.update();
@@ -29,6 +56,41 @@ impl<T> ReceivePort<T> {
     }
 }
 
+/// A port an input can be connected to, see [`ReceivePort::connect_from`].
+/// Implemented by [`crate::prelude::SendPort`] and [`ReceivePort`], so connecting
+/// to an output port is a compile error:
+///
+/// ```compile_fail
+/// use ports::prelude::*;
+/// let input = ReceivePort::new(0);
+/// let output = SendPort::new(0);
+/// output.connect_from(&input);
+/// ```
+///
+/// Ports can only be linked through these checked methods, not through the port they deref to:
+///
+/// ```compile_fail
+/// use ports::prelude::*;
+/// let input = ReceivePort::new(0);
+/// let output = SendPort::new(0);
+/// output.connect_to_source(&input);
+/// ```
+pub trait PortSource<T> {
+    fn source_port(&self) -> &InnerPort<T>;
+}
+
+impl<T> PortSource<T> for ReceivePort<T> {
+    fn source_port(&self) -> &InnerPort<T> {
+        &self.inner_port
+    }
+}
+
+impl<T> PortSource<T> for crate::send_port::SendPort<T> {
+    fn source_port(&self) -> &InnerPort<T> {
+        self
+    }
+}
+
 impl<T: Default> Clone for ReceivePort<T> {
     fn clone(&self) -> Self {
         Self {
@@ -1,6 +1,8 @@
 use derive_more::Deref;
 use crate::inner_port::InnerPort;
 use crate::port_data::PortData;
+use crate::port_reflection::PortError;
+use crate::receive_port::PortSource;
 
 /// A port that can send data to a connected port.
 #[derive(Deref)]
@@ -20,6 +22,31 @@ impl<T> SendPort<T> {
         self.inner_port.write(&PortData::new(data));
     }
 
+    /// Forwards the data of another output, e.g. of a module inside a group to an output of the group.
+    /// Data sent through this port afterwards is written to `source`.
+    /// Fails if this port is already forwarding or the connection would create a cycle.
+    pub fn forward_from(&self, source: &SendPort<T>) -> Result<(), PortError> {
+        if self.inner_port.is_connected() {
+            return Err(PortError::AlreadyConnected);
+        }
+        if source.reads_from(&self.inner_port) {
+            return Err(PortError::Cycle);
+        }
+        self.inner_port.connect_to_source(source);
+        Ok(())
+    }
+
+    /// Forwards the data of an input or output instead of the previous source,
+    /// e.g. of the input a fusion selects. Fails if the connection would create a cycle.
+    pub fn switch_source(&self, source: &impl PortSource<T>) -> Result<(), PortError> {
+        let source = source.source_port();
+        if source.reads_from(&self.inner_port) {
+            return Err(PortError::Cycle);
+        }
+        self.inner_port.connect_to_source(source);
+        Ok(())
+    }
+
     /// Read the last data from the internal buffer.
     pub fn get_last_data(&self) -> &T {
         self.inner_port.read_from_buffer().get_data()
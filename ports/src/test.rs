@@ -33,7 +33,73 @@ mod tests {
         assert!(second_timestamp > first_timestamp);
 
 
-        let port_3: SendPort<Option<i32>> = SendPort::default();
+        let _port_3: SendPort<Option<i32>> = SendPort::default();
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn connect_from() {
+        let mut output = SendPort::new(1);
+        let forward = ReceivePort::<i32>::default();
+        let mut input = ReceivePort::<i32>::default();
+        input.connect_from(&forward).unwrap();
+        forward.connect_from(&output).unwrap();
+        assert!(input.is_connected());
+        assert!(!output.is_connected());
+
+        output.send(2);
+        input.update();
+        assert_eq!(*input.get_data(), 2);
+        assert_eq!(input.connect_from(&output), Err(PortError::AlreadyConnected));
+        let unconnected = ReceivePort::<i32>::default();
+        assert_eq!(unconnected.connect_from(&unconnected), Err(PortError::Cycle));
+    }
+
+    #[derive(PortMethods, Default)]
+    struct Reflected {
+        pub input: ReceivePort<i32>,
+        pub output: SendPort<Vec<i32>>,
+        pub inputs: Vec<ReceivePort<bool>>,
+        count: u32,
+    }
+
+    #[derive(PortMethods, Default)]
+    struct Wrapper<T: Default> {
+        #[nested_ports]
+        inner: Reflected,
+        pub generic: SendPort<T>,
+    }
+
+    #[test]
+    fn reflection() {
+        let mut wrapper = Wrapper::<String> { inner: Reflected { inputs: vec![ReceivePort::default()], ..Default::default() }, ..Default::default() };
+        let ports = wrapper.ports();
+        let names: Vec<_> = ports.iter().map(|entry| (entry.name.as_str(), entry.direction)).collect();
+        assert_eq!(names, [
+            ("input", PortDirection::Input),
+            ("output", PortDirection::Output),
+            ("inputs[0]", PortDirection::Input),
+            ("generic", PortDirection::Output),
+        ]);
+        assert_eq!(wrapper.inner.count, 0);
+
+        let [input, output, inputs_0, generic] = &ports[..] else { unreachable!() };
+        input.port.deserialize("42").unwrap();
+        wrapper.update_ports();
+        assert_eq!(*wrapper.inner.input.get_data(), 42);
+        assert_eq!(input.port.deserialize("a"), Err(PortError::InvalidValue { type_name: "i32", value: "a".to_string() }));
+
+        assert_eq!(output.port.serialize(), Ok("[]".to_string()));
+        assert!(matches!(output.port.deserialize("[1]"), Err(PortError::NotDeserializable { .. })));
+        // The data type of a generic port is not known to implement PortSerialize.
+        assert!(matches!(generic.port.serialize(), Err(PortError::NotSerializable { .. })));
+
+        assert!(matches!(input.port.connect_to_source(&output.port), Err(PortError::TypeMismatch { .. })));
+        // An output listed by reflection can not be turned into a receiver.
+        assert_eq!(output.port.connect_to_source(&inputs_0.port), Err(PortError::NotAnInput));
+        let mut source = SendPort::new(true);
+        inputs_0.port.connect_to_source(&DynPort::output(&source, None, None)).unwrap();
+        source.send(true);
+        wrapper.update_ports();
+        assert!(*wrapper.inner.inputs[0].get_data());
+    }
+}
@@ -0,0 +1,502 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use derive_more::with_trait::{Deref, DerefMut};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::{Interval, MissedTickBehavior};
+use crate::clock::{Clock, SystemClock};
+use crate::module::{short_type_name, Module};
+use crate::runtime::{ContainerId, ModuleId};
+use crate::thread_container::{ContainerCommand, ContainerHandle};
+
+/// A module running on the async executor instead of a thread of its own.
+/// Async modules exchange data with blocking modules through the same ports.
+/// Reading and writing ports never waits for other modules, so it does not block the executor.
+pub trait AsyncModule: Send + 'static {
+    /// Update the module's internal state. Runs to completion before the next command is handled.
+    fn update(&mut self) -> impl Future<Output = ()> + Send;
+
+    /// Waits for the next event of a module with [`AsyncTrigger::Event`], e.g. a message from
+    /// a network device. Dropped when the module is paused or removed, so it must be cancel safe.
+    fn next_event(&mut self) -> impl Future<Output = ()> + Send {
+        std::future::pending()
+    }
+
+    /// Earliest deadline of the module's timers, e.g. from [`crate::Timers::next_deadline`].
+    /// The executor wakes the module at this time to call [`AsyncModule::fire_timers`].
+    /// The executor runs on the system clock.
+    fn next_deadline(&mut self) -> Option<Instant> {
+        None
+    }
+
+    /// Fires the module's due timers. Called before every `update` and at the deadlines
+    /// returned by [`AsyncModule::next_deadline`], never while the module is paused.
+    fn fire_timers(&mut self) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+
+    /// Called once before the first `update`.
+    fn start(&mut self) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+
+    /// Called once when the module is removed or the executor is shut down.
+    fn stop(&mut self) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+
+    /// Called when the module is paused. Neither `update` nor `next_event` run while it is paused.
+    fn pause(&mut self) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+
+    /// Called when a paused module is resumed.
+    fn resume(&mut self) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+
+    /// Name used for the module if none is set on the [`AsyncModuleBuilder`].
+    fn default_name() -> String where Self: Sized {
+        short_type_name::<Self>()
+    }
+}
+
+/// When an [`AsyncModule`] is updated.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AsyncTrigger {
+    /// Update with a fixed cycle time. Late updates are delayed, not run in a burst.
+    Periodic(Duration),
+    /// Update every time [`AsyncModule::next_event`] completes.
+    Event,
+}
+
+#[derive(Deref, DerefMut)]
+pub struct AsyncModuleBuilder<M: AsyncModule> {
+    #[deref] #[deref_mut]
+    pub inner: M,
+    pub trigger: AsyncTrigger,
+    pub name: String,
+}
+
+impl<M: AsyncModule> AsyncModuleBuilder<M> {
+    /// create a new async module builder.
+    pub fn new(inner: M, trigger: AsyncTrigger) -> Self {
+        Self { inner, trigger, name: M::default_name() }
+    }
+
+    /// Set the name of the module. The name is the last element of the module path
+    /// and is made unique within its group when the module is added.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+}
+
+/// Type erased async module waiting to be spawned on an [`AsyncExecutor`].
+pub(crate) struct AsyncModuleData {
+    pub(crate) name: String,
+    pub(crate) spawn: SpawnAsync,
+}
+
+pub(crate) type SpawnAsync = Box<dyn FnOnce(&AsyncShared, ModuleId) + Send>;
+
+impl<M: AsyncModule> From<AsyncModuleBuilder<M>> for AsyncModuleData {
+    fn from(builder: AsyncModuleBuilder<M>) -> Self {
+        let AsyncModuleBuilder { inner, trigger, name } = builder;
+        AsyncModuleData {
+            name,
+            spawn: Box::new(move |executor, id| executor.spawn(id, inner, trigger)),
+        }
+    }
+}
+
+/// Commands for a single module task.
+enum TaskCommand {
+    SetPaused(bool),
+    SetCycleTime(Duration),
+    Remove(std::sync::mpsc::Sender<bool>),
+}
+
+struct AsyncTask {
+    commands: mpsc::UnboundedSender<TaskCommand>,
+    join: JoinHandle<()>,
+}
+
+pub(crate) struct AsyncShared {
+    runtime: tokio::runtime::Handle,
+    tasks: Mutex<HashMap<ModuleId, AsyncTask>>,
+    shutdown: Mutex<Option<oneshot::Sender<Vec<JoinHandle<()>>>>>,
+}
+
+/// A tokio runtime running [`AsyncModule`]s, each as a task of its own.
+/// Blocking modules added through its handle run with their cycle time
+/// in `block_in_place`, so they do not block the other tasks.
+pub struct AsyncExecutor {
+    id: ContainerId,
+    shared: Arc<AsyncShared>,
+}
+
+impl AsyncExecutor {
+    /// Starts a multi threaded tokio runtime with `threads` workers (at least one).
+    pub fn new(threads: usize) -> Self {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(threads.max(1))
+            .enable_time()
+            .build()
+            .expect("failed to start the async executor");
+        let (shutdown, stopped) = oneshot::channel::<Vec<JoinHandle<()>>>();
+        let shared = Arc::new(AsyncShared {
+            runtime: runtime.handle().clone(),
+            tasks: Mutex::new(HashMap::new()),
+            shutdown: Mutex::new(Some(shutdown)),
+        });
+        std::thread::spawn(move || runtime.block_on(async {
+            // The executor keeps running when all handles are dropped.
+            let Ok(tasks) = stopped.await else {
+                return std::future::pending().await;
+            };
+            for task in tasks {
+                let _ = task.await;
+            }
+        }));
+        Self { id: ContainerId::next(), shared }
+    }
+
+    /// Returns a handle to add, remove and pause modules of the executor.
+    /// Dropping all handles does not stop the executor.
+    pub fn handle(&self) -> ContainerHandle {
+        ContainerHandle::executor(self.id, Arc::clone(&self.shared))
+    }
+
+    /// Adds an async module to the running executor.
+    pub fn add_module<M: AsyncModule>(&self, builder: AsyncModuleBuilder<M>) -> ModuleId {
+        let id = ModuleId::next();
+        self.shared.spawn(id, builder.inner, builder.trigger);
+        id
+    }
+
+    pub(crate) fn id(&self) -> ContainerId {
+        self.id
+    }
+
+    pub(crate) fn spawn_with_id(&self, id: ModuleId, spawn: SpawnAsync) {
+        spawn(&self.shared, id);
+    }
+}
+
+impl AsyncShared {
+    pub(crate) fn spawn<M: AsyncModule>(&self, id: ModuleId, module: M, trigger: AsyncTrigger) {
+        let (commands, receiver) = mpsc::unbounded_channel();
+        let join = self.runtime.spawn(drive(module, trigger, receiver));
+        self.tasks.lock().unwrap().insert(id, AsyncTask { commands, join });
+    }
+
+    pub(crate) fn send(&self, command: ContainerCommand) {
+        match command {
+            ContainerCommand::AddModule { id, module, cycle_time } => {
+                self.spawn(id, BlockingModule(module), AsyncTrigger::Periodic(cycle_time));
+            }
+            ContainerCommand::RemoveModule { id, done } => {
+                // Removed from the map right away, so a second removal fails.
+                match self.tasks.lock().unwrap().remove(&id) {
+                    Some(task) => {
+                        if let Err(mpsc::error::SendError(TaskCommand::Remove(done))) = task.commands.send(TaskCommand::Remove(done)) {
+                            let _ = done.send(false);
+                        }
+                    }
+                    None => {
+                        let _ = done.send(false);
+                    }
+                }
+            }
+            ContainerCommand::SetPaused { id, paused } => {
+                if let Some(task) = self.tasks.lock().unwrap().get(&id) {
+                    let _ = task.commands.send(TaskCommand::SetPaused(paused));
+                }
+            }
+            ContainerCommand::SetCycleTime { id, cycle_time } => {
+                if let Some(task) = self.tasks.lock().unwrap().get(&id) {
+                    let _ = task.commands.send(TaskCommand::SetCycleTime(cycle_time));
+                }
+            }
+            ContainerCommand::Wake => {}
+            ContainerCommand::Shutdown => {
+                // Dropping the command senders stops the tasks after their `stop` hook.
+                let tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+                let joins = tasks.into_values().map(|task| task.join).collect();
+                if let Some(shutdown) = self.shutdown.lock().unwrap().take() {
+                    let _ = shutdown.send(joins);
+                }
+            }
+        }
+    }
+}
+
+/// What woke a module task.
+enum Wakeup {
+    Command(Option<TaskCommand>),
+    Update,
+    Timers,
+}
+
+/// Runs a module until it is removed or the executor is shut down.
+async fn drive<M: AsyncModule>(mut module: M, trigger: AsyncTrigger, mut commands: mpsc::UnboundedReceiver<TaskCommand>) {
+    let mut interval = match trigger {
+        AsyncTrigger::Periodic(cycle_time) => Some(periodic(cycle_time, tokio::time::Instant::now())),
+        AsyncTrigger::Event => None,
+    };
+    let mut paused = false;
+    module.start().await;
+    loop {
+        let deadline = match paused {
+            true => None,
+            false => module.next_deadline(),
+        };
+        let wakeup = tokio::select! {
+            biased;
+            command = commands.recv() => Wakeup::Command(command),
+            _ = async {
+                match deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+                    None => std::future::pending().await,
+                }
+            } => Wakeup::Timers,
+            _ = async {
+                match &mut interval {
+                    Some(interval) => { interval.tick().await; }
+                    None => module.next_event().await,
+                }
+            }, if !paused => Wakeup::Update,
+        };
+        let command = match wakeup {
+            Wakeup::Command(command) => command,
+            Wakeup::Update => {
+                module.fire_timers().await;
+                module.update().await;
+                continue;
+            }
+            Wakeup::Timers => {
+                module.fire_timers().await;
+                continue;
+            }
+        };
+        match command {
+            Some(TaskCommand::SetPaused(pause)) if pause != paused => {
+                paused = pause;
+                if pause {
+                    module.pause().await;
+                } else {
+                    module.resume().await;
+                }
+            }
+            Some(TaskCommand::SetPaused(_)) => {}
+            Some(TaskCommand::SetCycleTime(cycle_time)) => {
+                // Event triggered modules have no cycle time.
+                if interval.is_some() {
+                    interval = Some(periodic(cycle_time, tokio::time::Instant::now() + cycle_time));
+                }
+            }
+            Some(TaskCommand::Remove(done)) => {
+                module.stop().await;
+                let _ = done.send(true);
+                return;
+            }
+            None => {
+                module.stop().await;
+                return;
+            }
+        }
+    }
+}
+
+/// Interval of a periodic module with its first tick at `start`.
+fn periodic(cycle_time: Duration, start: tokio::time::Instant) -> Interval {
+    let mut interval = tokio::time::interval_at(start, cycle_time.max(Duration::from_nanos(1)));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    interval
+}
+
+/// Runs a blocking module on the executor without blocking other tasks.
+struct BlockingModule(Box<dyn Module + Send>);
+
+impl AsyncModule for BlockingModule {
+    async fn update(&mut self) {
+        tokio::task::block_in_place(|| self.0.update());
+    }
+
+    fn next_deadline(&mut self) -> Option<Instant> {
+        self.0.timers().and_then(|timers| timers.next_deadline())
+    }
+
+    async fn fire_timers(&mut self) {
+        tokio::task::block_in_place(|| self.0.fire_timers());
+    }
+
+    async fn start(&mut self) {
+        // The executor schedules with tokio's timer, which follows the system clock.
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+        tokio::task::block_in_place(|| {
+            self.0.set_clock(&clock);
+            self.0.start();
+        });
+    }
+
+    async fn stop(&mut self) {
+        tokio::task::block_in_place(|| self.0.stop());
+    }
+
+    async fn pause(&mut self) {
+        tokio::task::block_in_place(|| self.0.pause());
+    }
+
+    async fn resume(&mut self) {
+        tokio::task::block_in_place(|| self.0.resume());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::{channel, Sender};
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    use crate::{AsyncExecutor, AsyncModule, AsyncModuleBuilder, AsyncTrigger, Module, TimerQueue, Timers};
+
+    struct CountingModule {
+        updates: Sender<&'static str>,
+    }
+
+    impl AsyncModule for CountingModule {
+        async fn update(&mut self) {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            let _ = self.updates.send("update");
+        }
+
+        async fn stop(&mut self) {
+            let _ = self.updates.send("stop");
+        }
+    }
+
+    struct EventModule {
+        events: mpsc::UnboundedReceiver<u32>,
+        last: Option<u32>,
+        updates: Sender<u32>,
+    }
+
+    impl AsyncModule for EventModule {
+        async fn next_event(&mut self) {
+            self.last = self.events.recv().await;
+        }
+
+        async fn update(&mut self) {
+            if let Some(last) = self.last.take() {
+                let _ = self.updates.send(last);
+            }
+        }
+    }
+
+    struct BlockingModule {
+        updates: Sender<&'static str>,
+    }
+
+    impl Module for BlockingModule {
+        fn update(&mut self) {
+            std::thread::sleep(Duration::from_millis(5));
+            let _ = self.updates.send("blocking");
+        }
+    }
+
+    struct TimedModule {
+        timers: Timers<TimedModule>,
+        events: Sender<&'static str>,
+    }
+
+    impl Module for TimedModule {
+        fn update(&mut self) {
+            let _ = self.events.send("update");
+        }
+
+        fn timers(&mut self) -> Option<&mut dyn TimerQueue> {
+            Some(&mut self.timers)
+        }
+
+        fn fire_timers(&mut self) {
+            Timers::fire(self, |module| &mut module.timers);
+        }
+    }
+
+    #[test]
+    fn periodic_module() {
+        let executor = AsyncExecutor::new(2);
+        let (updates_tx, updates) = channel();
+        let id = executor.add_module(AsyncModuleBuilder::new(
+            CountingModule { updates: updates_tx },
+            AsyncTrigger::Periodic(Duration::from_millis(5)),
+        ));
+        for _ in 0..3 {
+            assert_eq!(updates.recv_timeout(Duration::from_secs(1)), Ok("update"));
+        }
+        let handle = executor.handle();
+        assert!(handle.remove_module(id));
+        assert!(!handle.remove_module(id));
+        while let Ok(event) = updates.recv_timeout(Duration::from_secs(1)) {
+            if event == "stop" {
+                break;
+            }
+        }
+        assert!(updates.recv_timeout(Duration::from_millis(20)).is_err());
+        handle.shutdown();
+    }
+
+    #[test]
+    fn event_module_and_pause() {
+        let executor = AsyncExecutor::new(1);
+        let (events, events_rx) = mpsc::unbounded_channel();
+        let (updates_tx, updates) = channel();
+        let id = executor.add_module(AsyncModuleBuilder::new(
+            EventModule { events: events_rx, last: None, updates: updates_tx },
+            AsyncTrigger::Event,
+        ));
+        events.send(1).unwrap();
+        assert_eq!(updates.recv_timeout(Duration::from_secs(1)), Ok(1));
+
+        let handle = executor.handle();
+        handle.pause_module(id);
+        std::thread::sleep(Duration::from_millis(20));
+        events.send(2).unwrap();
+        assert!(updates.recv_timeout(Duration::from_millis(50)).is_err());
+        handle.resume_module(id);
+        assert_eq!(updates.recv_timeout(Duration::from_secs(1)), Ok(2));
+        handle.shutdown();
+    }
+
+    #[test]
+    fn blocking_module_does_not_block_async_modules() {
+        let executor = AsyncExecutor::new(1);
+        let handle = executor.handle();
+        let (updates_tx, updates) = channel();
+        handle.add_module(BlockingModule { updates: updates_tx.clone() }, Duration::ZERO);
+        executor.add_module(AsyncModuleBuilder::new(
+            CountingModule { updates: updates_tx },
+            AsyncTrigger::Periodic(Duration::from_millis(1)),
+        ));
+        let received: Vec<_> = (0..20).filter_map(|_| updates.recv_timeout(Duration::from_secs(1)).ok()).collect();
+        assert!(received.contains(&"blocking"));
+        assert!(received.contains(&"update"));
+        handle.shutdown();
+    }
+
+    #[test]
+    fn timers_of_blocking_modules_fire_between_cycles() {
+        let executor = AsyncExecutor::new(1);
+        let handle = executor.handle();
+        let (events, received) = channel();
+        let mut module = TimedModule { timers: Timers::new(), events };
+        module.timers.once(Duration::from_millis(20), |module| { let _ = module.events.send("timer"); });
+        handle.add_module(module, Duration::from_secs(3600));
+        assert_eq!(received.recv_timeout(Duration::from_secs(1)), Ok("update"));
+        assert_eq!(received.recv_timeout(Duration::from_secs(1)), Ok("timer"));
+        handle.shutdown();
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Source of the current time used by [`crate::ThreadContainer`] and [`crate::Timers`].
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    /// Registers `wake` to be called whenever the clock is moved forward by hand,
+    /// so a container waiting for a later time notices it. `wake` returns false once
+    /// it is no longer needed. Clocks moving by themselves never call it.
+    fn on_advance(&self, _wake: Waker) {}
+}
+
+/// Callback registered with [`Clock::on_advance`].
+pub type Waker = Box<dyn Fn() -> bool + Send>;
+
+/// The monotonic system clock.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when it is advanced, used to test time dependent behaviour.
+/// Clones share the same time. Advancing it wakes the containers using it.
+#[derive(Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<Instant>>,
+    wakers: Arc<Mutex<Vec<Waker>>>,
+}
+
+impl ManualClock {
+    /// Creates a clock starting at the current system time.
+    pub fn new() -> Self {
+        Self { now: Arc::new(Mutex::new(Instant::now())), wakers: Arc::default() }
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+        self.wakers.lock().unwrap().retain(|wake| wake());
+    }
+}
+
+impl std::fmt::Debug for ManualClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ManualClock").field("now", &self.now()).finish_non_exhaustive()
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+
+    fn on_advance(&self, wake: Waker) {
+        self.wakers.lock().unwrap().push(wake);
+    }
+}
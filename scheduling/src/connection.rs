@@ -0,0 +1,179 @@
+use std::fmt::{Display, Formatter};
+use ports::prelude::{PortError, PortSource, ReceivePort, SendPort};
+use crate::GroupBuilder;
+
+/// A failed connection of the [`crate::connect`] macro.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConnectionError {
+    /// The source port as written in the macro.
+    pub source: &'static str,
+    /// The connected input as written in the macro.
+    pub target: &'static str,
+    pub error: PortError,
+}
+
+impl Display for ConnectionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "can not connect `{}` to `{}`: {}", self.source, self.target, self.error)
+    }
+}
+
+impl std::error::Error for ConnectionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl GroupBuilder {
+    /// Connects an input of a module to an output, or to an input of the group.
+    /// Connecting to an output port does not compile. Fails if `target` is already connected
+    /// or the connection would create a cycle, see [`ReceivePort::connect_from`].
+    pub fn connect<T>(&mut self, source: &impl PortSource<T>, target: &ReceivePort<T>) -> Result<(), PortError> {
+        target.connect_from(source)?;
+        self.sources.insert(source.source_port().id());
+        Ok(())
+    }
+
+    /// Exports an output of a module as output of the group, see [`SendPort::forward_from`].
+    pub fn export<T>(&mut self, source: &SendPort<T>, output: &SendPort<T>) -> Result<(), PortError> {
+        output.forward_from(source)
+    }
+}
+
+/// Connects ports with [`GroupBuilder::connect`], stopping at the first failed connection.
+///
+/// ```ignore
+/// connect!(builder,
+///     oscillator.out_data => printer.in_data,
+///     group.in_data => filter.in_data,
+/// )?;
+/// ```
+#[macro_export]
+macro_rules! connect {
+    ($builder:expr, $($source:expr => $target:expr),+ $(,)?) => {
+        'connect: {
+            $(
+                if let Err(error) = $builder.connect(&$source, &$target) {
+                    break 'connect Err($crate::ConnectionError {
+                        source: stringify!($source),
+                        target: stringify!($target),
+                        error,
+                    });
+                }
+            )+
+            Ok::<(), $crate::ConnectionError>(())
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use ports::prelude::*;
+    use crate::{ConnectionError, Group, GroupBuilder, Module, ModuleBuilder, SpawnError, SpawnMode};
+
+    #[derive(Default)]
+    struct Filter {
+        in_data: ReceivePort<i32>,
+        out_data: SendPort<i32>,
+    }
+
+    #[test]
+    fn connect_ports() {
+        let mut builder = GroupBuilder::empty();
+        let group_input = ReceivePort::<i32>::default();
+        let first = Filter::default();
+        let mut second = Filter::default();
+        connect!(builder,
+            group_input => first.in_data,
+            first.out_data => second.in_data,
+        ).unwrap();
+
+        let mut first_out = first.out_data.clone();
+        first_out.send(3);
+        second.in_data.update();
+        assert_eq!(*second.in_data.get_data(), 3);
+
+        assert_eq!(connect!(builder, second.out_data => first.in_data), Err(ConnectionError {
+            source: "second.out_data",
+            target: "first.in_data",
+            error: PortError::AlreadyConnected,
+        }));
+        assert_eq!(builder.connect(&first.in_data, &group_input), Err(PortError::Cycle));
+        let unconnected = ReceivePort::<i32>::default();
+        assert_eq!(builder.connect(&unconnected, &unconnected), Err(PortError::Cycle));
+        assert_eq!(connect!(builder, first.in_data => first.in_data).unwrap_err().to_string(),
+            "can not connect `first.in_data` to `first.in_data`: the port is already connected to a source");
+    }
+
+    #[derive(PortMethods, Default)]
+    struct Increment {
+        pub in_data: ReceivePort<i32>,
+        pub out_data: SendPort<i32>,
+    }
+
+    impl Module for Increment {
+        fn update(&mut self) {
+            self.update_ports();
+            let data = self.in_data.get_data() + 1;
+            self.out_data.send(data);
+        }
+    }
+
+    /// Increments its input twice, `export` can be disabled to test the interface check.
+    #[derive(PortMethods, Default)]
+    struct TwoIncrements {
+        pub in_data: ReceivePort<i32>,
+        pub out_data: SendPort<i32>,
+        export: bool,
+    }
+
+    impl Group for TwoIncrements {
+        fn init(&mut self, builder: &mut GroupBuilder) {
+            let first = ModuleBuilder::new(Increment::default(), Duration::from_millis(1), SpawnMode::GroupThread);
+            let second = ModuleBuilder::new(Increment::default(), Duration::from_millis(1), SpawnMode::GroupThread);
+            connect!(builder,
+                self.in_data => first.in_data,
+                first.out_data => second.in_data,
+            ).unwrap();
+            if self.export {
+                builder.export(&second.out_data, &self.out_data).unwrap();
+            }
+            builder.add_module(first);
+            builder.add_module(second);
+        }
+
+        fn interface(&self) -> Vec<PortEntry> {
+            self.ports()
+        }
+    }
+
+    #[test]
+    fn group_interface() {
+        let mut root = GroupBuilder::empty();
+        let source = SendPort::new(10);
+        let group = GroupBuilder::new(TwoIncrements { export: true, ..Default::default() }, SpawnMode::GroupThread)
+            .with_name("increments");
+        let mut output = ReceivePort::default();
+        connect!(root,
+            source => group.in_data,
+            group.out_data => output,
+        ).unwrap();
+        root.add_group(group);
+        let runtime = root.spawn();
+
+        let start = Instant::now();
+        while *output.get_data() != 12 {
+            assert!(start.elapsed() < Duration::from_secs(1));
+            std::thread::sleep(Duration::from_millis(1));
+            output.update();
+        }
+        runtime.shutdown();
+
+        let mut root = GroupBuilder::empty();
+        root.add_group(GroupBuilder::new(TwoIncrements::default(), SpawnMode::GroupThread).with_name("increments"));
+        let error = root.try_spawn().err().unwrap();
+        assert_eq!(error, SpawnError::UnconnectedPort { group: "increments".to_string(), port: "out_data".to_string() });
+        assert_eq!(error.to_string(), "port `out_data` of group `increments` is not connected to a module of the group");
+    }
+}
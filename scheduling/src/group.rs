@@ -1,33 +1,108 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
 use std::time::Duration;
 use derive_more::{Deref, DerefMut};
-use crate::{Module, ModuleBuilder, ThreadContainer};
+use ports::prelude::{PortDirection, PortEntry, PortId, ReceivePort};
+use crate::{short_type_name, Module, ModuleBuilder, Runtime, TickSource};
 use crate::spawn_mode::SpawnMode;
+use crate::spawn_scope::{self, Dropped, SpawnScope};
+#[cfg(feature = "tokio")]
+use crate::async_module::{AsyncModule, AsyncModuleBuilder, AsyncModuleData};
 
 pub trait Group {
     fn init(&mut self, group: &mut GroupBuilder);
+
+    /// Input and output ports of the group, usually `self.ports()` with the
+    /// `PortMethods` derive. In `init`, inputs are connected to the modules of the group
+    /// with [`GroupBuilder::connect`] and outputs with [`GroupBuilder::export`].
+    /// Ports of the interface that are not wired this way prevent the group from spawning.
+    fn interface(&self) -> Vec<PortEntry> {
+        Vec::new()
+    }
+
+    /// Name used for the group if none is set on the [`GroupConnector`].
+    fn default_name() -> String where Self: Sized {
+        short_type_name::<Self>()
+    }
 }
 
-struct ModuleData {
-    module: Box<dyn Module + Send>,
-    cycle_time: Duration,
-    spawn_mode: SpawnMode,
+pub(crate) struct ModuleData {
+    pub(crate) name: String,
+    pub(crate) module: Box<dyn Module + Send>,
+    /// `None` uses the cycle time of the group.
+    pub(crate) cycle_time: Option<Duration>,
+    pub(crate) spawn_mode: SpawnMode,
 }
 
-struct GroupData {
-    group: GroupChildren,
-    spawn_mode: SpawnMode,
+pub(crate) struct GroupData {
+    pub(crate) name: String,
+    pub(crate) group: GroupChildren,
+    pub(crate) spawn_mode: SpawnMode,
+    /// Ports of the interface that are not wired to the modules of the group.
+    pub(crate) unconnected: Vec<String>,
+    /// See [`GroupBuilder::reject`].
+    pub(crate) rejection: Option<String>,
+    pub(crate) cycle_time: Option<Duration>,
+    /// The enable port, if it is connected.
+    pub(crate) enable: Option<ReceivePort<bool>>,
 }
 
+#[derive(Default)]
 pub struct GroupChildren {
-    modules: Vec<ModuleData>,
-    groups: Vec<GroupData>,
+    pub(crate) modules: Vec<ModuleData>,
+    pub(crate) groups: Vec<GroupData>,
+    #[cfg(feature = "tokio")]
+    pub(crate) async_modules: Vec<AsyncModuleData>,
 }
 
+/// Collects the children of a group until it is spawned or added to its parent.
+/// Like a [`ModuleBuilder`], a non-empty builder is added to the enclosing [`SpawnScope`]
+/// when dropped, or prints a warning outside of a scope.
 pub struct GroupBuilder {
-    spawn_mode: SpawnMode,
-    children: GroupChildren,
+    pub(crate) name: String,
+    pub(crate) spawn_mode: SpawnMode,
+    pub(crate) children: GroupChildren,
+    /// See [`Group::interface`].
+    interface: Vec<PortEntry>,
+    /// Sources of [`GroupBuilder::connect`], to find the wired inputs of the interface.
+    pub(crate) sources: HashSet<PortId>,
+    tick: Option<TickSource>,
+    cycle_time: Option<Duration>,
+    enable: ReceivePort<bool>,
+    /// See [`GroupBuilder::reject`].
+    rejection: Option<String>,
+    order: u64,
+}
+
+/// Errors found when spawning a group, see [`GroupBuilder::try_spawn`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpawnError {
+    /// A port of the [`Group::interface`] is not wired to the modules of the group.
+    UnconnectedPort { group: String, port: String },
+    /// A module without cycle time in groups without cycle time.
+    MissingCycleTime { module: String },
+    /// The group found its own setup invalid in [`Group::init`], see [`GroupBuilder::reject`].
+    InvalidGroup { group: String, reason: String },
 }
 
+impl Display for SpawnError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpawnError::UnconnectedPort { group, port } => {
+                write!(f, "port `{port}` of group `{group}` is not connected to a module of the group")
+            }
+            SpawnError::MissingCycleTime { module } => {
+                write!(f, "module `{module}` has no cycle time and none of its groups sets one")
+            }
+            SpawnError::InvalidGroup { group, reason } => {
+                write!(f, "group `{group}` is invalid: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SpawnError {}
+
 #[derive(Deref, DerefMut)]
 pub struct GroupConnector<G: Group> {
     #[deref] #[deref_mut]
@@ -39,95 +114,305 @@ pub struct GroupConnector<G: Group> {
 impl GroupBuilder {
     pub fn empty() -> Self {
         Self {
+            name: String::new(),
             spawn_mode: SpawnMode::NewThread,
-            children: GroupChildren {
-                modules: Vec::new(),
-                groups: Vec::new(),
-            }
+            children: GroupChildren::default(),
+            interface: Vec::new(),
+            sources: HashSet::new(),
+            tick: None,
+            cycle_time: None,
+            enable: ReceivePort::new(true),
+            rejection: None,
+            order: spawn_scope::next_order(),
         }
     }
 
+    /// Set the name of the group, see [`GroupConnector::with_name`].
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Set where the modules of the group with [`SpawnMode::GroupThread`] run.
+    pub fn with_spawn_mode(mut self, spawn_mode: SpawnMode) -> Self {
+        self.spawn_mode = spawn_mode;
+        self
+    }
+
+    /// Set the cycle time of the modules and child groups that do not set their own,
+    /// see [`ModuleBuilder::inherit`]. Can be changed at runtime with [`Runtime::set_cycle_time`].
+    pub fn with_cycle_time(mut self, cycle_time: Duration) -> Self {
+        self.cycle_time = Some(cycle_time);
+        self
+    }
+
+    /// Input enabling the group, `true` by default. While it or the enable port of a parent group
+    /// is `false`, the modules of the group are paused, including their safe state,
+    /// without changing their state in the [`Runtime`].
+    pub fn enable(&self) -> &ReceivePort<bool> {
+        &self.enable
+    }
+
+    /// Prevents the group from spawning with [`SpawnError::InvalidGroup`], for setups
+    /// [`Group::init`] finds invalid. Only the first reason is kept.
+    pub fn reject(&mut self, reason: impl Into<String>) {
+        self.rejection.get_or_insert_with(|| reason.into());
+    }
+
+    /// Aligns the cycles of all modules in threads and the worker pool to `tick`, see [`TickSource`].
+    /// Only used when this group is spawned, not when it is added to another group.
+    pub fn with_tick_source(mut self, tick: TickSource) -> Self {
+        self.tick = Some(tick);
+        self
+    }
+
+    #[allow(clippy::new_ret_no_self)]
     pub fn new<G: Group>(mut group: G, spawn_mode: SpawnMode) -> GroupConnector<G> {
         let mut builder = Self {
+            name: G::default_name(),
             spawn_mode,
-            children: GroupChildren {
-                modules: Vec::new(),
-                groups: Vec::new(),
-            }
+            children: GroupChildren::default(),
+            interface: Vec::new(),
+            sources: HashSet::new(),
+            tick: None,
+            cycle_time: None,
+            enable: ReceivePort::new(true),
+            rejection: None,
+            order: spawn_scope::next_order(),
         };
+        // Builders dropped in `init` belong to this group, not to an enclosing scope.
+        let barrier = SpawnScope::barrier();
         group.init(&mut builder);
+        drop(barrier);
+        builder.interface = group.interface();
         GroupConnector {
             inner: group,
             builder,
         }
-        
+
     }
 
     pub fn add_module<M: Module + Send + 'static>(&mut self, builder: ModuleBuilder<M>) {
-        self.children.modules.push(ModuleData {
-            module: Box::new(builder.inner),
-            cycle_time: builder.cycle_time,
-            spawn_mode: builder.spawn_mode,
-        });
+        self.add_module_data(builder.into());
     }
 
-    pub fn add_group<G>(&mut self, group: G) 
-    where 
+    pub(crate) fn add_module_data(&mut self, mut module: ModuleData) {
+        module.name = self.children.unique_name(module.name);
+        self.children.modules.push(module);
+    }
+
+    /// Adds a module running on the async executor of the [`Runtime`].
+    #[cfg(feature = "tokio")]
+    pub fn add_async_module<M: AsyncModule>(&mut self, builder: AsyncModuleBuilder<M>) {
+        let mut module: AsyncModuleData = builder.into();
+        module.name = self.children.unique_name(module.name);
+        self.children.async_modules.push(module);
+    }
+
+    pub fn add_group<G>(&mut self, group: G)
+    where
         G: Into<GroupBuilder>
     {
-        let group = group.into();
-        self.children.groups.push(GroupData {
-            group: group.children,
-            spawn_mode: group.spawn_mode
-        });
-    }
-
-    pub fn spawn(self) {
-        let mut main_container = ThreadContainer::new();
-        Self::spawn_on_thread(self.children, &mut main_container);
-        main_container.run();
-    }
-
-    fn spawn_on_thread(group_children: GroupChildren, container: &mut ThreadContainer) {
-        for child_module in group_children.modules {
-            match child_module.spawn_mode {
-                SpawnMode::GroupThread => container.add_dyn_module(child_module.module, child_module.cycle_time),
-                SpawnMode::NewThread => {
-                    let mut new_container = ThreadContainer::new();
-                    new_container.add_dyn_module(child_module.module, child_module.cycle_time);
-                    new_container.run();
-                }
-            }
+        let mut group = group.into().into_data();
+        group.name = self.children.unique_name(group.name);
+        self.children.groups.push(group);
+    }
+
+    /// Spawns all modules and groups and returns a [`Runtime`] to modify the running system.
+    /// Modules with [`SpawnMode::GroupThread`] share the thread of their group.
+    /// The worker pool for [`SpawnMode::ThreadPool`] uses one thread per available CPU core.
+    ///
+    /// Panics if the group can not be spawned, see [`GroupBuilder::try_spawn`].
+    pub fn spawn(self) -> Runtime {
+        self.try_spawn().unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Like [`GroupBuilder::spawn`], with `threads` workers in the worker pool.
+    pub fn spawn_with_pool(self, threads: usize) -> Runtime {
+        self.try_spawn_with_pool(threads).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Spawns the group unless the group or a child group was rejected or its interface is not wired.
+    pub fn try_spawn(self) -> Result<Runtime, SpawnError> {
+        let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+        self.try_spawn_with_pool(threads)
+    }
+
+    /// Like [`GroupBuilder::try_spawn`], with `threads` workers in the worker pool.
+    pub fn try_spawn_with_pool(self, threads: usize) -> Result<Runtime, SpawnError> {
+        self.check(None)?;
+        let mut runtime = Runtime::new(threads, self.tick);
+        runtime.spawn_root(self.into_data());
+        Ok(runtime)
+    }
+
+    /// Names of the ports of the interface that are not wired to the modules of the group.
+    fn unconnected(&self) -> Vec<String> {
+        self.interface.iter()
+            .filter(|entry| match entry.direction {
+                PortDirection::Input => !self.sources.contains(&entry.port.id()),
+                PortDirection::Output => !entry.port.is_connected(),
+                PortDirection::Parameter => false,
+            })
+            .map(|entry| entry.name.clone())
+            .collect()
+    }
+
+    /// Checks the interfaces and cycle times of this group and all child groups.
+    /// `cycle_time` is the cycle time of the parent group.
+    pub(crate) fn check(&self, cycle_time: Option<Duration>) -> Result<(), SpawnError> {
+        if let Some(reason) = &self.rejection {
+            return Err(SpawnError::InvalidGroup { group: self.name.clone(), reason: reason.clone() });
+        }
+        if let Some(port) = self.unconnected().into_iter().next() {
+            return Err(SpawnError::UnconnectedPort { group: self.name.clone(), port });
         }
-        for child_group in group_children.groups {
-            match child_group.spawn_mode {
-                SpawnMode::GroupThread => Self::spawn_on_thread(child_group.group, container),
-                SpawnMode::NewThread => {
-                    let mut new_container = ThreadContainer::new();
-                    Self::spawn_on_thread(child_group.group, &mut new_container);
-                    new_container.run();
-                }
+        self.children.check("", self.cycle_time.or(cycle_time))
+    }
+
+    /// Takes the group, leaving an empty builder that is dropped without effect.
+    pub(crate) fn into_data(mut self) -> GroupData {
+        let enable = std::mem::take(&mut self.enable);
+        GroupData {
+            unconnected: self.unconnected(),
+            rejection: self.rejection.take(),
+            name: std::mem::take(&mut self.name),
+            group: std::mem::take(&mut self.children),
+            spawn_mode: self.spawn_mode,
+            cycle_time: self.cycle_time,
+            enable: enable.is_connected().then_some(enable),
+        }
+    }
+}
+
+impl Drop for GroupBuilder {
+    fn drop(&mut self) {
+        if self.children.is_empty() || std::thread::panicking() {
+            return;
+        }
+        if spawn_scope::is_collecting() {
+            let group = GroupBuilder {
+                name: std::mem::take(&mut self.name),
+                spawn_mode: self.spawn_mode,
+                children: std::mem::take(&mut self.children),
+                interface: std::mem::take(&mut self.interface),
+                sources: std::mem::take(&mut self.sources),
+                tick: self.tick,
+                cycle_time: self.cycle_time,
+                enable: std::mem::take(&mut self.enable),
+                rejection: self.rejection.take(),
+                order: self.order,
+            };
+            spawn_scope::collect(self.order, Dropped::Group(group));
+        } else {
+            eprintln!("Warning: group `{}` was dropped without being spawned or added to a group", self.name);
+        }
+    }
+}
+
+impl GroupChildren {
+    /// Checks the interfaces of all child groups and the cycle times of all modules,
+    /// named by their path below `path`. `cycle_time` is the cycle time of the group.
+    fn check(&self, path: &str, cycle_time: Option<Duration>) -> Result<(), SpawnError> {
+        let join = |name: &str| if path.is_empty() { name.to_string() } else { format!("{path}/{name}") };
+        if let Some(module) = self.modules.iter().find(|module| module.cycle_time.or(cycle_time).is_none()) {
+            return Err(SpawnError::MissingCycleTime { module: join(&module.name) });
+        }
+        for group in &self.groups {
+            let group_path = join(&group.name);
+            if let Some(reason) = &group.rejection {
+                return Err(SpawnError::InvalidGroup { group: group_path, reason: reason.clone() });
+            }
+            if let Some(port) = group.unconnected.first() {
+                return Err(SpawnError::UnconnectedPort { group: group_path, port: port.clone() });
             }
+            group.group.check(&group_path, group.cycle_time.or(cycle_time))?;
         }
+        Ok(())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        let empty = self.modules.is_empty() && self.groups.is_empty();
+        #[cfg(feature = "tokio")]
+        let empty = empty && self.async_modules.is_empty();
+        empty
+    }
+
+    /// Returns `name`, or `name` with the lowest free numeric suffix if a child already uses it.
+    fn unique_name(&self, name: String) -> String {
+        let names = self.modules.iter().map(|m| &m.name).chain(self.groups.iter().map(|g| &g.name));
+        #[cfg(feature = "tokio")]
+        let names = names.chain(self.async_modules.iter().map(|m| &m.name));
+        unique_name(names, name)
+    }
+}
+
+/// Returns `name`, or `name` with the lowest free numeric suffix if it is already `taken`.
+pub(crate) fn unique_name<'a>(taken: impl Iterator<Item = &'a String> + Clone, name: String) -> String {
+    if !taken.clone().any(|t| *t == name) {
+        return name;
+    }
+    (1..)
+        .map(|i| format!("{name}_{i}"))
+        .find(|candidate| !taken.clone().any(|t| t == candidate))
+        .unwrap()
+}
+
+impl<M: Module + Send + 'static> From<ModuleBuilder<M>> for ModuleData {
+    fn from(mut builder: ModuleBuilder<M>) -> Self {
+        builder.take()
     }
 }
 
 impl<G: Group> GroupConnector<G> {
+    /// Set the name of the group. The name is part of the path of all its modules
+    /// and is made unique within its parent group when the group is added.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.builder.name = name.into();
+        self
+    }
+
+    /// See [`GroupBuilder::with_tick_source`].
+    pub fn with_tick_source(mut self, tick: TickSource) -> Self {
+        self.builder.tick = Some(tick);
+        self
+    }
+
+    /// See [`GroupBuilder::with_cycle_time`].
+    pub fn with_cycle_time(mut self, cycle_time: Duration) -> Self {
+        self.builder.cycle_time = Some(cycle_time);
+        self
+    }
+
+    /// See [`GroupBuilder::enable`].
+    pub fn enable(&self) -> &ReceivePort<bool> {
+        self.builder.enable()
+    }
+
     pub fn add_module<M: Module + Send + 'static>(&mut self, builder: ModuleBuilder<M>) {
         self.builder.add_module(builder);
     }
 
+    #[cfg(feature = "tokio")]
+    pub fn add_async_module<M: AsyncModule>(&mut self, builder: AsyncModuleBuilder<M>) {
+        self.builder.add_async_module(builder);
+    }
+
     pub fn add_group(&mut self, group: GroupBuilder) {
         self.builder.add_group(group);
     }
 
-    pub fn spawn(self) {
+    pub fn spawn(self) -> Runtime {
         self.builder.spawn()
     }
+
+    pub fn spawn_with_pool(self, threads: usize) -> Runtime {
+        self.builder.spawn_with_pool(threads)
+    }
 }
 
-impl<G: Group> Into<GroupBuilder> for GroupConnector<G> {
-    fn into(self) -> GroupBuilder {
-        self.builder
+impl<G: Group> From<GroupConnector<G>> for GroupBuilder {
+    fn from(connector: GroupConnector<G>) -> Self {
+        connector.builder
     }
-}
\ No newline at end of file
+}
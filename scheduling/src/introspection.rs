@@ -0,0 +1,285 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use ports::prelude::{PortDirection, PortEntry};
+use crate::clock::Clock;
+use crate::timer::TimerQueue;
+use crate::module::Module;
+use crate::runtime::{ContainerId, GroupId, ModuleId, ModuleState};
+
+/// What a container of a [`crate::Runtime`] was started for.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ContainerKind {
+    /// The container of the root group.
+    Main,
+    /// Started for a group with [`crate::SpawnMode::NewThread`].
+    Group,
+    /// Started for a module with [`crate::SpawnMode::NewThread`].
+    Module,
+    /// The shared worker pool.
+    Pool,
+    /// The executor of the async modules.
+    Executor,
+}
+
+/// Timing of a module, measured in its working thread.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModuleStatistics {
+    /// Number of completed updates.
+    pub updates: u64,
+    /// Time between the starts of the last two updates while the module was running.
+    pub measured_cycle_time: Option<Duration>,
+    /// Duration of the last update.
+    pub update_time: Option<Duration>,
+    pub max_update_time: Duration,
+    /// Whether `update` panicked. A failed module is not updated anymore.
+    pub failed: bool,
+}
+
+/// State of a running system at one point in time, see [`crate::Runtime::snapshot`].
+/// Groups and modules are sorted by path.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RuntimeSnapshot {
+    pub containers: Vec<ContainerSnapshot>,
+    pub groups: Vec<GroupSnapshot>,
+    pub modules: Vec<ModuleSnapshot>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContainerSnapshot {
+    pub id: ContainerId,
+    pub kind: ContainerKind,
+    /// Number of working threads.
+    pub threads: usize,
+    pub modules: Vec<ModuleId>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GroupSnapshot {
+    pub id: GroupId,
+    /// Path of the group, empty for the root group.
+    pub path: String,
+    pub state: ModuleState,
+    /// Cycle time of the group or its closest parent setting one.
+    pub cycle_time: Option<Duration>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModuleSnapshot {
+    pub id: ModuleId,
+    pub path: String,
+    pub container: ContainerId,
+    pub state: ModuleState,
+    /// Configured cycle time, `None` for async modules.
+    pub cycle_time: Option<Duration>,
+    /// `None` for async modules.
+    pub statistics: Option<ModuleStatistics>,
+    pub ports: Vec<PortSnapshot>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PortSnapshot {
+    pub name: String,
+    pub direction: PortDirection,
+    pub type_name: String,
+    /// The latest value serialized with `PortSerialize`, `None` if the data type does not implement it.
+    pub value: Option<String>,
+}
+
+impl RuntimeSnapshot {
+    pub fn module(&self, path: &str) -> Option<&ModuleSnapshot> {
+        self.modules.iter().find(|module| module.path == path)
+    }
+
+    pub fn group(&self, path: &str) -> Option<&GroupSnapshot> {
+        self.groups.iter().find(|group| group.path == path)
+    }
+}
+
+impl ModuleSnapshot {
+    pub fn port(&self, name: &str) -> Option<&PortSnapshot> {
+        self.ports.iter().find(|port| port.name == name)
+    }
+}
+
+impl From<&PortEntry> for PortSnapshot {
+    fn from(entry: &PortEntry) -> Self {
+        Self {
+            name: entry.name.clone(),
+            direction: entry.direction,
+            type_name: entry.port.type_name().to_string(),
+            value: entry.port.serialize().ok(),
+        }
+    }
+}
+
+/// Measures the updates of a module spawned by a [`crate::Runtime`] and stops updating
+/// it when `update` panics, so a failing module does not take down its working thread.
+pub(crate) struct MonitoredModule {
+    inner: Box<dyn Module + Send>,
+    statistics: Arc<Mutex<ModuleStatistics>>,
+    last_start: Option<Instant>,
+}
+
+impl MonitoredModule {
+    pub(crate) fn new(inner: Box<dyn Module + Send>) -> (Self, Arc<Mutex<ModuleStatistics>>) {
+        let statistics = Arc::new(Mutex::new(ModuleStatistics::default()));
+        (Self { inner, statistics: Arc::clone(&statistics), last_start: None }, statistics)
+    }
+
+    fn failed(&self) -> bool {
+        self.statistics.lock().unwrap().failed
+    }
+}
+
+impl Module for MonitoredModule {
+    fn update(&mut self) {
+        if self.failed() {
+            return;
+        }
+        let start = Instant::now();
+        let result = catch_unwind(AssertUnwindSafe(|| self.inner.update()));
+        let update_time = start.elapsed();
+        let mut statistics = self.statistics.lock().unwrap();
+        statistics.updates += 1;
+        statistics.measured_cycle_time = self.last_start.map(|last_start| start - last_start);
+        statistics.update_time = Some(update_time);
+        statistics.max_update_time = statistics.max_update_time.max(update_time);
+        statistics.failed = result.is_err();
+        self.last_start = Some(start);
+    }
+
+    fn set_clock(&mut self, clock: &Arc<dyn Clock>) {
+        self.inner.set_clock(clock);
+    }
+
+    fn timers(&mut self) -> Option<&mut dyn TimerQueue> {
+        if self.failed() {
+            return None;
+        }
+        self.inner.timers()
+    }
+
+    fn fire_timers(&mut self) {
+        if self.failed() {
+            return;
+        }
+        // A panicking timer callback fails the module like a panicking update.
+        if catch_unwind(AssertUnwindSafe(|| self.inner.fire_timers())).is_err() {
+            self.statistics.lock().unwrap().failed = true;
+        }
+    }
+
+    fn start(&mut self) {
+        self.inner.start();
+    }
+
+    fn stop(&mut self) {
+        if !self.failed() {
+            self.inner.stop();
+        }
+    }
+
+    fn pause(&mut self) {
+        // The time spent paused is not a cycle.
+        self.last_start = None;
+        if !self.failed() {
+            self.inner.pause();
+        }
+    }
+
+    fn resume(&mut self) {
+        if !self.failed() {
+            self.inner.resume();
+        }
+    }
+
+    fn interface(&self) -> Vec<PortEntry> {
+        self.inner.interface()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use ports::prelude::*;
+    use crate::{ContainerKind, GroupBuilder, Module, ModuleBuilder, ModuleState, Runtime, SpawnMode};
+
+    #[derive(PortMethods, Default)]
+    struct Counter {
+        pub count: SendPort<u32>,
+        pub limit: ParameterPort<u32>,
+        updates: u32,
+    }
+
+    impl Module for Counter {
+        fn update(&mut self) {
+            self.update_ports();
+            self.updates += 1;
+            assert!(self.updates <= *self.limit.get_data(), "limit exceeded");
+            self.count.send(self.updates);
+        }
+
+        fn interface(&self) -> Vec<PortEntry> {
+            self.ports()
+        }
+    }
+
+    fn counter(limit: u32, spawn_mode: SpawnMode) -> ModuleBuilder<Counter> {
+        ModuleBuilder::new(Counter { limit: ParameterPort::new(limit), ..Default::default() }, Duration::from_millis(2), spawn_mode)
+    }
+
+    fn wait_until(runtime: &Runtime, condition: impl Fn(&Runtime) -> bool) {
+        let start = Instant::now();
+        while !condition(runtime) {
+            assert!(start.elapsed() < Duration::from_secs(2));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn snapshot() {
+        let mut group = GroupBuilder::empty().with_name("counters");
+        group.add_module(counter(3, SpawnMode::GroupThread).with_name("failing"));
+        group.add_module(counter(u32::MAX, SpawnMode::NewThread).with_name("running"));
+        let mut builder = GroupBuilder::empty();
+        builder.add_group(group);
+        let runtime = builder.spawn();
+
+        let failing = runtime.find_module("counters/failing").unwrap();
+        let running = runtime.find_module("counters/running").unwrap();
+        wait_until(&runtime, |runtime| runtime.module_state(failing) == Some(ModuleState::Failed));
+        wait_until(&runtime, |runtime| runtime.module_statistics(running).unwrap().updates > 5);
+        assert_eq!(runtime.container_kind(runtime.container_of(running).unwrap()), Some(ContainerKind::Module));
+        let group_container = runtime.container_of(failing).unwrap();
+        assert_eq!(runtime.container_kind(group_container), Some(ContainerKind::Group));
+        assert_eq!(runtime.container_modules(group_container), [failing]);
+
+        let snapshot = runtime.snapshot();
+        runtime.shutdown();
+        assert_eq!(snapshot.groups.iter().map(|group| group.path.as_str()).collect::<Vec<_>>(), ["", "counters"]);
+        assert_eq!(snapshot.modules.iter().map(|module| module.path.as_str()).collect::<Vec<_>>(),
+            ["counters/failing", "counters/running"]);
+        assert_eq!(snapshot.containers.iter().map(|container| container.kind).collect::<Vec<_>>(),
+            [ContainerKind::Main, ContainerKind::Group, ContainerKind::Module]);
+
+        let failing = snapshot.module("counters/failing").unwrap();
+        assert_eq!(failing.state, ModuleState::Failed);
+        assert_eq!(failing.port("count").unwrap().value.as_deref(), Some("3"));
+        assert_eq!(failing.statistics.unwrap().updates, 4);
+        let running = snapshot.module("counters/running").unwrap();
+        assert_eq!(running.state, ModuleState::Running);
+        assert_eq!(running.cycle_time, Some(Duration::from_millis(2)));
+        assert!(running.statistics.unwrap().measured_cycle_time.is_some());
+        let limit = running.port("limit").unwrap();
+        assert_eq!((limit.direction, limit.type_name.as_str(), limit.value.as_deref()),
+            (PortDirection::Parameter, "u32", Some("4294967295")));
+    }
+}
@@ -1,10 +1,32 @@
+extern crate self as scheduling;
+
 mod module;
 mod thread_container;
 mod group;
 mod spawn_mode;
+mod runtime;
+mod thread_pool;
+mod clock;
+mod timer;
+mod spawn_scope;
+mod connection;
+mod sync;
+mod introspection;
+#[cfg(feature = "tokio")]
+mod async_module;
 
-pub use thread_container::ThreadContainer;
+pub use thread_container::{ThreadContainer, ContainerHandle};
+pub use thread_pool::ThreadPool;
+pub use clock::*;
+pub use timer::*;
+#[cfg(feature = "tokio")]
+pub use async_module::{AsyncExecutor, AsyncModule, AsyncModuleBuilder, AsyncTrigger};
+pub use runtime::*;
 pub use module::*;
 pub use group::*;
 pub use spawn_mode::*;
+pub use spawn_scope::SpawnScope;
+pub use connection::ConnectionError;
+pub use sync::{RateBarrier, TickSource};
+pub use introspection::{ContainerKind, ContainerSnapshot, GroupSnapshot, ModuleSnapshot, ModuleStatistics, PortSnapshot, RuntimeSnapshot};
 pub use spawn_macro::spawns;
\ No newline at end of file
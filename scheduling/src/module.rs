@@ -1,6 +1,13 @@
+use std::sync::Arc;
 use std::time::Duration;
-use derive_more::with_trait::{Deref, DerefMut};
+use std::ops::{Deref, DerefMut};
+use ports::prelude::{PortEntry, ReceivePort};
+use crate::clock::Clock;
+use crate::timer::TimerQueue;
+use crate::group::ModuleData;
 use crate::spawn_mode::SpawnMode;
+use crate::spawn_scope::{self, Dropped};
+use crate::sync::{BarrierWait, RateBarrier, SyncedModule};
 
 /// A scheduling that can be added to a `ThreadContainer`.
 /// The scheduling must implement the `update` method, which will be called
@@ -8,23 +15,314 @@ use crate::spawn_mode::SpawnMode;
 pub trait Module {
     /// Update the scheduling's internal state.
     fn update(&mut self);
+
+    /// Called in the working thread before `start` with the clock of the container.
+    /// Modules measuring time should use it instead of the system clock.
+    /// By default, it is passed on to the [`Module::timers`].
+    fn set_clock(&mut self, clock: &Arc<dyn Clock>) {
+        if let Some(timers) = self.timers() {
+            timers.set_clock(clock.clone());
+        }
+    }
+
+    /// Timers of the module, usually `Some(&mut self.timers)` with a [`crate::Timers`] field.
+    /// The container wakes the module at their earliest deadline, also between cycles,
+    /// and calls [`Module::fire_timers`]. Timers do not fire while the module is paused.
+    fn timers(&mut self) -> Option<&mut dyn TimerQueue> {
+        None
+    }
+
+    /// Fires the due [`Module::timers`], usually `Timers::fire(self, |module| &mut module.timers)`.
+    /// Called by the container at their deadlines and right before every `update`.
+    fn fire_timers(&mut self) {}
+
+    /// Called once in the working thread before the first `update`.
+    fn start(&mut self) {}
+
+    /// Called once in the working thread when the module is removed
+    /// from its container or the container is shut down.
+    fn stop(&mut self) {}
+
+    /// Called in the working thread when the module is paused.
+    /// `update` is not called while the module is paused, its ports keep their last values.
+    fn pause(&mut self) {}
+
+    /// Called in the working thread when a paused module is resumed.
+    fn resume(&mut self) {}
+
+    /// Ports of the module shown by [`crate::Runtime::snapshot`],
+    /// usually `self.ports()` with the `PortMethods` derive.
+    fn interface(&self) -> Vec<PortEntry> {
+        Vec::new()
+    }
+
+    /// Name used for the module if none is set on the [`ModuleBuilder`].
+    fn default_name() -> String where Self: Sized {
+        short_type_name::<Self>()
+    }
+}
+
+/// Name of a type without module paths and generic arguments.
+pub fn short_type_name<T: ?Sized>() -> String {
+    let name = std::any::type_name::<T>();
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name).to_string()
 }
 
-#[derive(Deref, DerefMut)]
-pub struct ModuleBuilder<M: Module> {
-    #[deref] #[deref_mut]
-    pub inner: M,
-    pub cycle_time: Duration,
-    pub spawn_mode: SpawnMode
+/// Callback applying the safe state of a paused module.
+pub(crate) type PauseAction<M> = Box<dyn FnMut(&mut M) + Send>;
+
+/// Wrapper for all modules until they are added to a group or [`crate::Runtime`].
+///
+/// A builder dropped inside a [`crate::SpawnScope`], e.g. in a function with the
+/// [`crate::spawns`] attribute, is added to the group of the scope. Otherwise dropping
+/// a builder without adding it prints a warning, as the module would never run.
+pub struct ModuleBuilder<M: Module + Send + 'static> {
+    /// Always `Some` until the module is added.
+    inner: Option<M>,
+    /// `None` uses the cycle time of the group, see [`crate::GroupBuilder::with_cycle_time`].
+    pub cycle_time: Option<Duration>,
+    pub spawn_mode: SpawnMode,
+    pub name: String,
+    pub(crate) on_pause: Option<PauseAction<M>>,
+    publishes: Vec<RateBarrier>,
+    waits_for: Vec<RateBarrier>,
+    order: u64,
 }
 
-impl<M: Module> ModuleBuilder<M> {
+impl<M: Module + Send + 'static> ModuleBuilder<M> {
     /// create a new module builder. Wrapper for all modules.
     pub fn new(
         inner: M,
         cycle_time: Duration,
         spawn_mode: SpawnMode
     ) -> Self {
-        Self { inner, cycle_time , spawn_mode }
+        Self {
+            inner: Some(inner),
+            cycle_time: Some(cycle_time),
+            spawn_mode,
+            name: M::default_name(),
+            on_pause: None,
+            publishes: Vec::new(),
+            waits_for: Vec::new(),
+            order: spawn_scope::next_order(),
+        }
+    }
+
+    /// Completes a cycle of `barrier` after every update, as the slower module of the barrier.
+    pub fn publishes(mut self, barrier: &RateBarrier) -> Self {
+        self.publishes.push(barrier.clone());
+        self
+    }
+
+    /// Waits for the slower module of `barrier` before every update, see [`RateBarrier`].
+    pub fn waits_for(mut self, barrier: &RateBarrier) -> Self {
+        self.waits_for.push(barrier.clone());
+        self
+    }
+
+    /// Takes the module out of the builder, which is then dropped without effect.
+    pub(crate) fn take(&mut self) -> ModuleData {
+        let inner = self.inner.take().unwrap();
+        let mut module: Box<dyn Module + Send> = match self.on_pause.take() {
+            Some(on_pause) => Box::new(SafeStateModule { inner, on_pause }),
+            None => Box::new(inner),
+        };
+        if !self.publishes.is_empty() || !self.waits_for.is_empty() {
+            module = Box::new(SyncedModule {
+                inner: module,
+                publishes: std::mem::take(&mut self.publishes),
+                waits_for: self.waits_for.drain(..).map(BarrierWait::new).collect(),
+            });
+        }
+        ModuleData {
+            name: std::mem::take(&mut self.name),
+            module,
+            cycle_time: self.cycle_time,
+            spawn_mode: self.spawn_mode,
+        }
+    }
+
+    /// Create a module builder using the cycle time of its group, running in the thread of its group.
+    pub fn inherit(inner: M) -> Self {
+        let mut builder = Self::new(inner, Duration::ZERO, SpawnMode::GroupThread);
+        builder.cycle_time = None;
+        builder
+    }
+
+    /// Set the cycle time, overriding the cycle time of the group.
+    pub fn with_cycle_time(mut self, cycle_time: Duration) -> Self {
+        self.cycle_time = Some(cycle_time);
+        self
+    }
+
+    pub fn with_spawn_mode(mut self, spawn_mode: SpawnMode) -> Self {
+        self.spawn_mode = spawn_mode;
+        self
+    }
+
+    /// Set the name of the module. The name is the last element of the module path
+    /// and is made unique within its group when the module is added.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Set the safe state of the module. `on_pause` is called in the working thread
+    /// after the module's `pause` hook, e.g. to publish safe values on its output ports.
+    /// Without it, a paused module keeps its last outputs.
+    pub fn on_pause<F: FnMut(&mut M) + Send + 'static>(mut self, on_pause: F) -> Self {
+        self.on_pause = Some(Box::new(on_pause));
+        self
+    }
+}
+
+impl<M: Module + Send + 'static> Deref for ModuleBuilder<M> {
+    type Target = M;
+
+    fn deref(&self) -> &M {
+        self.inner.as_ref().unwrap()
+    }
+}
+
+impl<M: Module + Send + 'static> DerefMut for ModuleBuilder<M> {
+    fn deref_mut(&mut self) -> &mut M {
+        self.inner.as_mut().unwrap()
+    }
+}
+
+impl<M: Module + Send + 'static> Drop for ModuleBuilder<M> {
+    fn drop(&mut self) {
+        if self.inner.is_none() || std::thread::panicking() {
+            return;
+        }
+        if spawn_scope::is_collecting() {
+            let module = self.take();
+            spawn_scope::collect(self.order, Dropped::Module(module));
+        } else {
+            eprintln!("Warning: module `{}` was dropped without being added to a group or runtime", self.name);
+        }
+    }
+}
+
+/// Module with a configured safe state, created by [`ModuleBuilder::on_pause`].
+pub(crate) struct SafeStateModule<M: Module> {
+    pub(crate) inner: M,
+    pub(crate) on_pause: PauseAction<M>,
+}
+
+impl<M: Module> Module for SafeStateModule<M> {
+    fn update(&mut self) {
+        self.inner.update();
+    }
+
+    fn set_clock(&mut self, clock: &Arc<dyn Clock>) {
+        self.inner.set_clock(clock);
+    }
+
+    fn timers(&mut self) -> Option<&mut dyn TimerQueue> {
+        self.inner.timers()
+    }
+
+    fn fire_timers(&mut self) {
+        self.inner.fire_timers();
+    }
+
+    fn start(&mut self) {
+        self.inner.start();
+    }
+
+    fn stop(&mut self) {
+        self.inner.stop();
+    }
+
+    fn pause(&mut self) {
+        self.inner.pause();
+        (self.on_pause)(&mut self.inner);
+    }
+
+    fn resume(&mut self) {
+        self.inner.resume();
+    }
+
+    fn interface(&self) -> Vec<PortEntry> {
+        self.inner.interface()
+    }
+}
+
+/// Module of a group with a connected enable port, see [`crate::GroupBuilder::enable`].
+/// While one of the enable ports is `false`, the module is paused and `update` is skipped.
+pub(crate) struct EnabledModule {
+    pub(crate) inner: Box<dyn Module + Send>,
+    /// Enable ports of the group and its parent groups.
+    pub(crate) enable: Vec<ReceivePort<bool>>,
+    /// Whether all enable ports were `true` at the last update.
+    /// The module is not updated while it is paused, so it keeps its last value meanwhile.
+    pub(crate) enabled: bool,
+}
+
+impl EnabledModule {
+    pub(crate) fn new(inner: Box<dyn Module + Send>, enable: Vec<ReceivePort<bool>>) -> Self {
+        Self { inner, enable, enabled: true }
+    }
+}
+
+impl Module for EnabledModule {
+    fn update(&mut self) {
+        let mut enabled = true;
+        for port in &mut self.enable {
+            port.update();
+            enabled &= *port.get_data();
+        }
+        if enabled != self.enabled {
+            self.enabled = enabled;
+            if enabled {
+                self.inner.resume();
+            } else {
+                self.inner.pause();
+            }
+        }
+        if enabled {
+            self.inner.update();
+        }
+    }
+
+    fn set_clock(&mut self, clock: &Arc<dyn Clock>) {
+        self.inner.set_clock(clock);
+    }
+
+    fn timers(&mut self) -> Option<&mut dyn TimerQueue> {
+        // A disabled module is paused, so its timers do not fire.
+        if self.enabled { self.inner.timers() } else { None }
+    }
+
+    fn fire_timers(&mut self) {
+        if self.enabled {
+            self.inner.fire_timers();
+        }
     }
-}
\ No newline at end of file
+
+    fn start(&mut self) {
+        self.inner.start();
+    }
+
+    fn stop(&mut self) {
+        self.inner.stop();
+    }
+
+    fn pause(&mut self) {
+        if self.enabled {
+            self.inner.pause();
+        }
+    }
+
+    fn resume(&mut self) {
+        if self.enabled {
+            self.inner.resume();
+        }
+    }
+
+    fn interface(&self) -> Vec<PortEntry> {
+        self.inner.interface()
+    }
+}
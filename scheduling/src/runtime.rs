@@ -0,0 +1,1089 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use ports::prelude::{PortEntry, ReceivePort};
+use crate::{GroupBuilder, Module, ModuleBuilder, SpawnError, SpawnMode, ThreadContainer, ThreadPool, TickSource};
+use crate::group::{unique_name, GroupChildren, GroupData, ModuleData};
+use crate::module::EnabledModule;
+use crate::introspection::{ContainerKind, ContainerSnapshot, GroupSnapshot, ModuleSnapshot, ModuleStatistics, MonitoredModule, RuntimeSnapshot};
+use crate::thread_container::ContainerHandle;
+#[cfg(feature = "tokio")]
+use crate::{AsyncExecutor, AsyncModule, AsyncModuleBuilder};
+#[cfg(feature = "tokio")]
+use crate::async_module::AsyncModuleData;
+
+macro_rules! runtime_id {
+    ($($(#[$meta:meta])* $name:ident),* $(,)?) => {
+        $(
+            $(#[$meta])*
+            #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+            #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+            pub struct $name(usize);
+
+            impl $name {
+                pub(crate) fn next() -> Self {
+                    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+                    Self(COUNTER.fetch_add(1, Ordering::Relaxed))
+                }
+            }
+        )*
+    };
+}
+
+runtime_id!(
+    /// Identifies a module running in a [`ThreadContainer`].
+    ModuleId,
+    /// Identifies a group spawned by a [`Runtime`].
+    GroupId,
+    /// Identifies a [`ThreadContainer`] and its working thread.
+    ContainerId,
+);
+
+/// Scheduling state of a running module or group.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ModuleState {
+    Running,
+    /// Paused by itself or one of its parent groups.
+    Paused,
+    /// An enable port of one of its groups is `false`, see [`crate::GroupBuilder::enable`].
+    Disabled,
+    /// The `update` of the module panicked, it is not updated anymore.
+    Failed,
+}
+
+/// Cycle time of a running module.
+#[derive(Copy, Clone)]
+enum CycleTime {
+    Fixed(Duration),
+    /// Uses the cycle time of its group.
+    Inherited,
+    /// Async modules are scheduled by their trigger.
+    #[cfg(feature = "tokio")]
+    Async,
+}
+
+/// Where a module is running and whether its container was created for it alone.
+struct ModuleEntry {
+    name: String,
+    group: GroupId,
+    container: ContainerId,
+    owns_container: bool,
+    paused: bool,
+    cycle_time: CycleTime,
+    ports: Vec<PortEntry>,
+    /// `None` for async modules.
+    statistics: Option<Arc<Mutex<ModuleStatistics>>>,
+}
+
+/// A spawned group with its direct children and the containers created for it.
+struct GroupEntry {
+    name: String,
+    parent: Option<GroupId>,
+    modules: Vec<ModuleId>,
+    groups: Vec<GroupId>,
+    containers: Vec<ContainerId>,
+    paused: bool,
+    /// `None` uses the cycle time of the parent group.
+    cycle_time: Option<Duration>,
+    enable: Option<ReceivePort<bool>>,
+}
+
+/// Handle to a running system, returned by [`GroupBuilder::spawn`].
+/// Modules and groups can be added to and removed from the running containers.
+/// Dropping the runtime does not stop the system.
+pub struct Runtime {
+    root: GroupId,
+    main_container: ContainerId,
+    /// Container of the shared worker pool, started with the first [`SpawnMode::ThreadPool`] child.
+    pool: Option<ContainerId>,
+    pool_threads: usize,
+    /// Aligns the cycles of all containers except the async executor.
+    tick: Option<TickSource>,
+    /// Executor of the async modules, started with the first async module.
+    #[cfg(feature = "tokio")]
+    executor: Option<AsyncExecutor>,
+    containers: HashMap<ContainerId, ContainerHandle>,
+    groups: HashMap<GroupId, GroupEntry>,
+    modules: HashMap<ModuleId, ModuleEntry>,
+}
+
+impl Runtime {
+    /// Creates a runtime with a running, empty main container.
+    /// The worker pool, if needed, uses `pool_threads` threads.
+    pub(crate) fn new(pool_threads: usize, tick: Option<TickSource>) -> Self {
+        let root = GroupId::next();
+        let main_container = Self::start_container(tick);
+        let main_container_id = main_container.id();
+        let mut groups = HashMap::new();
+        groups.insert(root, GroupEntry {
+            name: String::new(),
+            parent: None,
+            modules: Vec::new(),
+            groups: Vec::new(),
+            containers: vec![main_container_id],
+            paused: false,
+            cycle_time: None,
+            enable: None,
+        });
+        Self {
+            root,
+            main_container: main_container_id,
+            pool: None,
+            pool_threads,
+            tick,
+            #[cfg(feature = "tokio")]
+            executor: None,
+            containers: HashMap::from([(main_container_id, main_container)]),
+            groups,
+            modules: HashMap::new(),
+        }
+    }
+
+    /// The group created by [`GroupBuilder::spawn`].
+    pub fn root_group(&self) -> GroupId {
+        self.root
+    }
+
+    /// The container of the root group.
+    pub fn main_container(&self) -> ContainerId {
+        self.main_container
+    }
+
+    /// The container of the shared worker pool, if a module runs with [`SpawnMode::ThreadPool`].
+    pub fn pool(&self) -> Option<ContainerId> {
+        self.pool
+    }
+
+    /// The container of the async executor, if an async module was added.
+    #[cfg(feature = "tokio")]
+    pub fn executor(&self) -> Option<ContainerId> {
+        self.executor.as_ref().map(AsyncExecutor::id)
+    }
+
+    /// All running containers.
+    pub fn containers(&self) -> impl Iterator<Item = ContainerId> + '_ {
+        self.containers.keys().copied()
+    }
+
+    /// All running modules.
+    pub fn modules(&self) -> impl Iterator<Item = ModuleId> + '_ {
+        self.modules.keys().copied()
+    }
+
+    /// All running groups, including the root group.
+    pub fn groups(&self) -> impl Iterator<Item = GroupId> + '_ {
+        self.groups.keys().copied()
+    }
+
+    /// The container a module is running in.
+    pub fn container_of(&self, module: ModuleId) -> Option<ContainerId> {
+        self.modules.get(&module).map(|entry| entry.container)
+    }
+
+    /// Path of a module, made of the names of its groups and its own name separated by `/`.
+    /// The root group is not part of the path.
+    pub fn module_path(&self, id: ModuleId) -> Option<String> {
+        let entry = self.modules.get(&id)?;
+        Some(self.join_path(entry.group, &entry.name))
+    }
+
+    /// Path of a group. The path of the root group is empty.
+    pub fn group_path(&self, id: GroupId) -> Option<String> {
+        let entry = self.groups.get(&id)?;
+        match entry.parent {
+            Some(parent) => Some(self.join_path(parent, &entry.name)),
+            None => Some(String::new()),
+        }
+    }
+
+    /// Finds a running module by its path, see [`Runtime::module_path`].
+    pub fn find_module(&self, path: &str) -> Option<ModuleId> {
+        let (group, name) = match path.rsplit_once('/') {
+            Some((group_path, name)) => (self.find_group(group_path)?, name),
+            None => (self.root, path),
+        };
+        self.groups[&group].modules.iter().copied().find(|id| self.modules[id].name == name)
+    }
+
+    /// Finds a running group by its path, see [`Runtime::group_path`].
+    pub fn find_group(&self, path: &str) -> Option<GroupId> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(self.root, |group, name| {
+                self.groups[&group].groups.iter().copied().find(|id| self.groups[id].name == name)
+            })
+    }
+
+    /// Effective state of a module, taking paused and disabled parent groups into account.
+    pub fn module_state(&self, id: ModuleId) -> Option<ModuleState> {
+        let entry = self.modules.get(&id)?;
+        if self.module_statistics(id).is_some_and(|statistics| statistics.failed) {
+            return Some(ModuleState::Failed);
+        }
+        Some(self.state(entry.paused || self.group_paused(entry.group), entry.group))
+    }
+
+    /// Effective state of a group, taking paused and disabled parent groups into account.
+    pub fn group_state(&self, id: GroupId) -> Option<ModuleState> {
+        self.groups.contains_key(&id).then(|| self.state(self.group_paused(id), id))
+    }
+
+    /// The modules running in a container.
+    pub fn container_modules(&self, id: ContainerId) -> Vec<ModuleId> {
+        let mut modules: Vec<_> = self.modules.iter()
+            .filter(|(_, entry)| entry.container == id)
+            .map(|(module, _)| *module)
+            .collect();
+        modules.sort();
+        modules
+    }
+
+    /// What a container was started for. Returns `None` if the container is not running.
+    pub fn container_kind(&self, id: ContainerId) -> Option<ContainerKind> {
+        if !self.containers.contains_key(&id) {
+            return None;
+        }
+        #[cfg(feature = "tokio")]
+        if self.executor() == Some(id) {
+            return Some(ContainerKind::Executor);
+        }
+        Some(if id == self.main_container {
+            ContainerKind::Main
+        } else if self.pool == Some(id) {
+            ContainerKind::Pool
+        } else if self.modules.values().any(|entry| entry.owns_container && entry.container == id) {
+            ContainerKind::Module
+        } else {
+            ContainerKind::Group
+        })
+    }
+
+    /// Ports of a module, see [`Module::interface`].
+    pub fn module_ports(&self, id: ModuleId) -> Option<&[PortEntry]> {
+        self.modules.get(&id).map(|entry| entry.ports.as_slice())
+    }
+
+    /// Measured timing of a module, `None` for async modules or if the module is not running.
+    pub fn module_statistics(&self, id: ModuleId) -> Option<ModuleStatistics> {
+        let statistics = self.modules.get(&id)?.statistics.as_ref()?;
+        Some(*statistics.lock().unwrap())
+    }
+
+    /// Containers, groups and modules with their current state, timing and port values.
+    pub fn snapshot(&self) -> RuntimeSnapshot {
+        let mut containers: Vec<_> = self.containers.keys().map(|id| {
+            let kind = self.container_kind(*id).unwrap();
+            ContainerSnapshot {
+                id: *id,
+                kind,
+                threads: match kind {
+                    ContainerKind::Pool | ContainerKind::Executor => self.pool_threads,
+                    _ => 1,
+                },
+                modules: self.container_modules(*id),
+            }
+        }).collect();
+        containers.sort_by_key(|container| container.id);
+        let mut groups: Vec<_> = self.groups.keys().map(|id| GroupSnapshot {
+            id: *id,
+            path: self.group_path(*id).unwrap(),
+            state: self.group_state(*id).unwrap(),
+            cycle_time: self.group_cycle_time(*id),
+        }).collect();
+        groups.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut modules: Vec<_> = self.modules.iter().map(|(id, entry)| ModuleSnapshot {
+            id: *id,
+            path: self.module_path(*id).unwrap(),
+            container: entry.container,
+            state: self.module_state(*id).unwrap(),
+            cycle_time: self.cycle_time(*id),
+            statistics: self.module_statistics(*id),
+            ports: entry.ports.iter().map(Into::into).collect(),
+        }).collect();
+        modules.sort_by(|a, b| a.path.cmp(&b.path));
+        RuntimeSnapshot { containers, groups, modules }
+    }
+
+    /// Effective cycle time of a module, `None` for async modules or if the module is not running.
+    pub fn cycle_time(&self, id: ModuleId) -> Option<Duration> {
+        match self.modules.get(&id)?.cycle_time {
+            CycleTime::Fixed(cycle_time) => Some(cycle_time),
+            CycleTime::Inherited => self.group_cycle_time(self.modules[&id].group),
+            #[cfg(feature = "tokio")]
+            CycleTime::Async => None,
+        }
+    }
+
+    /// Sets the cycle time of the module or group at `path`, starting with the next cycle
+    /// of each module. The cycle time of a group applies to all modules and child groups that
+    /// use the cycle time of their group. Returns false if nothing was found or the module is async.
+    pub fn set_cycle_time(&mut self, path: &str, cycle_time: Duration) -> bool {
+        if let Some(module) = self.find_module(path) {
+            let entry = self.modules.get_mut(&module).unwrap();
+            #[cfg(feature = "tokio")]
+            if matches!(entry.cycle_time, CycleTime::Async) {
+                return false;
+            }
+            entry.cycle_time = CycleTime::Fixed(cycle_time);
+            self.containers[&entry.container].set_cycle_time(module, cycle_time);
+            return true;
+        }
+        let Some(group) = self.find_group(path) else {
+            return false;
+        };
+        self.groups.get_mut(&group).unwrap().cycle_time = Some(cycle_time);
+        self.apply_cycle_time(group, cycle_time);
+        true
+    }
+
+    /// Pauses a module. Its `pause` hook and safe state, see [`ModuleBuilder::on_pause`],
+    /// are applied in its working thread. Returns false if the module is not running.
+    pub fn pause_module(&mut self, id: ModuleId) -> bool {
+        self.set_module_paused(id, true)
+    }
+
+    /// Resumes a module. It stays paused while one of its groups is paused.
+    /// Returns false if the module is not running.
+    pub fn resume_module(&mut self, id: ModuleId) -> bool {
+        self.set_module_paused(id, false)
+    }
+
+    /// Pauses all modules of a group and its child groups.
+    /// Returns false if the group is not running.
+    pub fn pause_group(&mut self, id: GroupId) -> bool {
+        self.set_group_paused(id, true)
+    }
+
+    /// Resumes a group. Modules and child groups that were paused individually stay paused.
+    /// Returns false if the group is not running.
+    pub fn resume_group(&mut self, id: GroupId) -> bool {
+        self.set_group_paused(id, false)
+    }
+
+    /// Pauses the module or group at `path`. Returns false if nothing was found.
+    pub fn pause(&mut self, path: &str) -> bool {
+        match (self.find_module(path), self.find_group(path)) {
+            (Some(module), _) => self.pause_module(module),
+            (None, Some(group)) => self.pause_group(group),
+            (None, None) => false,
+        }
+    }
+
+    /// Resumes the module or group at `path`. Returns false if nothing was found.
+    pub fn resume(&mut self, path: &str) -> bool {
+        match (self.find_module(path), self.find_group(path)) {
+            (Some(module), _) => self.resume_module(module),
+            (None, Some(group)) => self.resume_group(group),
+            (None, None) => false,
+        }
+    }
+
+    /// Adds a module to the root group.
+    /// With [`SpawnMode::GroupThread`] it runs in the main container, otherwise in a new thread.
+    pub fn add_module<M: Module + Send + 'static>(&mut self, builder: ModuleBuilder<M>) -> ModuleId {
+        let module = self.unique_root_child(builder.into());
+        self.spawn_module(module, self.root, self.main_container)
+    }
+
+    /// Adds a module to the root group, running in the given container.
+    /// Returns `None` if the container is not running.
+    pub fn add_module_to<M: Module + Send + 'static>(&mut self, container: ContainerId, builder: ModuleBuilder<M>) -> Option<ModuleId> {
+        if !self.containers.contains_key(&container) {
+            return None;
+        }
+        let module = self.unique_root_child(builder.into());
+        Some(self.insert_module(self.root, container, module))
+    }
+
+    /// Adds an async module to the root group, running on the async executor.
+    #[cfg(feature = "tokio")]
+    pub fn add_async_module<M: AsyncModule>(&mut self, builder: AsyncModuleBuilder<M>) -> ModuleId {
+        let mut module: AsyncModuleData = builder.into();
+        module.name = unique_name(self.root_child_names().iter(), module.name);
+        self.insert_async_module(self.root, module)
+    }
+
+    /// Adds a group as child of the root group.
+    /// With [`SpawnMode::GroupThread`] it runs in the main container, otherwise in a new thread.
+    /// Panics if the group can not be spawned, see [`GroupBuilder::try_spawn`].
+    pub fn add_group<G: Into<GroupBuilder>>(&mut self, group: G) -> GroupId {
+        let group = self.checked_group(group.into());
+        let spawn_mode = group.spawn_mode;
+        let (id, children) = self.new_group(self.root, group);
+        let container = match spawn_mode {
+            SpawnMode::GroupThread => self.main_container,
+            SpawnMode::NewThread => self.new_container(id),
+            SpawnMode::ThreadPool => self.pool_container(),
+        };
+        self.spawn_children(children, id, container);
+        id
+    }
+
+    /// Adds a group as child of the root group. Its modules with [`SpawnMode::GroupThread`]
+    /// run in the given container. Returns `None` if the container is not running.
+    pub fn add_group_to<G: Into<GroupBuilder>>(&mut self, container: ContainerId, group: G) -> Option<GroupId> {
+        if !self.containers.contains_key(&container) {
+            return None;
+        }
+        let group = self.checked_group(group.into());
+        let (id, children) = self.new_group(self.root, group);
+        self.spawn_children(children, id, container);
+        Some(id)
+    }
+
+    /// Removes a module from its container after calling its `stop` hook.
+    /// A thread that was created only for this module is shut down.
+    /// Returns false if the module is not running.
+    pub fn remove_module(&mut self, id: ModuleId) -> bool {
+        let Some(entry) = self.modules.remove(&id) else {
+            return false;
+        };
+        if let Some(group) = self.groups.get_mut(&entry.group) {
+            group.modules.retain(|module| *module != id);
+        }
+        let removed = self.containers.get(&entry.container).is_some_and(|container| container.remove_module(id));
+        if entry.owns_container {
+            self.shutdown_container(entry.container);
+            if let Some(group) = self.groups.get_mut(&entry.group) {
+                group.containers.retain(|container| *container != entry.container);
+            }
+        }
+        removed
+    }
+
+    /// Removes a group with all its modules and child groups.
+    /// The `stop` hook of every module is called and threads created for the group are shut down.
+    /// The root group can not be removed. Returns false if the group is not running.
+    pub fn remove_group(&mut self, id: GroupId) -> bool {
+        if id == self.root {
+            return false;
+        }
+        let Some(entry) = self.groups.remove(&id) else {
+            return false;
+        };
+        if let Some(parent) = entry.parent.and_then(|parent| self.groups.get_mut(&parent)) {
+            parent.groups.retain(|group| *group != id);
+        }
+        for child in entry.groups {
+            self.remove_group(child);
+        }
+        for module in entry.modules {
+            self.remove_module(module);
+        }
+        for container in entry.containers {
+            self.shutdown_container(container);
+        }
+        true
+    }
+
+    /// Stops all containers. The `stop` hook of every module is called.
+    pub fn shutdown(self) {
+        for container in self.containers.values() {
+            container.shutdown();
+        }
+    }
+
+    /// Spawns the children of the group built by [`GroupBuilder::spawn`] into the root group.
+    pub(crate) fn spawn_root(&mut self, group: GroupData) {
+        let root = self.groups.get_mut(&self.root).unwrap();
+        root.cycle_time = group.cycle_time;
+        root.enable = group.enable;
+        let container = match group.spawn_mode {
+            SpawnMode::ThreadPool => self.pool_container(),
+            SpawnMode::GroupThread | SpawnMode::NewThread => self.main_container,
+        };
+        self.spawn_children(group.group, self.root, container);
+    }
+
+    /// Spawns the children of a group builder as part of the group `group`.
+    fn spawn_children(&mut self, children: GroupChildren, group: GroupId, container: ContainerId) {
+        for child_module in children.modules {
+            self.spawn_module(child_module, group, container);
+        }
+        #[cfg(feature = "tokio")]
+        for child_module in children.async_modules {
+            self.insert_async_module(group, child_module);
+        }
+        for child_group in children.groups {
+            let spawn_mode = child_group.spawn_mode;
+            let (id, children) = self.new_group(group, child_group);
+            let container = match spawn_mode {
+                SpawnMode::GroupThread => container,
+                SpawnMode::NewThread => self.new_container(id),
+                SpawnMode::ThreadPool => self.pool_container(),
+            };
+            self.spawn_children(children, id, container);
+        }
+    }
+
+    /// Spawns a module in `container`, in a container of its own or in the worker pool.
+    fn spawn_module(&mut self, module: ModuleData, group: GroupId, container: ContainerId) -> ModuleId {
+        match module.spawn_mode {
+            SpawnMode::GroupThread => self.insert_module(group, container, module),
+            SpawnMode::ThreadPool => {
+                let pool = self.pool_container();
+                self.insert_module(group, pool, module)
+            }
+            SpawnMode::NewThread => {
+                let new_container = Self::start_container(self.tick);
+                let new_container_id = new_container.id();
+                self.containers.insert(new_container_id, new_container);
+                let id = self.insert_module(group, new_container_id, module);
+                self.modules.get_mut(&id).unwrap().owns_container = true;
+                id
+            }
+        }
+    }
+
+    /// Panics if neither the module nor one of its groups sets a cycle time.
+    fn insert_module(&mut self, group: GroupId, container: ContainerId, module: ModuleData) -> ModuleId {
+        let id = ModuleId::next();
+        let cycle_time = module.cycle_time.or_else(|| self.group_cycle_time(group)).unwrap_or_else(|| {
+            panic!("{}", SpawnError::MissingCycleTime { module: self.join_path(group, &module.name) })
+        });
+        let ports = module.module.interface();
+        let enable = self.enable_ports(group);
+        let inner = match enable.is_empty() {
+            true => module.module,
+            false => Box::new(EnabledModule::new(module.module, enable)),
+        };
+        let (inner, statistics) = MonitoredModule::new(inner);
+        let container_handle = &self.containers[&container];
+        container_handle.add_module_with_id(id, Box::new(inner), cycle_time);
+        if self.group_paused(group) {
+            container_handle.set_paused(id, true);
+        }
+        self.modules.insert(id, ModuleEntry {
+            name: module.name,
+            group,
+            container,
+            owns_container: false,
+            paused: false,
+            cycle_time: module.cycle_time.map_or(CycleTime::Inherited, CycleTime::Fixed),
+            ports,
+            statistics: Some(statistics),
+        });
+        self.groups.get_mut(&group).unwrap().modules.push(id);
+        id
+    }
+
+    #[cfg(feature = "tokio")]
+    fn insert_async_module(&mut self, group: GroupId, module: AsyncModuleData) -> ModuleId {
+        let id = ModuleId::next();
+        let container = self.executor_container();
+        self.executor.as_ref().unwrap().spawn_with_id(id, module.spawn);
+        if self.group_paused(group) {
+            self.containers[&container].set_paused(id, true);
+        }
+        self.modules.insert(id, ModuleEntry {
+            name: module.name,
+            group,
+            container,
+            owns_container: false,
+            paused: false,
+            cycle_time: CycleTime::Async,
+            ports: Vec::new(),
+            statistics: None,
+        });
+        self.groups.get_mut(&group).unwrap().modules.push(id);
+        id
+    }
+
+    /// Adds the entry of a group, returning its id and the children to spawn.
+    fn new_group(&mut self, parent: GroupId, group: GroupData) -> (GroupId, GroupChildren) {
+        let id = GroupId::next();
+        let name = if parent == self.root {
+            unique_name(self.root_child_names().iter(), group.name)
+        } else {
+            group.name
+        };
+        self.groups.insert(id, GroupEntry {
+            name,
+            parent: Some(parent),
+            modules: Vec::new(),
+            groups: Vec::new(),
+            containers: Vec::new(),
+            paused: false,
+            cycle_time: group.cycle_time,
+            enable: group.enable,
+        });
+        self.groups.get_mut(&parent).unwrap().groups.push(id);
+        (id, group.group)
+    }
+
+    /// Checks a group added to the root group, panicking on errors.
+    fn checked_group(&self, group: GroupBuilder) -> GroupData {
+        if let Err(error) = group.check(self.groups[&self.root].cycle_time) {
+            panic!("{error}");
+        }
+        group.into_data()
+    }
+
+    /// Starts a new container owned by `group`.
+    fn new_container(&mut self, group: GroupId) -> ContainerId {
+        let container = Self::start_container(self.tick);
+        let id = container.id();
+        self.containers.insert(id, container);
+        self.groups.get_mut(&group).unwrap().containers.push(id);
+        id
+    }
+
+    fn start_container(tick: Option<TickSource>) -> ContainerHandle {
+        match tick {
+            Some(tick) => ThreadContainer::new().with_tick_source(tick).run(),
+            None => ThreadContainer::new().run(),
+        }
+    }
+
+    fn set_module_paused(&mut self, id: ModuleId, paused: bool) -> bool {
+        let Some(entry) = self.modules.get_mut(&id) else {
+            return false;
+        };
+        entry.paused = paused;
+        self.apply_module_state(id);
+        true
+    }
+
+    fn set_group_paused(&mut self, id: GroupId, paused: bool) -> bool {
+        let Some(entry) = self.groups.get_mut(&id) else {
+            return false;
+        };
+        entry.paused = paused;
+        self.apply_group_state(id);
+        true
+    }
+
+    /// Sends the effective state of a module to its container.
+    fn apply_module_state(&self, id: ModuleId) {
+        let entry = &self.modules[&id];
+        let paused = entry.paused || self.group_paused(entry.group);
+        self.containers[&entry.container].set_paused(id, paused);
+    }
+
+    fn apply_group_state(&self, id: GroupId) {
+        let entry = &self.groups[&id];
+        for module in &entry.modules {
+            self.apply_module_state(*module);
+        }
+        for group in &entry.groups {
+            self.apply_group_state(*group);
+        }
+    }
+
+    /// Sends the new cycle time of a group to the modules using it.
+    fn apply_cycle_time(&self, id: GroupId, cycle_time: Duration) {
+        let entry = &self.groups[&id];
+        for module in &entry.modules {
+            let module_entry = &self.modules[module];
+            if matches!(module_entry.cycle_time, CycleTime::Inherited) {
+                self.containers[&module_entry.container].set_cycle_time(*module, cycle_time);
+            }
+        }
+        for group in &entry.groups {
+            if self.groups[group].cycle_time.is_none() {
+                self.apply_cycle_time(*group, cycle_time);
+            }
+        }
+    }
+
+    /// Cycle time of a group or its closest parent setting one.
+    fn group_cycle_time(&self, id: GroupId) -> Option<Duration> {
+        let mut group = Some(id);
+        while let Some(entry) = group.and_then(|id| self.groups.get(&id)) {
+            if entry.cycle_time.is_some() {
+                return entry.cycle_time;
+            }
+            group = entry.parent;
+        }
+        None
+    }
+
+    /// Connected enable ports of a group and its parents.
+    fn enable_ports(&self, id: GroupId) -> Vec<ReceivePort<bool>> {
+        let mut ports = Vec::new();
+        let mut group = Some(id);
+        while let Some(entry) = group.and_then(|id| self.groups.get(&id)) {
+            ports.extend(entry.enable.clone());
+            group = entry.parent;
+        }
+        ports
+    }
+
+    /// Whether a group or one of its parents is paused.
+    fn group_paused(&self, id: GroupId) -> bool {
+        let mut group = Some(id);
+        while let Some(entry) = group.and_then(|id| self.groups.get(&id)) {
+            if entry.paused {
+                return true;
+            }
+            group = entry.parent;
+        }
+        false
+    }
+
+    /// Whether an enable port of a group or one of its parents is `false`.
+    fn group_disabled(&self, id: GroupId) -> bool {
+        self.enable_ports(id).into_iter().any(|mut port| {
+            port.update();
+            !*port.get_data()
+        })
+    }
+
+    fn state(&self, paused: bool, group: GroupId) -> ModuleState {
+        match paused {
+            true => ModuleState::Paused,
+            false if self.group_disabled(group) => ModuleState::Disabled,
+            false => ModuleState::Running,
+        }
+    }
+
+    fn join_path(&self, group: GroupId, name: &str) -> String {
+        match self.group_path(group) {
+            Some(path) if !path.is_empty() => format!("{path}/{name}"),
+            _ => name.to_string(),
+        }
+    }
+
+    fn root_child_names(&self) -> Vec<String> {
+        let root = &self.groups[&self.root];
+        root.modules.iter().map(|id| self.modules[id].name.clone())
+            .chain(root.groups.iter().map(|id| self.groups[id].name.clone()))
+            .collect()
+    }
+
+    /// Makes the name of a module added to the root group unique.
+    fn unique_root_child(&self, mut module: ModuleData) -> ModuleData {
+        module.name = unique_name(self.root_child_names().iter(), module.name);
+        module
+    }
+
+    /// The container of the worker pool, started on first use.
+    pub(crate) fn pool_container(&mut self) -> ContainerId {
+        if let Some(pool) = self.pool {
+            return pool;
+        }
+        let pool = match self.tick {
+            Some(tick) => ThreadPool::with_tick_source(self.pool_threads, tick),
+            None => ThreadPool::new(self.pool_threads),
+        }.handle();
+        let id = pool.id();
+        self.containers.insert(id, pool);
+        self.pool = Some(id);
+        id
+    }
+
+    /// The container of the async executor, started on first use with as many threads as the worker pool.
+    #[cfg(feature = "tokio")]
+    fn executor_container(&mut self) -> ContainerId {
+        let executor = self.executor.get_or_insert_with(|| AsyncExecutor::new(self.pool_threads));
+        let id = executor.id();
+        self.containers.entry(id).or_insert_with(|| executor.handle());
+        id
+    }
+
+    fn shutdown_container(&mut self, id: ContainerId) {
+        if let Some(container) = self.containers.remove(&id) {
+            container.shutdown();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::{channel, Sender};
+    use std::time::Duration;
+    use std::sync::mpsc::Receiver;
+    use ports::prelude::SendPort;
+    use crate::{Group, GroupBuilder, Module, ModuleBuilder, ModuleState, SpawnError, SpawnMode};
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Start,
+        Update,
+        Stop,
+        Pause,
+        Resume,
+        SafeState,
+    }
+
+    struct EventModule {
+        events: Sender<Event>,
+    }
+
+    impl Module for EventModule {
+        fn update(&mut self) {
+            let _ = self.events.send(Event::Update);
+        }
+        fn start(&mut self) {
+            let _ = self.events.send(Event::Start);
+        }
+        fn stop(&mut self) {
+            let _ = self.events.send(Event::Stop);
+        }
+        fn pause(&mut self) {
+            let _ = self.events.send(Event::Pause);
+        }
+        fn resume(&mut self) {
+            let _ = self.events.send(Event::Resume);
+        }
+    }
+
+    struct EventGroup {
+        events: Vec<Sender<Event>>,
+    }
+
+    impl Group for EventGroup {
+        fn init(&mut self, group: &mut GroupBuilder) {
+            for events in self.events.drain(..) {
+                group.add_module(ModuleBuilder::new(
+                    EventModule { events },
+                    Duration::from_millis(5),
+                    SpawnMode::GroupThread,
+                ));
+            }
+        }
+    }
+
+    fn wait_for(events: &Receiver<Event>, event: Event) {
+        while events.recv().unwrap() != event {}
+    }
+
+    fn module(events: Sender<Event>, spawn_mode: SpawnMode) -> ModuleBuilder<EventModule> {
+        ModuleBuilder::new(EventModule { events }, Duration::from_millis(5), spawn_mode)
+    }
+
+    #[test]
+    fn add_and_remove_module() {
+        let mut runtime = GroupBuilder::empty().spawn();
+        for spawn_mode in [SpawnMode::GroupThread, SpawnMode::NewThread] {
+            let (events_tx, events_rx) = channel();
+            let id = runtime.add_module(module(events_tx, spawn_mode));
+            assert!(matches!(events_rx.recv().unwrap(), Event::Start));
+            assert!(matches!(events_rx.recv().unwrap(), Event::Update));
+
+            assert!(runtime.remove_module(id));
+            assert!(!runtime.remove_module(id));
+            let last = events_rx.iter().last().unwrap();
+            assert!(matches!(last, Event::Stop));
+        }
+        assert_eq!(runtime.containers().count(), 1);
+        runtime.shutdown();
+    }
+
+    #[test]
+    fn add_and_remove_group() {
+        let mut runtime = GroupBuilder::empty().spawn();
+        let (events_tx_1, events_rx_1) = channel();
+        let (events_tx_2, events_rx_2) = channel();
+        let group = GroupBuilder::new(
+            EventGroup { events: vec![events_tx_1, events_tx_2] },
+            SpawnMode::NewThread,
+        );
+        let id = runtime.add_group(group);
+        assert_eq!(runtime.containers().count(), 2);
+        assert_eq!(runtime.modules().count(), 2);
+        assert!(matches!(events_rx_1.recv().unwrap(), Event::Start));
+        assert!(matches!(events_rx_2.recv().unwrap(), Event::Start));
+
+        assert!(runtime.remove_group(id));
+        assert!(!runtime.remove_group(runtime.root_group()));
+        assert!(matches!(events_rx_1.iter().last().unwrap(), Event::Stop));
+        assert!(matches!(events_rx_2.iter().last().unwrap(), Event::Stop));
+        assert_eq!(runtime.containers().count(), 1);
+        assert_eq!(runtime.modules().count(), 0);
+        runtime.shutdown();
+    }
+
+    #[test]
+    fn pause_and_resume() {
+        let (events_tx_1, events_rx_1) = channel();
+        let (events_tx_2, events_rx_2) = channel();
+        let group = GroupBuilder::new(
+            EventGroup { events: vec![events_tx_1, events_tx_2] },
+            SpawnMode::GroupThread,
+        ).with_name("events");
+        let mut builder = GroupBuilder::empty();
+        builder.add_group(group);
+        let mut runtime = builder.spawn();
+
+        let group = runtime.find_group("events").unwrap();
+        let module_1 = runtime.find_module("events/EventModule").unwrap();
+        let module_2 = runtime.find_module("events/EventModule_1").unwrap();
+        assert_eq!(runtime.module_path(module_2).as_deref(), Some("events/EventModule_1"));
+        assert_eq!(runtime.group_path(group).as_deref(), Some("events"));
+        assert_eq!(runtime.module_state(module_1), Some(ModuleState::Running));
+
+        assert!(runtime.pause("events"));
+        assert_eq!(runtime.group_state(group), Some(ModuleState::Paused));
+        assert_eq!(runtime.module_state(module_1), Some(ModuleState::Paused));
+        wait_for(&events_rx_1, Event::Pause);
+        wait_for(&events_rx_2, Event::Pause);
+        assert!(events_rx_1.recv_timeout(Duration::from_millis(30)).is_err());
+
+        assert!(runtime.pause_module(module_2));
+        assert!(runtime.resume_group(group));
+        assert_eq!(runtime.module_state(module_1), Some(ModuleState::Running));
+        assert_eq!(runtime.module_state(module_2), Some(ModuleState::Paused));
+        wait_for(&events_rx_1, Event::Resume);
+        wait_for(&events_rx_1, Event::Update);
+        assert!(events_rx_2.recv_timeout(Duration::from_millis(30)).is_err());
+
+        assert!(runtime.resume("events/EventModule_1"));
+        wait_for(&events_rx_2, Event::Resume);
+        wait_for(&events_rx_2, Event::Update);
+        assert!(!runtime.pause("events/missing"));
+        runtime.shutdown();
+    }
+
+    #[test]
+    fn pause_applies_safe_state() {
+        let mut runtime = GroupBuilder::empty().spawn();
+        let (events_tx, events_rx) = channel();
+        let builder = module(events_tx, SpawnMode::GroupThread)
+            .with_name("safe")
+            .on_pause(|module| module.events.send(Event::SafeState).unwrap());
+        let id = runtime.add_module(builder);
+        assert_eq!(runtime.find_module("safe"), Some(id));
+
+        runtime.pause_module(id);
+        wait_for(&events_rx, Event::Pause);
+        assert_eq!(events_rx.recv().unwrap(), Event::SafeState);
+        runtime.shutdown();
+    }
+
+    #[test]
+    fn thread_pool_group() {
+        let (events_tx_1, events_rx_1) = channel();
+        let (events_tx_2, events_rx_2) = channel();
+        let group = GroupBuilder::new(
+            EventGroup { events: vec![events_tx_1, events_tx_2] },
+            SpawnMode::ThreadPool,
+        );
+        let mut runtime = group.spawn_with_pool(2);
+        let pool = runtime.pool().unwrap();
+        let module = runtime.find_module("EventModule").unwrap();
+        assert_eq!(runtime.container_of(module), Some(pool));
+
+        wait_for(&events_rx_1, Event::Update);
+        wait_for(&events_rx_2, Event::Update);
+        assert!(runtime.pause_module(module));
+        wait_for(&events_rx_1, Event::Pause);
+        assert!(runtime.remove_module(module));
+        assert_eq!(events_rx_1.iter().last(), Some(Event::Stop));
+        assert!(runtime.containers().any(|container| container == pool));
+        runtime.shutdown();
+        assert_eq!(events_rx_2.iter().last(), Some(Event::Stop));
+    }
+
+    #[test]
+    fn group_cycle_time() {
+        let ms = Duration::from_millis;
+        let (inherited_tx, inherited_rx) = channel();
+        let (fixed_tx, fixed_rx) = channel();
+        let mut subsystem = GroupBuilder::empty().with_name("subsystem");
+        subsystem.add_module(ModuleBuilder::inherit(EventModule { events: inherited_tx }).with_name("inherited"));
+        subsystem.add_module(module(fixed_tx, SpawnMode::NewThread).with_name("fixed"));
+        let mut builder = GroupBuilder::empty().with_cycle_time(ms(10));
+        builder.add_group(subsystem);
+        let mut runtime = builder.spawn();
+
+        let inherited = runtime.find_module("subsystem/inherited").unwrap();
+        let fixed = runtime.find_module("subsystem/fixed").unwrap();
+        assert_eq!(runtime.cycle_time(inherited), Some(ms(10)));
+        assert_eq!(runtime.cycle_time(fixed), Some(ms(5)));
+        wait_for(&inherited_rx, Event::Update);
+
+        // Slowing the subsystem down only affects modules without their own cycle time.
+        assert!(runtime.set_cycle_time("subsystem", Duration::from_secs(3600)));
+        assert_eq!(runtime.cycle_time(inherited), Some(Duration::from_secs(3600)));
+        assert_eq!(runtime.cycle_time(fixed), Some(ms(5)));
+        while inherited_rx.recv_timeout(ms(50)).is_ok() {}
+        wait_for(&fixed_rx, Event::Update);
+        // Speeding up again does not wait for the cycle scheduled an hour ahead.
+        assert!(runtime.set_cycle_time("subsystem", ms(10)));
+        assert_eq!(inherited_rx.recv_timeout(Duration::from_secs(1)), Ok(Event::Update));
+
+        assert!(runtime.set_cycle_time("subsystem/fixed", ms(20)));
+        assert_eq!(runtime.cycle_time(fixed), Some(ms(20)));
+        assert!(!runtime.set_cycle_time("missing", ms(20)));
+        runtime.shutdown();
+
+        let mut builder = GroupBuilder::empty();
+        let (events_tx, _events_rx) = channel();
+        builder.add_module(ModuleBuilder::inherit(EventModule { events: events_tx }).with_name("orphan"));
+        let error = builder.try_spawn().err().unwrap();
+        assert_eq!(error, SpawnError::MissingCycleTime { module: "orphan".to_string() });
+        assert_eq!(error.to_string(), "module `orphan` has no cycle time and none of its groups sets one");
+    }
+
+    #[test]
+    fn group_enable_port() {
+        let (events_tx, events_rx) = channel();
+        let group = GroupBuilder::new(EventGroup { events: vec![events_tx] }, SpawnMode::GroupThread)
+            .with_name("events");
+        let mut enable = SendPort::new(true);
+        let mut builder = GroupBuilder::empty();
+        builder.connect(&enable, group.enable()).unwrap();
+        builder.add_group(group);
+        let runtime = builder.spawn();
+        wait_for(&events_rx, Event::Update);
+
+        enable.send(false);
+        wait_for(&events_rx, Event::Pause);
+        assert!(events_rx.recv_timeout(Duration::from_millis(30)).is_err());
+        let module = runtime.find_module("events/EventModule").unwrap();
+        assert_eq!(runtime.module_state(module), Some(ModuleState::Disabled));
+        assert_eq!(runtime.group_state(runtime.find_group("events").unwrap()), Some(ModuleState::Disabled));
+
+        enable.send(true);
+        assert_eq!(events_rx.recv().unwrap(), Event::Resume);
+        wait_for(&events_rx, Event::Update);
+        assert_eq!(runtime.module_state(module), Some(ModuleState::Running));
+        runtime.shutdown();
+    }
+
+    #[cfg(feature = "tokio")]
+    struct AsyncEventModule {
+        events: Sender<Event>,
+    }
+
+    #[cfg(feature = "tokio")]
+    impl crate::AsyncModule for AsyncEventModule {
+        async fn update(&mut self) {
+            let _ = self.events.send(Event::Update);
+        }
+        async fn stop(&mut self) {
+            let _ = self.events.send(Event::Stop);
+        }
+        async fn pause(&mut self) {
+            let _ = self.events.send(Event::Pause);
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn async_module_in_group() {
+        use crate::{AsyncModuleBuilder, AsyncTrigger};
+        let (events_tx, events_rx) = channel();
+        let mut group = GroupBuilder::empty().with_name("group");
+        group.add_async_module(AsyncModuleBuilder::new(
+            AsyncEventModule { events: events_tx },
+            AsyncTrigger::Periodic(Duration::from_millis(5)),
+        ).with_name("async"));
+        let mut builder = GroupBuilder::empty();
+        builder.add_group(group);
+        let mut runtime = builder.spawn();
+        assert!(runtime.executor().is_some());
+
+        let id = runtime.find_module("group/async").unwrap();
+        assert_eq!(runtime.container_of(id), runtime.executor());
+        wait_for(&events_rx, Event::Update);
+        assert!(runtime.pause_module(id));
+        wait_for(&events_rx, Event::Pause);
+        assert!(runtime.remove_module(id));
+        assert!(matches!(events_rx.iter().last().unwrap(), Event::Stop));
+        runtime.shutdown();
+    }
+}
@@ -1,49 +1,59 @@
 use proc_macro::TokenStream;
-use quote::{quote, ToTokens};
-use syn::{
-    parse_macro_input, ItemFn, Stmt, Pat, Local, Block,
-    visit_mut::VisitMut,
-};
-
-struct SpawnInjector;
-
-impl VisitMut for SpawnInjector {
-    fn visit_block_mut(&mut self, block: &mut Block) {
-        syn::visit_mut::visit_block_mut(self, block);
-        let mut injections = Vec::new();
-
-        for stmt in &block.stmts {
-            if let Stmt::Local(Local { pat, init: Some(init), .. }) = stmt {
-                if let Pat::Ident(ident) = pat {
-                    let var_name = &ident.ident;
-                    let init_expr = &init.expr.to_token_stream().to_string();
-
-                    if init_expr.contains("ModuleBuilder") {
-                        injections.push(syn::parse_quote! {
-                            builder.add_module(#var_name);
-                        });
-                    } else if init_expr.contains("GroupBuilder") {
-                        injections.push(syn::parse_quote! {
-                            builder.add_group(#var_name);
-                        });
-                    }
-                }
-            }
-        }
-
-        block.stmts.extend(injections);
-    }
+use quote::quote;
+use syn::{parse_macro_input, FnArg, Ident, ItemFn, Pat, ReturnType, Type};
+
+/// Finds the `&mut GroupBuilder` argument the spawned modules and groups are added to.
+fn builder_argument(function: &ItemFn) -> Option<&Ident> {
+    function.sig.inputs.iter().find_map(|argument| {
+        let FnArg::Typed(argument) = argument else { return None };
+        let Pat::Ident(ident) = &*argument.pat else { return None };
+        let Type::Reference(reference) = &*argument.ty else { return None };
+        let Type::Path(path) = &*reference.elem else { return None };
+        let is_builder = reference.mutability.is_some()
+            && path.path.segments.last().is_some_and(|segment| segment.ident == "GroupBuilder");
+        is_builder.then_some(&ident.ident)
+    })
 }
 
-/// Automatically injects `builder.add_module(...)` or `builder.add_group(...)`
-/// calls for each `ModuleBuilder` or `GroupBuilder` variable declared in the
-/// annotated function.
+/// Adds every `ModuleBuilder` and `GroupBuilder` that is dropped while the annotated
+/// function runs to its `&mut GroupBuilder` argument, see `scheduling::SpawnScope`.
+/// This includes builders created in helper functions, returned from `match` expressions
+/// or created in loops. Builders added explicitly or returned from the function are not affected.
+///
+/// The body runs in a closure returning the result of the function, so `return` and `?` leave
+/// the body with the value the function returns once the builders are added. `async fn` is
+/// rejected, as the scope is tied to the thread and the closure can not `.await`.
 #[proc_macro_attribute]
 pub fn spawns(_attr: TokenStream, item: TokenStream) -> TokenStream {
-    let mut input = parse_macro_input!(item as ItemFn);
-
-    let mut injector = SpawnInjector;
-    injector.visit_item_fn_mut(&mut input);
-
-    TokenStream::from(quote! { #input })
+    let function = parse_macro_input!(item as ItemFn);
+    if let Some(asyncness) = &function.sig.asyncness {
+        return syn::Error::new_spanned(asyncness, "`#[spawns]` can not be applied to an `async fn`")
+            .to_compile_error().into();
+    }
+    let Some(builder) = builder_argument(&function).cloned() else {
+        return syn::Error::new_spanned(
+            &function.sig,
+            "`#[spawns]` requires an argument of type `&mut GroupBuilder`",
+        ).to_compile_error().into();
+    };
+
+    let ItemFn { attrs, vis, sig, block } = function;
+    let output = match &sig.output {
+        ReturnType::Default => quote! { -> () },
+        ReturnType::Type(_, ty) if matches!(**ty, Type::ImplTrait(_)) => quote! {},
+        ReturnType::Type(arrow, ty) => quote! { #arrow #ty },
+    };
+
+    // The body runs in a closure, so its locals are dropped and `return` leaves
+    // the body before the collected builders are added.
+    TokenStream::from(quote! {
+        #(#attrs)*
+        #vis #sig {
+            let spawn_scope = ::scheduling::SpawnScope::enter();
+            #[allow(clippy::redundant_closure_call)]
+            let spawned = (|| #output #block)();
+            spawn_scope.finish(#builder);
+            spawned
+        }
+    })
 }
@@ -1,5 +1,8 @@
 #[derive(Copy, Clone)]
 pub enum SpawnMode {
     GroupThread,
-    NewThread
-}
\ No newline at end of file
+    NewThread,
+    /// Run on the worker pool shared by all groups of a [`crate::Runtime`].
+    /// Modules are scheduled by deadline and never run concurrently with themselves.
+    ThreadPool,
+}
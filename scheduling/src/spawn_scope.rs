@@ -0,0 +1,176 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::group::ModuleData;
+use crate::GroupBuilder;
+
+/// A [`crate::ModuleBuilder`] or [`GroupBuilder`] dropped without being added.
+pub(crate) enum Dropped {
+    Module(ModuleData),
+    Group(GroupBuilder),
+}
+
+/// Builders collected by a scope with their creation order.
+type Collected = Vec<(u64, Dropped)>;
+
+thread_local! {
+    /// Active scopes of this thread. `None` hides the enclosing scopes,
+    /// e.g. while [`GroupBuilder::new`] initializes a child group.
+    static SCOPES: RefCell<Vec<Option<Collected>>> = const { RefCell::new(Vec::new()) };
+}
+
+static NEXT_ORDER: AtomicU64 = AtomicU64::new(0);
+
+/// Creation order of builders, so collected builders are added in the order they were created.
+pub(crate) fn next_order() -> u64 {
+    NEXT_ORDER.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Whether builders dropped now are collected by a [`SpawnScope`].
+pub(crate) fn is_collecting() -> bool {
+    SCOPES.try_with(|scopes| matches!(scopes.borrow().last(), Some(Some(_)))).unwrap_or(false)
+}
+
+/// Collects a dropped builder, see [`is_collecting`].
+pub(crate) fn collect(order: u64, dropped: Dropped) {
+    SCOPES.with(|scopes| {
+        if let Some(Some(collected)) = scopes.borrow_mut().last_mut() {
+            collected.push((order, dropped));
+        }
+    });
+}
+
+/// Collects all module and group builders dropped in this thread while the scope is active,
+/// including builders created in helper functions or moved into collections.
+/// [`SpawnScope::finish`] adds them to a group in the order they were created.
+///
+/// Used by the [`crate::spawns`] attribute. Builders that are dropped outside of a scope
+/// without being added print a warning. The scope belongs to the current thread,
+/// so `#[spawns]` rejects `async fn`:
+///
+/// ```compile_fail
+/// use scheduling::{spawns, GroupBuilder};
+/// #[spawns]
+/// async fn init(builder: &mut GroupBuilder) {}
+/// ```
+pub struct SpawnScope {
+    finished: bool,
+}
+
+impl SpawnScope {
+    pub fn enter() -> Self {
+        SCOPES.with(|scopes| scopes.borrow_mut().push(Some(Vec::new())));
+        Self { finished: false }
+    }
+
+    /// Hides the enclosing scopes until the returned scope is dropped.
+    pub(crate) fn barrier() -> Self {
+        SCOPES.with(|scopes| scopes.borrow_mut().push(None));
+        Self { finished: false }
+    }
+
+    /// Leaves the scope and adds the collected builders to `builder`.
+    pub fn finish(mut self, builder: &mut GroupBuilder) {
+        self.finished = true;
+        let mut collected = SCOPES.with(|scopes| scopes.borrow_mut().pop()).flatten().unwrap_or_default();
+        collected.sort_by_key(|(order, _)| *order);
+        for (_, dropped) in collected {
+            match dropped {
+                Dropped::Module(module) => builder.add_module_data(module),
+                Dropped::Group(group) => builder.add_group(group),
+            }
+        }
+    }
+}
+
+impl Drop for SpawnScope {
+    fn drop(&mut self) {
+        if !self.finished {
+            // Collected builders of an unfinished scope, e.g. after a panic, are discarded.
+            let _ = SCOPES.try_with(|scopes| scopes.borrow_mut().pop());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::{spawns, Group, GroupBuilder, Module, ModuleBuilder, SpawnMode};
+
+    struct Idle;
+
+    impl Module for Idle {
+        fn update(&mut self) {}
+    }
+
+    fn idle(name: &str) -> ModuleBuilder<Idle> {
+        ModuleBuilder::new(Idle, Duration::from_millis(10), SpawnMode::GroupThread).with_name(name)
+    }
+
+    /// Adds `first`, `second` and the child group, `manual` is added explicitly.
+    struct Spawning {
+        second: bool,
+    }
+
+    impl Group for Spawning {
+        #[spawns]
+        fn init(&mut self, builder: &mut GroupBuilder) {
+            let first = idle("first");
+            let _module_builder_config = Duration::from_millis(1);
+            let _second = match self.second {
+                true => Some(idle("second")),
+                false => None,
+            };
+            builder.add_module(idle("manual"));
+            drop(first);
+            let child = GroupBuilder::new(Child, SpawnMode::GroupThread);
+            if !self.second {
+                return;
+            }
+            let _late = child;
+        }
+    }
+
+    struct Child;
+
+    impl Group for Child {
+        #[spawns]
+        fn init(&mut self, _builder: &mut GroupBuilder) {
+            let _module = idle("child");
+        }
+    }
+
+    fn names(builder: &GroupBuilder) -> Vec<&str> {
+        let modules = builder.children.modules.iter().map(|module| module.name.as_str());
+        modules.chain(builder.children.groups.iter().map(|group| group.name.as_str())).collect()
+    }
+
+    #[test]
+    fn spawns_dropped_builders() {
+        let group: GroupBuilder = GroupBuilder::new(Spawning { second: true }, SpawnMode::NewThread).into();
+        assert_eq!(names(&group), ["manual", "first", "second", "Child"]);
+        assert_eq!(group.children.groups[0].group.modules[0].name, "child");
+
+        // Leaving the body with `return` still adds the builders dropped so far.
+        let group: GroupBuilder = GroupBuilder::new(Spawning { second: false }, SpawnMode::NewThread).into();
+        assert_eq!(names(&group), ["manual", "first", "Child"]);
+    }
+
+    struct Manual;
+
+    impl Group for Manual {
+        fn init(&mut self, _builder: &mut GroupBuilder) {
+            let _dropped = idle("dropped");
+        }
+    }
+
+    #[test]
+    fn builders_outside_of_scopes_are_not_added() {
+        let mut outer = GroupBuilder::empty();
+        let scope = crate::SpawnScope::enter();
+        // `init` of a child group does not add to an enclosing scope.
+        let inner: GroupBuilder = GroupBuilder::new(Manual, SpawnMode::GroupThread).into();
+        assert!(inner.children.is_empty());
+        scope.finish(&mut outer);
+        assert!(outer.children.is_empty());
+    }
+}
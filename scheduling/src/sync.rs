@@ -0,0 +1,315 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use ports::prelude::PortEntry;
+use crate::clock::Clock;
+use crate::timer::TimerQueue;
+use crate::module::Module;
+
+/// Common time base of containers on different threads.
+/// Containers sharing a tick source start the cycles of their modules at multiples
+/// of the cycle time since the same epoch, so modules with cycle times of 10 ms and 100 ms
+/// run every tenth cycle at the same instant instead of drifting relative to each other.
+/// Set with [`crate::GroupBuilder::with_tick_source`] or [`crate::ThreadContainer::with_tick_source`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TickSource {
+    epoch: Instant,
+}
+
+impl TickSource {
+    pub fn new() -> Self {
+        Self::starting_at(Instant::now())
+    }
+
+    pub fn starting_at(epoch: Instant) -> Self {
+        Self { epoch }
+    }
+
+    pub fn epoch(&self) -> Instant {
+        self.epoch
+    }
+
+    /// The first cycle of a module with `cycle_time` starting at or after `now`.
+    pub fn next_tick(&self, cycle_time: Duration, now: Instant) -> Instant {
+        if now <= self.epoch {
+            return self.epoch;
+        }
+        if cycle_time.is_zero() {
+            return now;
+        }
+        const NANOS_PER_SEC: u128 = 1_000_000_000;
+        let cycle_nanos = cycle_time.as_nanos();
+        let nanos = (now - self.epoch).as_nanos().div_ceil(cycle_nanos) * cycle_nanos;
+        // An offset beyond the range of `Instant` starts the cycle right away.
+        u64::try_from(nanos / NANOS_PER_SEC).ok()
+            .and_then(|secs| self.epoch.checked_add(Duration::new(secs, (nanos % NANOS_PER_SEC) as u32)))
+            .unwrap_or(now)
+    }
+}
+
+impl Default for TickSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Start time of a module's next cycle, never in the past.
+/// With a tick source, skipped cycles keep the phase of the module aligned to the ticks.
+pub(crate) fn next_start(tick: Option<TickSource>, last_run: Instant, cycle_time: Duration, now: Instant) -> Instant {
+    let next = last_run + cycle_time;
+    if next >= now {
+        return next;
+    }
+    tick.map_or(now, |tick| tick.next_tick(cycle_time, now))
+}
+
+/// Synchronizes a module with a module running `ratio` times slower, e.g. a controller
+/// running every 10 ms with a planner running every 100 ms with a ratio of 10.
+///
+/// Before its cycle `n`, the faster module waits until the slower one has completed
+/// `n / ratio + 1` cycles, so its 10th cycle starts only after the planner published its
+/// second plan. If the slower module does not publish within the timeout, e.g. because it
+/// was paused, the faster module runs anyway and stops waiting until the slower module
+/// publishes again, then both are aligned anew.
+///
+/// Waiting blocks the thread of the faster module, so the two modules must run in different
+/// containers, e.g. with [`crate::SpawnMode::NewThread`]. In the same container the slower module
+/// cannot run while the faster one waits for it, and the barrier would only time out.
+/// Set up with [`crate::ModuleBuilder::publishes`] and [`crate::ModuleBuilder::waits_for`].
+#[derive(Clone)]
+pub struct RateBarrier {
+    shared: Arc<BarrierShared>,
+}
+
+struct BarrierShared {
+    ratio: u64,
+    timeout: Duration,
+    /// Completed cycles of the slower module.
+    published: Mutex<u64>,
+    condvar: Condvar,
+}
+
+impl RateBarrier {
+    /// Creates a barrier for a module running `ratio` cycles per cycle of the slower module.
+    pub fn new(ratio: u64) -> Self {
+        Self::with_timeout(ratio, Duration::from_secs(1))
+    }
+
+    /// Like [`RateBarrier::new`], waiting at most `timeout` for the slower module.
+    pub fn with_timeout(ratio: u64, timeout: Duration) -> Self {
+        Self {
+            shared: Arc::new(BarrierShared {
+                ratio: ratio.max(1),
+                timeout,
+                published: Mutex::new(0),
+                condvar: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Number of cycles the slower module has completed.
+    pub fn published(&self) -> u64 {
+        *self.shared.published.lock().unwrap()
+    }
+
+    fn publish(&self) {
+        *self.shared.published.lock().unwrap() += 1;
+        self.shared.condvar.notify_all();
+    }
+
+}
+
+/// A faster module waiting for a [`RateBarrier`].
+pub(crate) struct BarrierWait {
+    barrier: RateBarrier,
+    /// Cycles of the faster module since the modules were aligned.
+    cycles: u64,
+    /// Published cycles of the slower module when waiting for it timed out.
+    stalled: Option<u64>,
+}
+
+impl BarrierWait {
+    pub(crate) fn new(barrier: RateBarrier) -> Self {
+        Self { barrier, cycles: 0, stalled: None }
+    }
+
+    /// Waits until the slower module completed the cycles required before the next cycle,
+    /// or the timeout. After a timeout, it does not wait until the slower module publishes again.
+    fn wait(&mut self) {
+        let shared = &self.barrier.shared;
+        let mut published = shared.published.lock().unwrap();
+        if let Some(stalled) = self.stalled {
+            if *published == stalled {
+                return;
+            }
+            // Align the cycles to the latest publication of the slower module.
+            self.stalled = None;
+            self.cycles = (*published - 1) * shared.ratio;
+        }
+        let required = self.cycles / shared.ratio + 1;
+        let timeout;
+        (published, timeout) = shared.condvar
+            .wait_timeout_while(published, shared.timeout, |published| *published < required)
+            .unwrap();
+        if timeout.timed_out() {
+            self.stalled = Some(*published);
+        }
+        self.cycles += 1;
+    }
+}
+
+/// Module publishing to and waiting for [`RateBarrier`]s, created by the [`crate::ModuleBuilder`].
+pub(crate) struct SyncedModule {
+    pub(crate) inner: Box<dyn Module + Send>,
+    pub(crate) publishes: Vec<RateBarrier>,
+    pub(crate) waits_for: Vec<BarrierWait>,
+}
+
+impl Module for SyncedModule {
+    fn update(&mut self) {
+        for wait in &mut self.waits_for {
+            wait.wait();
+        }
+        self.inner.update();
+        for barrier in &self.publishes {
+            barrier.publish();
+        }
+    }
+
+    fn set_clock(&mut self, clock: &Arc<dyn Clock>) {
+        self.inner.set_clock(clock);
+    }
+
+    fn timers(&mut self) -> Option<&mut dyn TimerQueue> {
+        self.inner.timers()
+    }
+
+    fn fire_timers(&mut self) {
+        self.inner.fire_timers();
+    }
+
+    fn start(&mut self) {
+        self.inner.start();
+    }
+
+    fn stop(&mut self) {
+        self.inner.stop();
+    }
+
+    fn pause(&mut self) {
+        self.inner.pause();
+    }
+
+    fn resume(&mut self) {
+        self.inner.resume();
+    }
+
+    fn interface(&self) -> Vec<PortEntry> {
+        self.inner.interface()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use crate::{GroupBuilder, Module, ModuleBuilder, RateBarrier, SpawnMode, TickSource};
+    use super::next_start;
+
+    #[test]
+    fn ticks() {
+        let epoch = Instant::now();
+        let tick = TickSource::starting_at(epoch);
+        let ms = Duration::from_millis;
+        assert_eq!(tick.next_tick(ms(10), epoch), epoch);
+        assert_eq!(tick.next_tick(ms(10), epoch + ms(1)), epoch + ms(10));
+        assert_eq!(tick.next_tick(ms(100), epoch + ms(100)), epoch + ms(100));
+        assert_eq!(tick.next_tick(ms(100), epoch + ms(101)), epoch + ms(200));
+        // More than `u32::MAX` cycles since the epoch.
+        let late = epoch + Duration::from_secs(20) + Duration::from_nanos(2);
+        assert_eq!(tick.next_tick(Duration::from_nanos(3), late), late + Duration::from_nanos(2));
+
+        // A late cycle skips to the next tick instead of shifting the phase.
+        assert_eq!(next_start(Some(tick), epoch + ms(10), ms(10), epoch + ms(25)), epoch + ms(30));
+        assert_eq!(next_start(None, epoch + ms(10), ms(10), epoch + ms(25)), epoch + ms(25));
+        assert_eq!(next_start(Some(tick), epoch + ms(10), ms(10), epoch + ms(15)), epoch + ms(20));
+    }
+
+    /// Records the number of published cycles of the barrier before each update.
+    struct Controller {
+        barrier: RateBarrier,
+        observed: Arc<Mutex<Vec<u64>>>,
+    }
+
+    impl Module for Controller {
+        fn update(&mut self) {
+            self.observed.lock().unwrap().push(self.barrier.published());
+        }
+    }
+
+    struct Planner;
+
+    impl Module for Planner {
+        fn update(&mut self) {
+            // Publishing takes longer than a cycle of the controller.
+            std::thread::sleep(Duration::from_millis(3));
+        }
+    }
+
+    #[test]
+    fn rate_barrier() {
+        let barrier = RateBarrier::new(5);
+        let observed = Arc::new(Mutex::new(Vec::new()));
+        let mut group = GroupBuilder::empty().with_tick_source(TickSource::new());
+        group.add_module(
+            ModuleBuilder::new(Planner, Duration::from_millis(10), SpawnMode::NewThread).publishes(&barrier),
+        );
+        group.add_module(
+            ModuleBuilder::new(
+                Controller { barrier: barrier.clone(), observed: Arc::clone(&observed) },
+                Duration::from_millis(2),
+                SpawnMode::NewThread,
+            ).waits_for(&barrier),
+        );
+        let runtime = group.spawn();
+        let start = Instant::now();
+        while observed.lock().unwrap().len() < 25 {
+            assert!(start.elapsed() < Duration::from_secs(2));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        runtime.shutdown();
+
+        for (cycle, published) in observed.lock().unwrap().iter().enumerate() {
+            assert!(*published > cycle as u64 / 5, "cycle {cycle} started after {published} published cycles");
+        }
+    }
+
+    #[test]
+    fn paused_slower_module() {
+        let barrier = RateBarrier::with_timeout(5, Duration::from_millis(50));
+        let observed = Arc::new(Mutex::new(Vec::new()));
+        let mut group = GroupBuilder::empty();
+        group.add_module(
+            ModuleBuilder::new(Planner, Duration::from_millis(10), SpawnMode::NewThread).publishes(&barrier),
+        );
+        group.add_module(
+            ModuleBuilder::new(
+                Controller { barrier: barrier.clone(), observed: Arc::clone(&observed) },
+                Duration::from_millis(2),
+                SpawnMode::NewThread,
+            ).waits_for(&barrier),
+        );
+        let mut runtime = group.spawn();
+        assert!(runtime.pause("Planner"));
+        std::thread::sleep(Duration::from_millis(100));
+        let published = barrier.published();
+        let cycles = observed.lock().unwrap().len();
+
+        // After one timeout the controller runs at its own rate instead of waiting every cycle.
+        std::thread::sleep(Duration::from_millis(200));
+        let paused_cycles = observed.lock().unwrap().len() - cycles;
+        assert_eq!(barrier.published(), published);
+        assert!(paused_cycles > 20, "only {paused_cycles} cycles while the planner was paused");
+
+        runtime.shutdown();
+    }
+}
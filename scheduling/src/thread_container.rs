@@ -1,27 +1,118 @@
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
 use std::thread::sleep;
 use std::time::{Duration, Instant};
 use crate::module::Module;
+use std::sync::Arc;
+use crate::runtime::{ContainerId, ModuleId};
+use crate::thread_pool::PoolShared;
+use crate::clock::{Clock, SystemClock};
+use crate::sync::{self, TickSource};
+#[cfg(feature = "tokio")]
+use crate::async_module::AsyncShared;
 
 /// A Task, representing a scheduling and its next scheduled start time
 /// The ordering is reversed to make BinaryHeap a min-heap based on next_run time
 struct Task {
     scheduled_start: Instant,
-    module_index: usize,
+    module_id: ModuleId,
 }
 
 /// Generic dyn Module and its associated cycle time
 struct ModuleData {
     module: Box<dyn Module + Send>,
     cycle_time: Duration,
+    paused: bool,
+    /// Scheduled start of the last cycle.
+    last_start: Option<Instant>,
+    /// Scheduled start of the next cycle.
+    next_cycle: Instant,
+}
+
+impl ModuleData {
+    fn new(module: Box<dyn Module + Send>, cycle_time: Duration, next_cycle: Instant) -> Self {
+        Self { module, cycle_time, paused: false, last_start: None, next_cycle }
+    }
+
+    /// When the module has to run next: its next cycle or an earlier deadline of its timers.
+    fn wake_time(&mut self) -> Instant {
+        let deadline = match self.paused {
+            true => None,
+            false => self.module.timers().and_then(|timers| timers.next_deadline()),
+        };
+        deadline.map_or(self.next_cycle, |deadline| deadline.min(self.next_cycle))
+    }
+}
+
+/// Commands sent to a running container through a [`ContainerHandle`].
+pub(crate) enum ContainerCommand {
+    AddModule {
+        id: ModuleId,
+        module: Box<dyn Module + Send>,
+        cycle_time: Duration,
+    },
+    RemoveModule {
+        id: ModuleId,
+        done: Sender<bool>,
+    },
+    SetPaused {
+        id: ModuleId,
+        paused: bool,
+    },
+    SetCycleTime {
+        id: ModuleId,
+        cycle_time: Duration,
+    },
+    /// The clock of the container was advanced, see [`Clock::on_advance`].
+    Wake,
+    Shutdown,
 }
 
 /// A container that manages and runs multiple modules in a separate thread
 /// Each scheduling is scheduled to run based on its specified cycle time
 /// Modules never run more frequently than their cycle time, but may run less frequently
 pub struct ThreadContainer {
-    modules: Vec<ModuleData>,
-    task_queue: BinaryHeap<Task>,
+    id: ContainerId,
+    modules: HashMap<ModuleId, ModuleData>,
+    commands: Receiver<ContainerCommand>,
+    sender: Sender<ContainerCommand>,
+    clock: Arc<dyn Clock>,
+    tick: Option<TickSource>,
+}
+
+/// Handle to a [`ThreadContainer`] used to add and remove modules while it is running.
+/// The container keeps running its modules when all handles are dropped.
+#[derive(Clone)]
+pub struct ContainerHandle {
+    id: ContainerId,
+    sender: CommandSender,
+}
+
+/// Delivers commands to a [`ThreadContainer`], a [`crate::ThreadPool`] or an async executor.
+#[derive(Clone)]
+enum CommandSender {
+    Thread(Sender<ContainerCommand>),
+    Pool(Arc<PoolShared>),
+    #[cfg(feature = "tokio")]
+    Executor(Arc<AsyncShared>),
+}
+
+impl CommandSender {
+    /// Returns false if the container is no longer running.
+    fn send(&self, command: ContainerCommand) -> bool {
+        match self {
+            CommandSender::Thread(sender) => sender.send(command).is_ok(),
+            CommandSender::Pool(pool) => {
+                pool.send(command);
+                true
+            }
+            #[cfg(feature = "tokio")]
+            CommandSender::Executor(executor) => {
+                executor.send(command);
+                true
+            }
+        }
+    }
 }
 
 impl ThreadContainer {
@@ -29,65 +120,308 @@ impl ThreadContainer {
     /// The container can be used to add modules and then run them in a separate thread
     /// with each scheduling being called based on its cycle time.
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// Creates a new, empty ThreadContainer scheduling its modules with `clock`.
+    /// With a [`crate::ManualClock`], modules only become due when the clock is advanced.
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        let (sender, commands) = channel();
         Self {
-            modules: Vec::new(),
-            task_queue: BinaryHeap::new(),
+            id: ContainerId::next(),
+            modules: HashMap::new(),
+            commands,
+            sender,
+            clock,
+            tick: None,
+        }
+    }
+
+    /// Aligns the cycles of the modules to `tick`, see [`TickSource`].
+    pub fn with_tick_source(mut self, tick: TickSource) -> Self {
+        self.tick = Some(tick);
+        self
+    }
+
+    /// Returns the id of this container.
+    pub fn id(&self) -> ContainerId {
+        self.id
+    }
+
+    /// Returns a handle that can be used to modify the container after `run` was called.
+    pub fn handle(&self) -> ContainerHandle {
+        ContainerHandle {
+            id: self.id,
+            sender: CommandSender::Thread(self.sender.clone()),
         }
     }
 
     /// Adds a scheduling to the container with the specified cycle time
     /// The scheduling will be called every `cycle_time` duration in the working thread
-    pub fn add_module<M: Module + Send + 'static>(&mut self, module: M, cycle_time: Duration) {
+    pub fn add_module<M: Module + Send + 'static>(&mut self, module: M, cycle_time: Duration) -> ModuleId {
         self.add_dyn_module(Box::new(module), cycle_time)
     }
 
     /// Adds a boxed scheduling to the container with the specified cycle time
     /// The scheduling will be called every `cycle_time` duration in the working thread
-    pub fn add_dyn_module(&mut self, module: Box<dyn Module + Send>, cycle_time: Duration){
-        self.modules.push(ModuleData { module, cycle_time });
-        self.task_queue.push(
-            Task {
-                scheduled_start: Instant::now(),
-                module_index: self.modules.len() - 1,
-            }
-        )
+    pub fn add_dyn_module(&mut self, module: Box<dyn Module + Send>, cycle_time: Duration) -> ModuleId {
+        let id = ModuleId::next();
+        self.insert_module(id, module, cycle_time);
+        id
     }
 
     /// Starts the working thread that schedules modules based on their cycle times
     /// calling their `update` method when it's time
-    pub fn run(mut self) {
+    pub fn run(self) -> ContainerHandle {
         println!("Running threads");
+        let handle = self.handle();
+        let Self { mut modules, commands, sender, clock, tick, .. } = self;
+        // Only handles and a clock moved by hand may keep the command channel open.
+        clock.on_advance(Box::new(move || sender.send(ContainerCommand::Wake).is_ok()));
         std::thread::spawn(move || {
-            while let Some(mut task) = self.task_queue.pop() {
-                self.wait_for_module(task.scheduled_start);
+            let mut task_queue = BinaryHeap::new();
+            for (&module_id, data) in &mut modules {
+                data.module.set_clock(&clock);
+                data.module.start();
+                task_queue.push(Task { scheduled_start: data.wake_time(), module_id });
+            }
+            let mut container = RunningContainer { modules, task_queue, commands: Some(commands), clock, tick };
+            container.run();
+            println!("Threads stopped");
+        });
+        handle
+    }
+
+    fn insert_module(&mut self, id: ModuleId, module: Box<dyn Module + Send>, cycle_time: Duration) {
+        let next_cycle = first_start(self.tick, cycle_time, self.clock.now());
+        self.modules.insert(id, ModuleData::new(module, cycle_time, next_cycle));
+    }
+}
 
-                let ModuleData { module, cycle_time } = &mut self.modules[task.module_index];
-                module.update();
+impl Default for ThreadContainer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-                task.scheduled_start = Self::next_start(task.scheduled_start, *cycle_time);
+/// State of a container inside its working thread.
+/// `commands` is `None` once every [`ContainerHandle`] was dropped.
+struct RunningContainer {
+    modules: HashMap<ModuleId, ModuleData>,
+    task_queue: BinaryHeap<Task>,
+    commands: Option<Receiver<ContainerCommand>>,
+    clock: Arc<dyn Clock>,
+    tick: Option<TickSource>,
+}
+
+impl RunningContainer {
+    fn run(&mut self) {
+        loop {
+            let Some(next_start) = self.task_queue.peek().map(|task| task.scheduled_start) else {
+                // Nothing to schedule, wait until a module is added.
+                match self.commands.as_ref().map(|commands| commands.recv()) {
+                    Some(Ok(command)) => {
+                        if !self.handle_command(command) {
+                            break;
+                        }
+                        continue;
+                    }
+                    _ => break,
+                }
+            };
+
+            if !self.wait_for_module(next_start) {
+                break;
+            }
+            if self.task_queue.peek().is_none_or(|task| task.scheduled_start > self.clock.now()) {
+                // A command changed the queue while waiting.
+                continue;
+            }
+
+            let mut task = self.task_queue.pop().unwrap();
+            // Tasks of removed modules are dropped here.
+            if let Some(data) = self.modules.get_mut(&task.module_id) {
+                if data.next_cycle <= self.clock.now() {
+                    if !data.paused {
+                        data.module.fire_timers();
+                        data.module.update();
+                    }
+                    data.last_start = Some(data.next_cycle);
+                    data.next_cycle = sync::next_start(self.tick, data.next_cycle, data.cycle_time, self.clock.now());
+                } else if !data.paused {
+                    // Woken for a timer between two cycles.
+                    data.module.fire_timers();
+                }
+                task.scheduled_start = data.wake_time();
                 self.task_queue.push(task);
             }
-            println!("Threads stopped");
-        });
+        }
+        self.stop_all();
+    }
+
+    /// Sleeps until the next scheduling is scheduled to run, handling incoming commands.
+    /// Returns false if the container was shut down.
+    fn wait_for_module(&mut self, scheduled_start: Instant) -> bool {
+        loop {
+            let now = self.clock.now();
+            let timeout = scheduled_start.saturating_duration_since(now);
+            let Some(commands) = &self.commands else {
+                sleep(timeout);
+                return true;
+            };
+            match commands.recv_timeout(timeout) {
+                Ok(command) => {
+                    if !self.handle_command(command) {
+                        return false;
+                    }
+                    if self.task_queue.peek().is_none_or(|task| task.scheduled_start < scheduled_start) {
+                        return true;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => return true,
+                Err(RecvTimeoutError::Disconnected) => self.commands = None,
+            }
+        }
+    }
+
+    /// Applies a command to the container. Returns false on shutdown.
+    fn handle_command(&mut self, command: ContainerCommand) -> bool {
+        match command {
+            ContainerCommand::AddModule { id, mut module, cycle_time } => {
+                module.set_clock(&self.clock);
+                module.start();
+                let mut data = ModuleData::new(module, cycle_time, first_start(self.tick, cycle_time, self.clock.now()));
+                self.task_queue.push(Task { scheduled_start: data.wake_time(), module_id: id });
+                self.modules.insert(id, data);
+            }
+            ContainerCommand::RemoveModule { id, done } => {
+                let removed = self.modules.remove(&id);
+                let found = removed.is_some();
+                if let Some(mut data) = removed {
+                    data.module.stop();
+                }
+                let _ = done.send(found);
+            }
+            ContainerCommand::SetPaused { id, paused } => {
+                if let Some(data) = self.modules.get_mut(&id)
+                    && data.paused != paused
+                {
+                    data.paused = paused;
+                    if paused {
+                        data.module.pause();
+                    } else {
+                        data.module.resume();
+                        // Timers that became due while paused fire now.
+                        let wake_time = data.wake_time();
+                        self.reschedule(id, wake_time);
+                    }
+                }
+            }
+            ContainerCommand::SetCycleTime { id, cycle_time } => {
+                if let Some(data) = self.modules.get_mut(&id) {
+                    data.cycle_time = cycle_time;
+                    // The pending cycle was scheduled with the old cycle time.
+                    if let Some(last_start) = data.last_start {
+                        data.next_cycle = data.next_cycle.min(last_start + cycle_time);
+                        let wake_time = data.wake_time();
+                        self.reschedule(id, wake_time);
+                    }
+                }
+            }
+            ContainerCommand::Wake => {}
+            ContainerCommand::Shutdown => return false,
+        }
+        true
     }
 
-    /// Sleeps until the next scheduling is scheduled to run
-    fn wait_for_module(&self, scheduled_start: Instant) {
-        let now = Instant::now();
-        if scheduled_start > now {
-            let sleep_duration = scheduled_start - now;
-            sleep(sleep_duration);
+    /// Moves the task of a module forward to `earliest` if it is scheduled later.
+    fn reschedule(&mut self, id: ModuleId, earliest: Instant) {
+        let mut tasks = std::mem::take(&mut self.task_queue).into_vec();
+        for task in tasks.iter_mut().filter(|task| task.module_id == id) {
+            task.scheduled_start = task.scheduled_start.min(earliest);
         }
+        self.task_queue = BinaryHeap::from(tasks);
     }
 
-    /// Calculates the next start time for a scheduling, ensuring it is not in the past
-    fn next_start(last_run: Instant, cycle_time: Duration) -> Instant {
-        let next = last_run + cycle_time;
-        let now = Instant::now();
-        if next < now {
-            return now;
+    fn stop_all(&mut self) {
+        for (_, mut data) in self.modules.drain() {
+            data.module.stop();
         }
-        next
+        self.task_queue.clear();
+    }
+}
+
+/// Start time of the first cycle of a module added at `now`.
+fn first_start(tick: Option<TickSource>, cycle_time: Duration, now: Instant) -> Instant {
+    tick.map_or(now, |tick| tick.next_tick(cycle_time, now))
+}
+
+impl ContainerHandle {
+    pub(crate) fn pool(id: ContainerId, pool: Arc<PoolShared>) -> Self {
+        Self { id, sender: CommandSender::Pool(pool) }
+    }
+
+    #[cfg(feature = "tokio")]
+    pub(crate) fn executor(id: ContainerId, executor: Arc<AsyncShared>) -> Self {
+        Self { id, sender: CommandSender::Executor(executor) }
+    }
+
+    /// Returns the id of the container.
+    pub fn id(&self) -> ContainerId {
+        self.id
+    }
+
+    /// Adds a scheduling to the running container with the specified cycle time.
+    pub fn add_module<M: Module + Send + 'static>(&self, module: M, cycle_time: Duration) -> ModuleId {
+        self.add_dyn_module(Box::new(module), cycle_time)
+    }
+
+    /// Adds a boxed scheduling to the running container with the specified cycle time.
+    /// The `start` hook of the module is called in the working thread.
+    pub fn add_dyn_module(&self, module: Box<dyn Module + Send>, cycle_time: Duration) -> ModuleId {
+        let id = ModuleId::next();
+        self.add_module_with_id(id, module, cycle_time);
+        id
+    }
+
+    pub(crate) fn add_module_with_id(&self, id: ModuleId, module: Box<dyn Module + Send>, cycle_time: Duration) {
+        self.sender.send(ContainerCommand::AddModule { id, module, cycle_time });
+    }
+
+    /// Removes a scheduling from the running container and calls its `stop` hook.
+    /// Blocks until the module was removed. Returns false if the module was not found
+    /// or the container is no longer running.
+    pub fn remove_module(&self, id: ModuleId) -> bool {
+        let (done, result) = channel();
+        if !self.sender.send(ContainerCommand::RemoveModule { id, done }) {
+            return false;
+        }
+        result.recv().unwrap_or(false)
+    }
+
+    /// Pauses a scheduling. Its `pause` hook is called and `update` is skipped until it is resumed.
+    pub fn pause_module(&self, id: ModuleId) {
+        self.set_paused(id, true);
+    }
+
+    /// Resumes a paused scheduling. Its `resume` hook is called before the next `update`.
+    pub fn resume_module(&self, id: ModuleId) {
+        self.set_paused(id, false);
+    }
+
+    pub(crate) fn set_paused(&self, id: ModuleId, paused: bool) {
+        self.sender.send(ContainerCommand::SetPaused { id, paused });
+    }
+
+    /// Changes the cycle time of a scheduling. A shorter cycle time moves its next cycle forward,
+    /// a longer one applies after the next cycle.
+    pub fn set_cycle_time(&self, id: ModuleId, cycle_time: Duration) {
+        self.sender.send(ContainerCommand::SetCycleTime { id, cycle_time });
+    }
+
+    /// Stops the container after calling the `stop` hook of all its modules.
+    pub fn shutdown(&self) {
+        self.sender.send(ContainerCommand::Shutdown);
     }
 }
 
@@ -98,7 +432,7 @@ impl Ord for Task {
 }
 impl PartialOrd for Task {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(other.scheduled_start.cmp(&self.scheduled_start))
+        Some(self.cmp(other))
     }
 }
 impl Eq for Task {}
@@ -110,7 +444,10 @@ impl PartialEq for Task {
 
 #[cfg(test)]
 mod tests {
-    use crate::Module;
+    use std::sync::Arc;
+    use std::sync::mpsc::{channel, Sender};
+    use std::time::Duration;
+    use crate::{ManualClock, Module, TimerQueue, Timers};
 
     struct TestModule {
         count: usize,
@@ -179,4 +516,72 @@ mod tests {
         assert!(had_to_wait);
 
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn add_and_remove_while_running() {
+        let (result_tx_1, result_rx_1) = std::sync::mpsc::channel();
+        let (result_tx_2, result_rx_2) = std::sync::mpsc::channel();
+        let module_1 = TestModule { count: 0, sleep_time: std::time::Duration::ZERO, channel: result_tx_1 };
+        let module_2 = TestModule { count: 0, sleep_time: std::time::Duration::ZERO, channel: result_tx_2 };
+
+        let container = super::ThreadContainer::new();
+        let handle = container.run();
+        let id_1 = handle.add_module(module_1, std::time::Duration::from_millis(5));
+        handle.add_module(module_2, std::time::Duration::from_millis(5));
+
+        assert_eq!(result_rx_1.recv().unwrap(), 1);
+        assert_eq!(result_rx_2.recv().unwrap(), 1);
+
+        assert!(handle.remove_module(id_1));
+        assert!(!handle.remove_module(id_1));
+        while result_rx_1.try_recv().is_ok() {}
+        assert!(result_rx_1.recv().is_err());
+        assert_eq!(result_rx_2.recv().unwrap(), 2);
+
+        handle.shutdown();
+        while result_rx_2.recv().is_ok() {}
+    }
+
+    struct TimedModule {
+        timers: Timers<TimedModule>,
+        events: Sender<&'static str>,
+    }
+    impl Module for TimedModule {
+        fn update(&mut self) {
+            self.events.send("update").unwrap();
+        }
+
+        fn timers(&mut self) -> Option<&mut dyn TimerQueue> {
+            Some(&mut self.timers)
+        }
+
+        fn fire_timers(&mut self) {
+            Timers::fire(self, |module| &mut module.timers);
+        }
+    }
+
+    #[test]
+    fn manual_clock() {
+        let (events, received) = channel();
+        let mut module = TimedModule { timers: Timers::new(), events };
+        module.timers.once(Duration::from_secs(5), |module| module.events.send("timer").unwrap());
+
+        let clock = ManualClock::new();
+        let mut container = super::ThreadContainer::with_clock(Arc::new(clock.clone()));
+        container.add_module(module, Duration::from_secs(10));
+        let handle = container.run();
+        assert_eq!(received.recv_timeout(Duration::from_secs(1)), Ok("update"));
+        assert!(received.recv_timeout(Duration::from_millis(50)).is_err());
+
+        // Advancing the clock wakes the container, which fires the timer between two cycles.
+        clock.advance(Duration::from_secs(4));
+        assert!(received.recv_timeout(Duration::from_millis(50)).is_err());
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(received.recv_timeout(Duration::from_secs(1)), Ok("timer"));
+        assert!(received.recv_timeout(Duration::from_millis(50)).is_err());
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(received.recv_timeout(Duration::from_secs(1)), Ok("update"));
+        assert!(received.recv_timeout(Duration::from_millis(50)).is_err());
+        handle.shutdown();
+    }
+}
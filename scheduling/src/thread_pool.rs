@@ -0,0 +1,475 @@
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+use crate::clock::{Clock, SystemClock};
+use crate::module::Module;
+use crate::runtime::{ContainerId, ModuleId};
+use crate::thread_container::{ContainerCommand, ContainerHandle};
+use crate::sync::{self, TickSource};
+
+/// A module waiting in the pool's queue, ordered by the time it has to run next:
+/// its next cycle or an earlier deadline of its timers.
+/// The task owns its module, so a module never runs on two workers at the same time.
+struct PoolTask {
+    scheduled_start: Instant,
+    id: ModuleId,
+    module: Box<dyn Module + Send>,
+    cycle_time: Duration,
+    /// Scheduled start of the last cycle.
+    last_start: Option<Instant>,
+    /// Scheduled start of the next cycle.
+    next_cycle: Instant,
+    started: bool,
+    paused: bool,
+}
+
+impl PoolTask {
+    fn wake_time(&mut self) -> Instant {
+        let deadline = match self.paused {
+            true => None,
+            false => self.module.timers().and_then(|timers| timers.next_deadline()),
+        };
+        deadline.map_or(self.next_cycle, |deadline| deadline.min(self.next_cycle))
+    }
+}
+
+struct PoolState {
+    queue: BinaryHeap<PoolTask>,
+    /// All modules of the pool, queued or currently running.
+    modules: HashSet<ModuleId>,
+    /// Commands for modules, applied by the worker that runs the module next.
+    pending: HashMap<ModuleId, Vec<ContainerCommand>>,
+    shutdown: bool,
+}
+
+pub(crate) struct PoolShared {
+    state: Mutex<PoolState>,
+    wakeup: Condvar,
+    tick: Option<TickSource>,
+    clock: Arc<dyn Clock>,
+}
+
+/// A fixed number of worker threads running modules by deadline.
+/// Like a [`crate::ThreadContainer`], modules never run more frequently than their cycle time.
+/// Unlike it, modules with overlapping deadlines run in parallel on different workers.
+pub struct ThreadPool {
+    id: ContainerId,
+    shared: Arc<PoolShared>,
+}
+
+impl ThreadPool {
+    /// Starts a pool with `threads` workers (at least one).
+    pub fn new(threads: usize) -> Self {
+        Self::start(threads, None, Arc::new(SystemClock))
+    }
+
+    /// Like [`ThreadPool::new`], aligning the cycles of the modules to `tick`, see [`TickSource`].
+    pub fn with_tick_source(threads: usize, tick: TickSource) -> Self {
+        Self::start(threads, Some(tick), Arc::new(SystemClock))
+    }
+
+    /// Like [`ThreadPool::new`], scheduling modules with `clock` instead of the system clock,
+    /// see [`crate::ThreadContainer::with_clock`].
+    pub fn with_clock(threads: usize, clock: Arc<dyn Clock>) -> Self {
+        Self::start(threads, None, clock)
+    }
+
+    fn start(threads: usize, tick: Option<TickSource>, clock: Arc<dyn Clock>) -> Self {
+        let shared = Arc::new(PoolShared {
+            state: Mutex::new(PoolState {
+                queue: BinaryHeap::new(),
+                modules: HashSet::new(),
+                pending: HashMap::new(),
+                shutdown: false,
+            }),
+            wakeup: Condvar::new(),
+            tick,
+            clock: Arc::clone(&clock),
+        });
+        let weak = Arc::downgrade(&shared);
+        clock.on_advance(Box::new(move || weak.upgrade().is_some_and(|shared| shared.wake())));
+        for _ in 0..threads.max(1) {
+            let shared = Arc::clone(&shared);
+            std::thread::spawn(move || shared.work());
+        }
+        Self { id: ContainerId::next(), shared }
+    }
+
+    /// Returns a handle to add, remove and pause modules of the pool.
+    /// Dropping all handles does not stop the pool.
+    pub fn handle(&self) -> ContainerHandle {
+        ContainerHandle::pool(self.id, Arc::clone(&self.shared))
+    }
+}
+
+impl PoolShared {
+    /// Hands a command to the pool. Commands for queued modules are applied by the worker
+    /// that picks the module up next, which happens immediately but does not update the module
+    /// before its next cycle.
+    pub(crate) fn send(&self, command: ContainerCommand) {
+        let mut state = self.state.lock().unwrap();
+        match command {
+            ContainerCommand::AddModule { id, module, cycle_time } => {
+                state.modules.insert(id);
+                let now = self.clock.now();
+                let next_cycle = self.tick.map_or(now, |tick| tick.next_tick(cycle_time, now));
+                // Timers are only known once the module started with the clock of the pool.
+                state.queue.push(PoolTask {
+                    scheduled_start: next_cycle,
+                    id,
+                    module,
+                    cycle_time,
+                    last_start: None,
+                    next_cycle,
+                    started: false,
+                    paused: false,
+                });
+            }
+            ContainerCommand::RemoveModule { id, done } if !state.modules.contains(&id) => {
+                let _ = done.send(false);
+                return;
+            }
+            ContainerCommand::RemoveModule { id, .. } | ContainerCommand::SetPaused { id, .. } => {
+                state.pending.entry(id).or_default().push(command);
+                self.make_due(&mut state, id);
+            }
+            ContainerCommand::SetCycleTime { id, cycle_time } => {
+                // A queued module was scheduled with the old cycle time, a running one
+                // applies the new cycle time before its next cycle is scheduled.
+                let queued = Self::reschedule(&mut state, id, |task| {
+                    task.cycle_time = cycle_time;
+                    if let Some(last_start) = task.last_start {
+                        task.next_cycle = task.next_cycle.min(last_start + cycle_time);
+                        task.scheduled_start = task.scheduled_start.min(task.next_cycle);
+                    }
+                });
+                if !queued {
+                    state.pending.entry(id).or_default().push(command);
+                }
+            }
+            ContainerCommand::Wake => {
+                self.wakeup.notify_all();
+                return;
+            }
+            ContainerCommand::Shutdown => {
+                state.shutdown = true;
+                self.wakeup.notify_all();
+                return;
+            }
+        }
+        self.wakeup.notify_one();
+    }
+
+    fn make_due(&self, state: &mut PoolState, id: ModuleId) {
+        let now = self.clock.now();
+        Self::reschedule(state, id, |task| task.scheduled_start = task.scheduled_start.min(now));
+    }
+
+    /// Wakes the workers after the clock was advanced by hand. Returns false once the pool shut down.
+    fn wake(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        self.wakeup.notify_all();
+        !state.shutdown
+    }
+
+    /// Applies `change` to the queued task of a module. Returns false if the module is not queued.
+    fn reschedule(state: &mut PoolState, id: ModuleId, change: impl FnOnce(&mut PoolTask)) -> bool {
+        let mut tasks = std::mem::take(&mut state.queue).into_vec();
+        let task = tasks.iter_mut().find(|task| task.id == id);
+        let queued = task.is_some();
+        if let Some(task) = task {
+            change(task);
+        }
+        state.queue = BinaryHeap::from(tasks);
+        queued
+    }
+
+    /// Worker loop: runs the module with the earliest wake time once it is due.
+    fn work(&self) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.shutdown {
+                Self::stop_queued(&mut state);
+                return;
+            }
+            let Some(next_start) = state.queue.peek().map(|task| task.scheduled_start) else {
+                state = self.wakeup.wait(state).unwrap();
+                continue;
+            };
+            let now = self.clock.now();
+            if next_start > now {
+                state = self.wakeup.wait_timeout(state, next_start - now).unwrap().0;
+                continue;
+            }
+
+            let mut task = state.queue.pop().unwrap();
+            // Another worker may pick the next task while this one runs.
+            self.wakeup.notify_one();
+            drop(state);
+            let mut removed = self.run_task(&mut task);
+            state = self.state.lock().unwrap();
+            // Apply commands that arrived while the module was running.
+            while removed.is_none() && let Some(commands) = state.pending.remove(&task.id) {
+                drop(state);
+                removed = Self::apply_commands(&mut task, commands);
+                state = self.state.lock().unwrap();
+            }
+            if let Some(done) = removed {
+                state.modules.remove(&task.id);
+                for command in state.pending.remove(&task.id).into_iter().flatten() {
+                    if let ContainerCommand::RemoveModule { done, .. } = command {
+                        let _ = done.send(false);
+                    }
+                }
+                let _ = done.send(true);
+            } else if state.shutdown {
+                task.module.stop();
+                state.modules.remove(&task.id);
+            } else {
+                task.scheduled_start = task.wake_time();
+                state.queue.push(task);
+                self.wakeup.notify_one();
+            }
+        }
+    }
+
+    /// Runs a task without holding the lock.
+    /// Returns the sender to acknowledge a removal if the module was removed.
+    fn run_task(&self, task: &mut PoolTask) -> Option<Sender<bool>> {
+        if !task.started {
+            task.module.set_clock(&self.clock);
+            task.module.start();
+            task.started = true;
+        }
+        let commands = self.state.lock().unwrap().pending.remove(&task.id);
+        if let Some(commands) = commands {
+            let removed = Self::apply_commands(task, commands);
+            if removed.is_some() {
+                return removed;
+            }
+        }
+        if task.next_cycle <= self.clock.now() {
+            if !task.paused {
+                task.module.fire_timers();
+                task.module.update();
+            }
+            task.last_start = Some(task.next_cycle);
+            task.next_cycle = sync::next_start(self.tick, task.next_cycle, task.cycle_time, self.clock.now());
+        } else if !task.paused {
+            // Woken for a timer between two cycles.
+            task.module.fire_timers();
+        }
+        None
+    }
+
+    /// Applies commands to a task.
+    /// Returns the sender to acknowledge a removal if the module was removed.
+    fn apply_commands(task: &mut PoolTask, commands: Vec<ContainerCommand>) -> Option<Sender<bool>> {
+        for command in commands {
+            match command {
+                ContainerCommand::SetPaused { paused, .. } if paused != task.paused => {
+                    task.paused = paused;
+                    if paused {
+                        task.module.pause();
+                    } else {
+                        task.module.resume();
+                    }
+                }
+                ContainerCommand::SetCycleTime { cycle_time, .. } => {
+                    task.cycle_time = cycle_time;
+                }
+                ContainerCommand::RemoveModule { done, .. } => {
+                    task.module.stop();
+                    return Some(done);
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Stops the queued modules and answers their pending removals.
+    fn stop_queued(state: &mut PoolState) {
+        for mut task in std::mem::take(&mut state.queue) {
+            if task.started {
+                task.module.stop();
+            }
+            state.modules.remove(&task.id);
+            // The first removal succeeds with the shutdown, like for a running module.
+            let mut removed = false;
+            for command in state.pending.remove(&task.id).into_iter().flatten() {
+                if let ContainerCommand::RemoveModule { done, .. } = command {
+                    let _ = done.send(!removed);
+                    removed = true;
+                }
+            }
+        }
+    }
+}
+
+impl Ord for PoolTask {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.scheduled_start.cmp(&self.scheduled_start)
+    }
+}
+impl PartialOrd for PoolTask {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Eq for PoolTask {}
+impl PartialEq for PoolTask {
+    fn eq(&self, other: &Self) -> bool {
+        self.scheduled_start == other.scheduled_start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc::{channel, Sender};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use crate::{ManualClock, Module, TimerQueue, Timers};
+    use super::ThreadPool;
+
+    struct SlowModule {
+        running: Arc<AtomicUsize>,
+        max_running: Arc<AtomicUsize>,
+        finished: Sender<Instant>,
+    }
+
+    impl Module for SlowModule {
+        fn update(&mut self) {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_running.fetch_max(running, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(20));
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            let _ = self.finished.send(Instant::now());
+        }
+    }
+
+    #[test]
+    fn modules_run_in_parallel() {
+        let pool = ThreadPool::new(4);
+        let handle = pool.handle();
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let (finished_tx, finished_rx) = channel();
+        for _ in 0..4 {
+            handle.add_module(SlowModule {
+                running: Arc::clone(&running),
+                max_running: Arc::clone(&max_running),
+                finished: finished_tx.clone(),
+            }, Duration::from_millis(100));
+        }
+        for _ in 0..4 {
+            finished_rx.recv().unwrap();
+        }
+        assert!(max_running.load(Ordering::SeqCst) > 1);
+        handle.shutdown();
+    }
+
+    #[test]
+    fn module_never_runs_concurrently_with_itself() {
+        let pool = ThreadPool::new(4);
+        let handle = pool.handle();
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let (finished_tx, finished_rx) = channel();
+        let id = handle.add_module(SlowModule {
+            running: Arc::clone(&running),
+            max_running: Arc::clone(&max_running),
+            finished: finished_tx,
+        }, Duration::ZERO);
+        for _ in 0..5 {
+            finished_rx.recv().unwrap();
+        }
+        assert_eq!(max_running.load(Ordering::SeqCst), 1);
+
+        handle.pause_module(id);
+        assert!(handle.remove_module(id));
+        assert!(!handle.remove_module(id));
+        while finished_rx.try_recv().is_ok() {}
+        assert!(finished_rx.recv().is_err());
+        handle.shutdown();
+    }
+
+    #[test]
+    fn shorter_cycle_time_applies_immediately() {
+        let pool = ThreadPool::new(2);
+        let handle = pool.handle();
+        let (finished_tx, finished_rx) = channel();
+        let id = handle.add_module(SlowModule {
+            running: Arc::new(AtomicUsize::new(0)),
+            max_running: Arc::new(AtomicUsize::new(0)),
+            finished: finished_tx,
+        }, Duration::from_secs(3600));
+        finished_rx.recv().unwrap();
+
+        handle.set_cycle_time(id, Duration::from_millis(10));
+        assert!(finished_rx.recv_timeout(Duration::from_secs(1)).is_ok());
+        handle.shutdown();
+    }
+
+    struct TimedModule {
+        timers: Timers<TimedModule>,
+        events: Sender<&'static str>,
+    }
+
+    impl Module for TimedModule {
+        fn update(&mut self) {
+            self.events.send("update").unwrap();
+        }
+
+        fn timers(&mut self) -> Option<&mut dyn TimerQueue> {
+            Some(&mut self.timers)
+        }
+
+        fn fire_timers(&mut self) {
+            Timers::fire(self, |module| &mut module.timers);
+        }
+    }
+
+    #[test]
+    fn timers_fire_between_cycles() {
+        let (events, received) = channel();
+        let mut module = TimedModule { timers: Timers::new(), events };
+        module.timers.every(Duration::from_secs(4), |module| module.events.send("timer").unwrap());
+
+        let clock = ManualClock::new();
+        let pool = ThreadPool::with_clock(2, Arc::new(clock.clone()));
+        let handle = pool.handle();
+        handle.add_module(module, Duration::from_secs(10));
+        assert_eq!(received.recv_timeout(Duration::from_secs(1)), Ok("update"));
+        assert!(received.recv_timeout(Duration::from_millis(50)).is_err());
+
+        for _ in 0..2 {
+            clock.advance(Duration::from_secs(4));
+            assert_eq!(received.recv_timeout(Duration::from_secs(1)), Ok("timer"));
+            assert!(received.recv_timeout(Duration::from_millis(50)).is_err());
+        }
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(received.recv_timeout(Duration::from_secs(1)), Ok("update"));
+        assert!(received.recv_timeout(Duration::from_millis(50)).is_err());
+        handle.shutdown();
+    }
+
+    #[test]
+    fn commands_do_not_update_queued_modules() {
+        let (events, received) = channel();
+        let clock = ManualClock::new();
+        let pool = ThreadPool::with_clock(2, Arc::new(clock.clone()));
+        let handle = pool.handle();
+        let id = handle.add_module(TimedModule { timers: Timers::new(), events }, Duration::from_secs(10));
+        assert_eq!(received.recv_timeout(Duration::from_secs(1)), Ok("update"));
+
+        handle.pause_module(id);
+        handle.resume_module(id);
+        assert!(received.recv_timeout(Duration::from_millis(50)).is_err());
+        assert!(handle.remove_module(id));
+        assert!(received.recv_timeout(Duration::from_millis(50)).is_err());
+        handle.shutdown();
+    }
+}
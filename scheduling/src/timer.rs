@@ -0,0 +1,227 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::clock::{Clock, SystemClock};
+
+/// Identifies a timer registered in [`Timers`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TimerId(usize);
+
+enum Callback<T> {
+    Once(Box<dyn FnOnce(&mut T) + Send>),
+    Periodic(Box<dyn FnMut(&mut T) + Send>, Duration),
+}
+
+struct Timer<T> {
+    id: TimerId,
+    deadline: Instant,
+    callback: Callback<T>,
+}
+
+/// One-shot and periodic timers of a module.
+/// Callbacks receive the module and run in its working thread. A module returning its timers
+/// from [`crate::Module::timers`] is woken by its container at their deadlines, also between
+/// cycles, and timers due at the start of a cycle fire right before its update.
+/// Callbacks may register and cancel timers, including their own.
+pub struct Timers<T> {
+    clock: Arc<dyn Clock>,
+    timers: Vec<Timer<T>>,
+    next_id: usize,
+    /// The periodic timer whose callback is running and whether it was cancelled by it.
+    firing: Option<(TimerId, bool)>,
+}
+
+impl<T> Timers<T> {
+    /// Creates timers using the system clock.
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// Creates timers using `clock`, e.g. a [`crate::ManualClock`] in tests.
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self { clock, timers: Vec::new(), next_id: 0, firing: None }
+    }
+
+    /// Measures deadlines with `clock` from now on, e.g. the clock of the container
+    /// running the module, see [`TimerQueue`]. Pending timers keep their remaining delay.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        let (old, new) = (self.clock.now(), clock.now());
+        for timer in &mut self.timers {
+            timer.deadline = new + timer.deadline.saturating_duration_since(old);
+        }
+        self.clock = clock;
+    }
+
+    /// The clock deadlines are measured with.
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    /// Calls `callback` once, `delay` from now.
+    /// With a zero delay it fires as soon as the container gets to it, or right away if registered by a callback.
+    pub fn once<F: FnOnce(&mut T) + Send + 'static>(&mut self, delay: Duration, callback: F) -> TimerId {
+        self.insert(delay, Callback::Once(Box::new(callback)))
+    }
+
+    /// Calls `callback` every `period`, starting one period from now.
+    /// Missed periods are skipped instead of fired in a burst. A zero period fires once per update.
+    pub fn every<F: FnMut(&mut T) + Send + 'static>(&mut self, period: Duration, callback: F) -> TimerId {
+        self.insert(period, Callback::Periodic(Box::new(callback), period))
+    }
+
+    /// Cancels a timer. Returns false if it already fired or was cancelled.
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        if let Some((firing, cancelled)) = &mut self.firing
+            && *firing == id
+        {
+            return !std::mem::replace(cancelled, true);
+        }
+        let len = self.timers.len();
+        self.timers.retain(|timer| timer.id != id);
+        self.timers.len() != len
+    }
+
+    /// Whether a timer is still waiting to fire.
+    pub fn is_pending(&self, id: TimerId) -> bool {
+        self.firing.is_some_and(|(firing, cancelled)| firing == id && !cancelled)
+            || self.timers.iter().any(|timer| timer.id == id)
+    }
+
+    /// The earliest deadline of all timers. Periodic timers with a zero period
+    /// fire with every update and do not count, so they do not keep the container busy.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.timers.iter()
+            .filter(|timer| !matches!(timer.callback, Callback::Periodic(_, period) if period.is_zero()))
+            .map(|timer| timer.deadline)
+            .min()
+    }
+
+    /// Fires all timers that are due, in order of their deadlines.
+    /// `timers` returns the timers stored in `target`.
+    pub fn fire(target: &mut T, timers: fn(&mut T) -> &mut Timers<T>) {
+        let now = timers(target).clock.now();
+        while let Some(timer) = timers(target).pop_due(now) {
+            match timer.callback {
+                Callback::Once(callback) => callback(target),
+                Callback::Periodic(mut callback, period) => {
+                    timers(target).firing = Some((timer.id, false));
+                    callback(target);
+                    let this = timers(target);
+                    if let Some((_, false)) = this.firing.take() {
+                        let mut deadline = timer.deadline + period;
+                        while deadline <= now && !period.is_zero() {
+                            deadline += period;
+                        }
+                        this.timers.push(Timer {
+                            id: timer.id,
+                            // A zero period fires once per call.
+                            deadline: deadline.max(now + Duration::from_nanos(1)),
+                            callback: Callback::Periodic(callback, period),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, delay: Duration, callback: Callback<T>) -> TimerId {
+        let id = TimerId(self.next_id);
+        self.next_id += 1;
+        self.timers.push(Timer { id, deadline: self.clock.now() + delay, callback });
+        id
+    }
+
+    fn pop_due(&mut self, now: Instant) -> Option<Timer<T>> {
+        let index = self.timers.iter()
+            .enumerate()
+            .filter(|(_, timer)| timer.deadline <= now)
+            .min_by_key(|(_, timer)| timer.deadline)?
+            .0;
+        Some(self.timers.remove(index))
+    }
+}
+
+/// The part of [`Timers`] a container uses, independent of the type of the module.
+pub trait TimerQueue {
+    /// Measures deadlines with the clock of the container, see [`Timers::set_clock`].
+    fn set_clock(&mut self, clock: Arc<dyn Clock>);
+
+    /// The earliest deadline, the container wakes the module for it.
+    fn next_deadline(&self) -> Option<Instant>;
+}
+
+impl<T> TimerQueue for Timers<T> {
+    fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        Timers::set_clock(self, clock);
+    }
+
+    fn next_deadline(&self) -> Option<Instant> {
+        Timers::next_deadline(self)
+    }
+}
+
+impl<T> Default for Timers<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use crate::{ManualClock, TimerId, Timers};
+
+    #[derive(Default)]
+    struct Counter {
+        fired: Vec<&'static str>,
+        timers: Timers<Counter>,
+        periodic: Option<TimerId>,
+    }
+
+    fn timers(counter: &mut Counter) -> &mut Timers<Counter> {
+        &mut counter.timers
+    }
+
+    #[test]
+    fn one_shot_and_periodic() {
+        let clock = ManualClock::new();
+        let mut counter = Counter { timers: Timers::with_clock(Arc::new(clock.clone())), ..Default::default() };
+        let once = counter.timers.once(Duration::from_secs(2), |c| c.fired.push("once"));
+        counter.timers.every(Duration::from_secs(1), |c| c.fired.push("every"));
+
+        Timers::fire(&mut counter, timers);
+        assert!(counter.fired.is_empty());
+        clock.advance(Duration::from_secs(1));
+        Timers::fire(&mut counter, timers);
+        assert_eq!(counter.fired, ["every"]);
+        clock.advance(Duration::from_secs(1));
+        Timers::fire(&mut counter, timers);
+        assert_eq!(counter.fired, ["every", "once", "every"]);
+        assert!(!counter.timers.is_pending(once));
+
+        // Missed periods are skipped.
+        clock.advance(Duration::from_secs(5));
+        Timers::fire(&mut counter, timers);
+        assert_eq!(counter.fired.len(), 4);
+    }
+
+    #[test]
+    fn callbacks_cancel_and_register_timers() {
+        let clock = ManualClock::new();
+        let mut counter = Counter { timers: Timers::with_clock(Arc::new(clock.clone())), ..Default::default() };
+        let periodic = counter.timers.every(Duration::from_secs(1), |c| {
+            c.fired.push("periodic");
+            let id = c.periodic.unwrap();
+            assert!(c.timers.cancel(id));
+            c.timers.once(Duration::ZERO, |c| c.fired.push("follow up"));
+        });
+        counter.periodic = Some(periodic);
+
+        clock.advance(Duration::from_secs(1));
+        Timers::fire(&mut counter, timers);
+        assert_eq!(counter.fired, ["periodic", "follow up"]);
+        assert!(!counter.timers.is_pending(periodic));
+        assert!(!counter.timers.cancel(periodic));
+        assert_eq!(counter.timers.next_deadline(), None);
+    }
+}
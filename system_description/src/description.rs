@@ -0,0 +1,81 @@
+use std::time::Duration;
+use serde::Deserialize;
+use scheduling::SpawnMode;
+
+/// A group in a system description. The root table of the file is the root group.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct GroupDescription {
+    #[serde(default)]
+    pub(crate) name: Option<String>,
+    #[serde(default)]
+    pub(crate) spawn_mode: Option<String>,
+    #[serde(default)]
+    pub(crate) modules: Vec<ModuleDescription>,
+    #[serde(default)]
+    pub(crate) groups: Vec<GroupDescription>,
+    /// Connections between ports of modules in this group and its child groups.
+    #[serde(default)]
+    pub(crate) connections: Vec<ConnectionDescription>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ModuleDescription {
+    pub(crate) name: String,
+    #[serde(rename = "type")]
+    pub(crate) type_name: String,
+    pub(crate) cycle_time: String,
+    #[serde(default)]
+    pub(crate) spawn_mode: Option<String>,
+    /// Values written to ports of the module, deserialized with `PortDeserialize`.
+    #[serde(default)]
+    pub(crate) parameters: toml::Table,
+    /// Port lists passed to modules registered with inputs, e.g. data and activity ports of a fusion.
+    #[serde(default)]
+    pub(crate) inputs: Vec<Vec<String>>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ConnectionDescription {
+    pub(crate) from: String,
+    pub(crate) to: String,
+}
+
+/// Parses a duration like `500ms`, `2s`, `1.5s` or `100us`.
+pub(crate) fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let split = value.find(|c: char| c.is_ascii_alphabetic())?;
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.trim().parse().ok()?;
+    let seconds = match unit {
+        "s" => number,
+        "ms" => number / 1e3,
+        "us" => number / 1e6,
+        "ns" => number / 1e9,
+        _ => return None,
+    };
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+pub(crate) fn parse_spawn_mode(value: &str) -> Option<SpawnMode> {
+    match value {
+        "GroupThread" => Some(SpawnMode::GroupThread),
+        "NewThread" => Some(SpawnMode::NewThread),
+        "ThreadPool" => Some(SpawnMode::ThreadPool),
+        _ => None,
+    }
+}
+
+/// Converts a TOML parameter value to the string passed to `PortDeserialize`.
+/// Arrays and tables have no unique string form and must be written as strings.
+pub(crate) fn parameter_string(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(value) => Some(value.clone()),
+        toml::Value::Integer(value) => Some(value.to_string()),
+        toml::Value::Float(value) => Some(value.to_string()),
+        toml::Value::Boolean(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use ports::prelude::PortError;
+
+/// Errors found while loading a system description or parameter file.
+/// Modules are referred to by their path in the description, e.g. `sensors/lidar`.
+#[derive(Debug)]
+pub enum LoadError {
+    Io { path: PathBuf, error: std::io::Error },
+    /// The file is not valid TOML or does not match the description format.
+    Parse(toml::de::Error),
+    UnknownType { module: String, type_name: String, known: Vec<String> },
+    DuplicateName { path: String },
+    /// A child group without a name. The root group has an empty path.
+    MissingGroupName { parent: String },
+    InvalidCycleTime { module: String, value: String },
+    InvalidSpawnMode { path: String, value: String },
+    /// A port reference that is not of the form `module.port`.
+    InvalidPortReference { reference: String },
+    UnknownModule { reference: String },
+    UnknownPort { module: String, port: String, available: Vec<String> },
+    UnsupportedParameter { module: String, port: String },
+    Parameter { module: String, port: String, error: PortError },
+    NotAnInput { port: String },
+    Connection { from: String, to: String, error: PortError },
+    /// `inputs` was set on a module whose type was not registered with inputs.
+    NoInputs { module: String, type_name: String },
+    Input { module: String, error: PortError },
+    UnknownParameter { name: String },
+    /// The parameter file could not be watched.
+    Watch(notify::Error),
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Io { path, error } => write!(f, "failed to read `{}`: {error}", path.display()),
+            LoadError::Parse(error) => write!(f, "invalid system description: {error}"),
+            LoadError::UnknownType { module, type_name, known } => {
+                write!(f, "module `{module}` has unknown type `{type_name}`, registered types are: {}", known.join(", "))
+            }
+            LoadError::DuplicateName { path } => write!(f, "`{path}` is defined more than once"),
+            LoadError::MissingGroupName { parent } if parent.is_empty() => write!(f, "a child group of the root group has no name"),
+            LoadError::MissingGroupName { parent } => write!(f, "a child group of `{parent}` has no name"),
+            LoadError::InvalidCycleTime { module, value } => {
+                write!(f, "module `{module}` has invalid cycle time `{value}`, expected e.g. `10ms` or `1.5s`")
+            }
+            LoadError::InvalidSpawnMode { path, value } => {
+                write!(f, "`{path}` has invalid spawn mode `{value}`, expected `GroupThread`, `NewThread` or `ThreadPool`")
+            }
+            LoadError::InvalidPortReference { reference } => {
+                write!(f, "invalid port reference `{reference}`, expected `module.port` or `group/module.port`")
+            }
+            LoadError::UnknownModule { reference } => write!(f, "`{reference}` refers to an unknown module"),
+            LoadError::UnknownPort { module, port, available } => {
+                write!(f, "module `{module}` has no port `{port}`, available ports are: {}", available.join(", "))
+            }
+            LoadError::UnsupportedParameter { module, port } => {
+                write!(f, "parameter `{port}` of module `{module}` must be a string, number or boolean")
+            }
+            LoadError::Parameter { module, port, error } => write!(f, "parameter `{port}` of module `{module}`: {error}"),
+            LoadError::NotAnInput { port } => write!(f, "`{port}` is not an input and can not be connected to a source"),
+            LoadError::Connection { from, to, error } => write!(f, "can not connect `{from}` to `{to}`: {error}"),
+            LoadError::NoInputs { module, type_name } => {
+                write!(f, "module `{module}` has `inputs`, but type `{type_name}` was not registered with inputs")
+            }
+            LoadError::Input { module, error } => write!(f, "invalid input of module `{module}`: {error}"),
+            LoadError::UnknownParameter { name } => write!(f, "unknown parameter `{name}`"),
+            LoadError::Watch(error) => write!(f, "failed to watch the parameter file: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io { error, .. } => Some(error),
+            LoadError::Parse(error) => Some(error),
+            LoadError::Watch(error) => Some(error),
+            LoadError::Parameter { error, .. } | LoadError::Connection { error, .. } | LoadError::Input { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
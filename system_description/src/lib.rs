@@ -0,0 +1,213 @@
+//! Builds a system from a TOML description instead of `init` functions.
+//!
+//! Module types are registered by name in a [`ModuleRegistry`], which instantiates the
+//! described modules and returns the root [`scheduling::GroupBuilder`], ready to spawn.
+//!
+//! ```toml
+//! name = "robot"
+//! spawn_mode = "NewThread"           # optional, default `NewThread` for the root group
+//!
+//! [[modules]]
+//! name = "oscillator"                # unique within its group
+//! type = "Oscillator"                # name used in `ModuleRegistry::register`
+//! cycle_time = "500ms"               # `ns`, `us`, `ms` or `s`
+//! spawn_mode = "GroupThread"         # optional, default `GroupThread`
+//! parameters = { frequency = 2.5 }   # written to ports with `PortDeserialize`
+//!
+//! [[modules]]
+//! name = "fusion"
+//! type = "MaximumFusion"
+//! cycle_time = "10ms"
+//! inputs = [["oscillator.out_data", "oscillator.activity"]]
+//!
+//! [[groups]]
+//! name = "output"                    # child groups use `GroupThread` by default
+//! spawn_mode = "ThreadPool"
+//!
+//! [[groups.modules]]
+//! name = "printer"
+//! type = "PrintModule"
+//! cycle_time = "300ms"
+//!
+//! [[connections]]                    # from a source port to an input
+//! from = "fusion.output_port"
+//! to = "output/printer.in_data"
+//! ```
+//!
+//! Port references are relative to the group the connection or input is defined in.
+//! The [`ports::prelude::ParameterPort`]s of the loaded modules can be collected with
+//! [`ModuleRegistry::load_with_parameters`] to tune them from a parameter file, see [`Parameters`].
+
+mod description;
+mod error;
+mod registry;
+mod parameters;
+
+pub use error::LoadError;
+pub use registry::ModuleRegistry;
+pub use parameters::{ParameterWatcher, Parameters};
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use ports::prelude::*;
+    use scheduling::Module;
+    use crate::{LoadError, ModuleRegistry};
+
+    #[derive(PortMethods, Default)]
+    struct Counter {
+        pub step: ReceivePort<i32>,
+        pub count: SendPort<i32>,
+    }
+
+    impl Module for Counter {
+        fn update(&mut self) {
+            self.update_ports();
+            let count = self.count.get_last_data() + self.step.get_data();
+            self.count.send(count);
+        }
+    }
+
+    #[derive(PortMethods, Default)]
+    struct Sum {
+        pub offset: ReceivePort<i32>,
+        pub label: ReceivePort<String>,
+        pub sum: SendPort<i32>,
+        inputs: Vec<ReceivePort<i32>>,
+    }
+
+    impl Module for Sum {
+        fn update(&mut self) {
+            self.update_ports();
+            let sum = self.offset.get_data() + self.inputs.iter().map(|port| port.get_data()).sum::<i32>();
+            self.sum.send(sum);
+        }
+    }
+
+    /// Registers the test modules. The output of every `Sum` is connected to a port in `sums`.
+    fn registry(sums: Arc<Mutex<Vec<ReceivePort<i32>>>>) -> ModuleRegistry {
+        let mut registry = ModuleRegistry::new();
+        registry.register("Counter", Counter::default);
+        registry.register_with_inputs(
+            "Sum",
+            move || {
+                let sum = Sum::default();
+                let probe = ReceivePort::default();
+                probe.connect_from(&sum.sum).unwrap();
+                sums.lock().unwrap().push(probe);
+                sum
+            },
+            |sum, ports| {
+                let input = ReceivePort::default();
+                input.connect_from_dyn(&ports[0])?;
+                sum.inputs.push(input);
+                Ok(())
+            },
+        );
+        registry
+    }
+
+    const SYSTEM: &str = r#"
+        name = "system"
+
+        [[modules]]
+        name = "counter"
+        type = "Counter"
+        cycle_time = "1ms"
+        parameters = { step = 2 }
+
+        [[modules]]
+        name = "sum"
+        type = "Sum"
+        cycle_time = "1ms"
+        parameters = { offset = 100 }
+        inputs = [["counter.count"], ["counter.count"]]
+
+        [[groups]]
+        name = "output"
+        spawn_mode = "NewThread"
+
+        [[groups.modules]]
+        name = "sum"
+        type = "Sum"
+        cycle_time = "1ms"
+        parameters = { label = "output" }
+
+        [[connections]]
+        from = "sum.sum"
+        to = "output/sum.offset"
+    "#;
+
+    fn load(from: &str, to: &str) -> Result<scheduling::GroupBuilder, LoadError> {
+        assert!(SYSTEM.contains(from));
+        registry(Arc::default()).load(&SYSTEM.replacen(from, to, 1))
+    }
+
+    fn error(from: &str, to: &str) -> String {
+        load(from, to).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn load_and_spawn() {
+        let sums = Arc::new(Mutex::new(Vec::new()));
+        let runtime = registry(Arc::clone(&sums)).load(SYSTEM).unwrap().spawn();
+        assert!(runtime.find_module("counter").is_some());
+        assert!(runtime.find_module("output/sum").is_some());
+        assert_eq!(runtime.groups().count(), 2);
+
+        let mut output = sums.lock().unwrap().pop().unwrap();
+        let start = Instant::now();
+        // offset + 2 * count, with the count increasing in steps of 2.
+        while *output.get_data() <= 104 {
+            assert!(start.elapsed() < Duration::from_secs(1));
+            std::thread::sleep(Duration::from_millis(1));
+            output.update();
+        }
+        assert_eq!(output.get_data() % 2, 0);
+        runtime.shutdown();
+    }
+
+    #[test]
+    fn module_errors() {
+        assert_eq!(error(r#"type = "Counter""#, r#"type = "Count""#),
+            "module `counter` has unknown type `Count`, registered types are: Counter, Sum");
+        assert_eq!(error(r#"cycle_time = "1ms""#, r#"cycle_time = "fast""#),
+            "module `counter` has invalid cycle time `fast`, expected e.g. `10ms` or `1.5s`");
+        assert_eq!(error(r#"spawn_mode = "NewThread""#, r#"spawn_mode = "Thread""#),
+            "`output` has invalid spawn mode `Thread`, expected `GroupThread`, `NewThread` or `ThreadPool`");
+        assert_eq!(error(r#"name = "output""#, r#"name = "counter""#), "`counter` is defined more than once");
+        assert_eq!(error("step = 2", r#"step = "two""#),
+            "parameter `step` of module `counter`: `two` is not a valid `i32`");
+        assert_eq!(error("step = 2", "steps = 2"),
+            "module `counter` has no port `steps`, available ports are: step, count");
+        assert_eq!(error("step = 2", "step = [2]"),
+            "parameter `step` of module `counter` must be a string, number or boolean");
+        let parse_error = error(r#"cycle_time = "1ms""#, r#"cycle = "1ms""#);
+        assert!(parse_error.starts_with("invalid system description: TOML parse error at line 7, column 9"));
+        assert!(parse_error.contains("unknown field `cycle`"));
+    }
+
+    #[test]
+    fn connection_errors() {
+        assert_eq!(error(r#"to = "output/sum.offset""#, r#"to = "output/sum.label""#),
+            "can not connect `sum.sum` to `output/sum.label`: expected a port of type `alloc::string::String`, found `i32`");
+        assert_eq!(error(r#"to = "output/sum.offset""#, r#"to = "output/sum.sum""#),
+            "`output/sum.sum` is not an input and can not be connected to a source");
+        assert_eq!(error("[[connections]]", "[[connections]]\nfrom = \"counter.count\"\nto = \"output/sum.offset\"\n[[connections]]"),
+            "can not connect `sum.sum` to `output/sum.offset`: the port is already connected to a source");
+        assert_eq!(error(r#"from = "sum.sum""#, r#"from = "sum""#),
+            "invalid port reference `sum`, expected `module.port` or `group/module.port`");
+        assert_eq!(error(r#"to = "output/sum.offset""#, r#"to = "outputs/sum.offset""#),
+            "`outputs/sum.offset` refers to an unknown module");
+        assert_eq!(error(r#"[["counter.count"], ["counter.count"]]"#, r#"[["counter.counts"]]"#),
+            "module `counter` has no port `counts`, available ports are: step, count");
+        assert_eq!(error(r#"[["counter.count"], ["counter.count"]]"#, r#"[["sum.label"]]"#),
+            "invalid input of module `sum`: expected a port of type `i32`, found `alloc::string::String`");
+
+        let mut registry = registry(Arc::default());
+        registry.register("Sum", Sum::default);
+        assert_eq!(registry.load(SYSTEM).err().unwrap().to_string(),
+            "module `sum` has `inputs`, but type `Sum` was not registered with inputs");
+    }
+}
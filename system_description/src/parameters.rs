@@ -0,0 +1,272 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use ports::prelude::{DynPort, PortDirection, PortEntry, PortError, PortReflection};
+use crate::description::parameter_string;
+use crate::error::LoadError;
+
+/// The [`ports::prelude::ParameterPort`]s of a system, named by the path of their module
+/// and the name of the port, e.g. `sensors/lidar.gain`.
+///
+/// Parameter files are TOML files with a table per group and module:
+///
+/// ```toml
+/// [sensors.lidar]
+/// gain = 2.5
+/// ```
+#[derive(Clone, Default)]
+pub struct Parameters {
+    ports: BTreeMap<String, DynPort>,
+}
+
+/// Watches a parameter file, see [`Parameters::watch`]. Dropping it stops watching.
+pub struct ParameterWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl Parameters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the parameter ports of the module at `path`, e.g. `sensors/lidar`.
+    pub fn add_module(&mut self, path: &str, module: &impl PortReflection) {
+        self.add_ports(path, &module.ports());
+    }
+
+    pub(crate) fn add_ports(&mut self, path: &str, ports: &[PortEntry]) {
+        for entry in ports.iter().filter(|entry| entry.direction == PortDirection::Parameter) {
+            self.ports.insert(format!("{path}.{}", entry.name), entry.port.clone());
+        }
+    }
+
+    /// Names of all parameters in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.ports.keys().map(String::as_str)
+    }
+
+    /// The current value of a parameter, serialized with `PortSerialize`.
+    pub fn get(&self, name: &str) -> Result<String, LoadError> {
+        self.port(name)?.serialize().map_err(|error| parameter_error(name, error))
+    }
+
+    /// Sets a parameter, deserialized with `PortDeserialize`. Running modules see the value
+    /// after their next update. Returns false if the parameter already had this value.
+    pub fn set(&self, name: &str, value: &str) -> Result<bool, LoadError> {
+        let port = self.port(name)?;
+        if port.serialize().is_ok_and(|current| current == value) {
+            return Ok(false);
+        }
+        port.deserialize(value).map_err(|error| parameter_error(name, error))?;
+        Ok(true)
+    }
+
+    /// Sets all parameters of a parameter file given as string.
+    /// Nothing is set if the file contains unknown parameters or invalid values.
+    /// Returns the names of the parameters whose value changed.
+    pub fn load(&self, parameters: &str) -> Result<Vec<String>, LoadError> {
+        let table: toml::Table = toml::from_str(parameters).map_err(LoadError::Parse)?;
+        let mut values = Vec::new();
+        flatten(&table, &mut Vec::new(), &mut values)?;
+        // Check every value before setting any, so an invalid file changes nothing.
+        for (name, value) in &values {
+            self.port(name)?.validate(value).map_err(|error| parameter_error(name, error))?;
+        }
+        let mut changed = Vec::new();
+        for (name, value) in values {
+            if self.set(&name, &value)? {
+                changed.push(name);
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Sets all parameters of the parameter file at `path`, see [`Parameters::load`].
+    pub fn load_file(&self, path: impl AsRef<Path>) -> Result<Vec<String>, LoadError> {
+        let path = path.as_ref();
+        let parameters = std::fs::read_to_string(path)
+            .map_err(|error| LoadError::Io { path: path.to_path_buf(), error })?;
+        self.load(&parameters)
+    }
+
+    /// The current values of all parameters as parameter file.
+    pub fn to_toml(&self) -> Result<String, LoadError> {
+        let mut root = toml::Table::new();
+        for name in self.ports.keys() {
+            let (module, port) = split_name(name);
+            let table = module.split('/').fold(&mut root, |table, group| {
+                table.entry(group)
+                    .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                    .as_table_mut()
+                    .unwrap()
+            });
+            table.insert(port.to_string(), toml_value(self.get(name)?));
+        }
+        Ok(toml::to_string(&root).unwrap())
+    }
+
+    /// Writes the current values of all parameters to `path`.
+    pub fn save_file(&self, path: impl AsRef<Path>) -> Result<(), LoadError> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_toml()?)
+            .map_err(|error| LoadError::Io { path: path.to_path_buf(), error })
+    }
+
+    /// Loads the parameter file at `path` every time it changes and passes the result of
+    /// each reload to `on_reload`. Errors in the changed file leave the parameters unchanged.
+    pub fn watch(
+        &self,
+        path: impl AsRef<Path>,
+        mut on_reload: impl FnMut(Result<Vec<String>, LoadError>) + Send + 'static,
+    ) -> Result<ParameterWatcher, LoadError> {
+        let path = path.as_ref().to_path_buf();
+        // Editors often replace files instead of writing them, so the directory is watched.
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => Path::new(".").to_path_buf(),
+        };
+        let file_name = path.file_name().map(|name| name.to_os_string());
+        let parameters = self.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let event = match event {
+                Ok(event) => event,
+                Err(error) => return on_reload(Err(LoadError::Watch(error))),
+            };
+            let is_file = event.paths.iter().any(|changed| changed.file_name() == file_name.as_deref());
+            if is_file && (event.kind.is_modify() || event.kind.is_create()) {
+                on_reload(parameters.load_file(&path));
+            }
+        }).map_err(LoadError::Watch)?;
+        watcher.watch(&directory, RecursiveMode::NonRecursive).map_err(LoadError::Watch)?;
+        Ok(ParameterWatcher { _watcher: watcher })
+    }
+
+    fn port(&self, name: &str) -> Result<&DynPort, LoadError> {
+        self.ports.get(name).ok_or_else(|| LoadError::UnknownParameter { name: name.to_string() })
+    }
+}
+
+fn parameter_error(name: &str, error: PortError) -> LoadError {
+    let (module, port) = split_name(name);
+    LoadError::Parameter { module: module.to_string(), port: port.to_string(), error }
+}
+
+/// Splits a parameter name into module path and port name.
+fn split_name(name: &str) -> (&str, &str) {
+    name.rsplit_once('.').unwrap_or(("", name))
+}
+
+/// Collects the values of nested tables as `group/module.port` names.
+fn flatten(table: &toml::Table, path: &mut Vec<String>, values: &mut Vec<(String, String)>) -> Result<(), LoadError> {
+    for (key, value) in table {
+        if let toml::Value::Table(table) = value {
+            path.push(key.clone());
+            flatten(table, path, values)?;
+            path.pop();
+            continue;
+        }
+        let module = path.join("/");
+        let value = parameter_string(value).ok_or_else(|| LoadError::UnsupportedParameter {
+            module: module.clone(),
+            port: key.clone(),
+        })?;
+        values.push((format!("{module}.{key}"), value));
+    }
+    Ok(())
+}
+
+/// Writes numbers and booleans as such, so they can be edited like in the system description.
+fn toml_value(value: String) -> toml::Value {
+    if let Ok(integer) = value.parse::<i64>() {
+        toml::Value::Integer(integer)
+    } else if let Ok(float) = value.parse::<f64>()
+        && float.is_finite()
+    {
+        toml::Value::Float(float)
+    } else if let Ok(boolean) = value.parse::<bool>() {
+        toml::Value::Boolean(boolean)
+    } else {
+        toml::Value::String(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use ports::prelude::*;
+    use crate::{LoadError, Parameters};
+
+    #[derive(PortMethods, Default)]
+    struct Controller {
+        pub gain: ParameterPort<f64>,
+        pub enabled: ParameterPort<bool>,
+        pub input: ReceivePort<f64>,
+    }
+
+    fn parameters(controller: &Controller) -> Parameters {
+        let mut parameters = Parameters::new();
+        parameters.add_module("arm/controller", controller);
+        parameters
+    }
+
+    #[test]
+    fn load_and_save() {
+        let mut controller = Controller::default();
+        let parameters = parameters(&controller);
+        assert_eq!(parameters.names().collect::<Vec<_>>(), ["arm/controller.enabled", "arm/controller.gain"]);
+
+        let changed = parameters.load("[arm.controller]\ngain = 2.5\nenabled = false\n").unwrap();
+        assert_eq!(changed, ["arm/controller.gain"]);
+        controller.update_ports();
+        assert_eq!(*controller.gain.get_data(), 2.5);
+        assert_eq!(parameters.to_toml().unwrap(), "[arm.controller]\nenabled = false\ngain = 2.5\n");
+
+        assert!(matches!(parameters.load("[arm.controller]\ninput = 1.0\ngain = 3.0"),
+            Err(LoadError::UnknownParameter { name }) if name == "arm/controller.input"));
+        assert_eq!(parameters.get("arm/controller.gain").unwrap(), "2.5");
+        // An invalid value leaves the valid values before it unset.
+        assert!(matches!(parameters.load("[arm.controller]\nenabled = true\ngain = \"fast\""),
+            Err(LoadError::Parameter { port, .. }) if port == "gain"));
+        assert_eq!(parameters.get("arm/controller.enabled").unwrap(), "false");
+        assert_eq!(parameters.set("arm/controller.gain", "fast").unwrap_err().to_string(),
+            "parameter `gain` of module `arm/controller`: `fast` is not a valid `f64`");
+    }
+
+    #[test]
+    fn hot_reload() {
+        let directory = std::env::temp_dir().join(format!("parameters_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let file = directory.join("parameters.toml");
+
+        let mut controller = Controller::default();
+        let parameters = parameters(&controller);
+        parameters.set("arm/controller.gain", "1.5").unwrap();
+        parameters.save_file(&file).unwrap();
+        let (sender, reloads) = std::sync::mpsc::channel();
+        let _watcher = parameters.watch(&file, move |result| {
+            let _ = sender.send(result);
+        }).unwrap();
+
+        std::fs::write(&file, "[arm.controller]\ngain = 4\nenabled = true\n").unwrap();
+        let start = Instant::now();
+        while *controller.gain.get_data() != 4.0 {
+            assert!(start.elapsed() < Duration::from_secs(5), "parameter file was not reloaded");
+            std::thread::sleep(Duration::from_millis(10));
+            controller.update_ports();
+        }
+        assert!(*controller.enabled.get_data());
+
+        // Editors may write a file in several steps, so skip the reloads of the valid file.
+        while reloads.try_recv().is_ok() {}
+        std::fs::write(&file, "[arm.controller]\ngain = \"fast\"\n").unwrap();
+        let error = loop {
+            match reloads.recv_timeout(Duration::from_secs(5)).expect("invalid file was not reported") {
+                Err(error) => break error,
+                Ok(_) => continue,
+            }
+        };
+        assert!(matches!(error, LoadError::Parameter { port, .. } if port == "gain"));
+        assert_eq!(parameters.get("arm/controller.gain").unwrap(), "4");
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
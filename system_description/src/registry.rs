@@ -0,0 +1,289 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use ports::prelude::{DynPort, PortDirection, PortEntry, PortError, PortReflection};
+use scheduling::{GroupBuilder, Module, ModuleBuilder, SpawnMode};
+use crate::description::{parameter_string, parse_duration, parse_spawn_mode, GroupDescription, ModuleDescription};
+use crate::error::LoadError;
+use crate::parameters::Parameters;
+
+/// Adds a list of type erased ports to a module, see [`ModuleRegistry::register_with_inputs`].
+type InputHook<M> = Arc<dyn Fn(&mut M, &[DynPort]) -> Result<(), PortError>>;
+
+type Factory = Box<dyn Fn() -> Box<dyn PendingModule>>;
+
+/// A module created from the description that was not added to its group yet.
+trait PendingModule {
+    fn ports(&self) -> Vec<PortEntry>;
+
+    /// Returns `None` if the module was registered without inputs.
+    fn add_input(&mut self, ports: &[DynPort]) -> Option<Result<(), PortError>>;
+
+    fn add_to(self: Box<Self>, group: &mut GroupBuilder, name: String, cycle_time: Duration, spawn_mode: SpawnMode);
+}
+
+struct Pending<M> {
+    module: M,
+    add_input: Option<InputHook<M>>,
+}
+
+impl<M: Module + PortReflection + Send + 'static> PendingModule for Pending<M> {
+    fn ports(&self) -> Vec<PortEntry> {
+        self.module.ports()
+    }
+
+    fn add_input(&mut self, ports: &[DynPort]) -> Option<Result<(), PortError>> {
+        let add_input = self.add_input.as_ref()?;
+        Some(add_input(&mut self.module, ports))
+    }
+
+    fn add_to(self: Box<Self>, group: &mut GroupBuilder, name: String, cycle_time: Duration, spawn_mode: SpawnMode) {
+        group.add_module(ModuleBuilder::new(self.module, cycle_time, spawn_mode).with_name(name));
+    }
+}
+
+/// Creates modules by their registered type name to build a system from a description,
+/// see the [crate documentation](crate) for the format.
+#[derive(Default)]
+pub struct ModuleRegistry {
+    factories: BTreeMap<String, Factory>,
+}
+
+impl ModuleRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a module type. `factory` creates a new instance for every module of that type.
+    pub fn register<M, F>(&mut self, type_name: impl Into<String>, factory: F)
+    where
+        M: Module + PortReflection + Send + 'static,
+        F: Fn() -> M + 'static,
+    {
+        self.factories.insert(type_name.into(), Box::new(move || {
+            Box::new(Pending { module: factory(), add_input: None })
+        }));
+    }
+
+    /// Registers a module type taking lists of ports from the `inputs` of its description,
+    /// e.g. the data and activity port of every module connected to a fusion.
+    pub fn register_with_inputs<M, F, I>(&mut self, type_name: impl Into<String>, factory: F, add_input: I)
+    where
+        M: Module + PortReflection + Send + 'static,
+        F: Fn() -> M + 'static,
+        I: Fn(&mut M, &[DynPort]) -> Result<(), PortError> + 'static,
+    {
+        let add_input: InputHook<M> = Arc::new(add_input);
+        self.factories.insert(type_name.into(), Box::new(move || {
+            Box::new(Pending { module: factory(), add_input: Some(Arc::clone(&add_input)) })
+        }));
+    }
+
+    /// All registered type names in alphabetical order.
+    pub fn type_names(&self) -> impl Iterator<Item = &str> {
+        self.factories.keys().map(String::as_str)
+    }
+
+    /// Builds the root group of the system described by the TOML file at `path`.
+    pub fn load_file(&self, path: impl AsRef<Path>) -> Result<GroupBuilder, LoadError> {
+        let path = path.as_ref();
+        let description = std::fs::read_to_string(path)
+            .map_err(|error| LoadError::Io { path: path.to_path_buf(), error })?;
+        self.load(&description)
+    }
+
+    /// Builds the root group of the system described by a TOML string.
+    pub fn load(&self, description: &str) -> Result<GroupBuilder, LoadError> {
+        self.load_with_parameters(description).map(|(group, _)| group)
+    }
+
+    /// Like [`ModuleRegistry::load`], also returning the parameter ports of all modules.
+    pub fn load_with_parameters(&self, description: &str) -> Result<(GroupBuilder, Parameters), LoadError> {
+        let description: GroupDescription = toml::from_str(description).map_err(LoadError::Parse)?;
+        let mut loader = Loader { registry: self, modules: HashMap::new(), parameters: Parameters::new() };
+        let root = loader.instantiate(&description, String::new())?;
+        loader.connect(&description, "")?;
+        let group = loader.build(root);
+        Ok((group, loader.parameters))
+    }
+}
+
+struct LoadedModule {
+    module: Box<dyn PendingModule>,
+    ports: Vec<PortEntry>,
+    name: String,
+    type_name: String,
+    cycle_time: Duration,
+    spawn_mode: SpawnMode,
+}
+
+/// A group of the description with the paths of its modules.
+struct LoadedGroup {
+    name: String,
+    spawn_mode: SpawnMode,
+    modules: Vec<String>,
+    groups: Vec<LoadedGroup>,
+}
+
+struct Loader<'a> {
+    registry: &'a ModuleRegistry,
+    modules: HashMap<String, LoadedModule>,
+    parameters: Parameters,
+}
+
+impl Loader<'_> {
+    /// Creates the modules of a group and its child groups and applies their parameters.
+    /// Groups use [`SpawnMode::GroupThread`] unless set, the root group [`SpawnMode::NewThread`].
+    fn instantiate(&mut self, group: &GroupDescription, path: String) -> Result<LoadedGroup, LoadError> {
+        let default_spawn_mode = if path.is_empty() { SpawnMode::NewThread } else { SpawnMode::GroupThread };
+        let spawn_mode = spawn_mode(group.spawn_mode.as_deref(), &path, default_spawn_mode)?;
+        let mut names = HashSet::new();
+        let mut loaded = LoadedGroup {
+            name: group.name.clone().unwrap_or_default(),
+            spawn_mode,
+            modules: Vec::new(),
+            groups: Vec::new(),
+        };
+        for module in &group.modules {
+            let module_path = join_path(&path, &module.name);
+            if !names.insert(module.name.as_str()) {
+                return Err(LoadError::DuplicateName { path: module_path });
+            }
+            self.instantiate_module(module, &module_path)?;
+            loaded.modules.push(module_path);
+        }
+        for child in &group.groups {
+            let child_name = child.name.as_deref().ok_or_else(|| LoadError::MissingGroupName { parent: path.clone() })?;
+            let child_path = join_path(&path, child_name);
+            if !names.insert(child_name) {
+                return Err(LoadError::DuplicateName { path: child_path });
+            }
+            loaded.groups.push(self.instantiate(child, child_path)?);
+        }
+        Ok(loaded)
+    }
+
+    fn instantiate_module(&mut self, module: &ModuleDescription, path: &str) -> Result<(), LoadError> {
+        let factory = self.registry.factories.get(&module.type_name).ok_or_else(|| LoadError::UnknownType {
+            module: path.to_string(),
+            type_name: module.type_name.clone(),
+            known: self.registry.type_names().map(String::from).collect(),
+        })?;
+        let cycle_time = parse_duration(&module.cycle_time).ok_or_else(|| LoadError::InvalidCycleTime {
+            module: path.to_string(),
+            value: module.cycle_time.clone(),
+        })?;
+        let spawn_mode = spawn_mode(module.spawn_mode.as_deref(), path, SpawnMode::GroupThread)?;
+        let instance = factory();
+        let ports = instance.ports();
+        self.parameters.add_ports(path, &ports);
+        for (port_name, value) in &module.parameters {
+            let port = find_port(&ports, path, port_name)?;
+            let value = parameter_string(value).ok_or_else(|| LoadError::UnsupportedParameter {
+                module: path.to_string(),
+                port: port_name.clone(),
+            })?;
+            port.port.deserialize(&value).map_err(|error| LoadError::Parameter {
+                module: path.to_string(),
+                port: port_name.clone(),
+                error,
+            })?;
+        }
+        self.modules.insert(path.to_string(), LoadedModule {
+            module: instance,
+            ports,
+            name: module.name.clone(),
+            type_name: module.type_name.clone(),
+            cycle_time,
+            spawn_mode,
+        });
+        Ok(())
+    }
+
+    /// Applies the connections and inputs of a group and its child groups.
+    /// References are relative to the group they are defined in.
+    fn connect(&mut self, group: &GroupDescription, path: &str) -> Result<(), LoadError> {
+        for connection in &group.connections {
+            let (from, source) = self.port(path, &connection.from)?;
+            let (to, target) = self.port(path, &connection.to)?;
+            if target.direction != PortDirection::Input {
+                return Err(LoadError::NotAnInput { port: to });
+            }
+            target.port.connect_to_source(&source.port)
+                .map_err(|error| LoadError::Connection { from, to, error })?;
+        }
+        for module in &group.modules {
+            let module_path = join_path(path, &module.name);
+            for input in &module.inputs {
+                let ports = input.iter()
+                    .map(|reference| self.port(path, reference).map(|(_, entry)| entry.port.clone()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let loaded = self.modules.get_mut(&module_path).unwrap();
+                match loaded.module.add_input(&ports) {
+                    None => return Err(LoadError::NoInputs { module: module_path, type_name: loaded.type_name.clone() }),
+                    Some(result) => result.map_err(|error| LoadError::Input { module: module_path.clone(), error })?,
+                }
+            }
+        }
+        for child in &group.groups {
+            self.connect(child, &join_path(path, child.name.as_deref().unwrap_or_default()))?;
+        }
+        Ok(())
+    }
+
+    /// Resolves a reference like `group/module.port` relative to the group at `path`.
+    fn port(&self, path: &str, reference: &str) -> Result<(String, &PortEntry), LoadError> {
+        let invalid = || LoadError::InvalidPortReference { reference: reference.to_string() };
+        let (module, port) = reference.rsplit_once('.').ok_or_else(invalid)?;
+        if module.is_empty() || port.is_empty() {
+            return Err(invalid());
+        }
+        let module_path = join_path(path, module);
+        let full_reference = format!("{module_path}.{port}");
+        let loaded = self.modules.get(&module_path)
+            .ok_or(LoadError::UnknownModule { reference: full_reference.clone() })?;
+        Ok((full_reference, find_port(&loaded.ports, &module_path, port)?))
+    }
+
+    fn build(&mut self, group: LoadedGroup) -> GroupBuilder {
+        let mut builder = GroupBuilder::empty()
+            .with_name(group.name)
+            .with_spawn_mode(group.spawn_mode);
+        for path in group.modules {
+            let loaded = self.modules.remove(&path).unwrap();
+            loaded.module.add_to(&mut builder, loaded.name, loaded.cycle_time, loaded.spawn_mode);
+        }
+        for child in group.groups {
+            let child = self.build(child);
+            builder.add_group(child);
+        }
+        builder
+    }
+}
+
+fn find_port<'a>(ports: &'a [PortEntry], module: &str, port: &str) -> Result<&'a PortEntry, LoadError> {
+    ports.iter().find(|entry| entry.name == port).ok_or_else(|| LoadError::UnknownPort {
+        module: module.to_string(),
+        port: port.to_string(),
+        available: ports.iter().map(|entry| entry.name.clone()).collect(),
+    })
+}
+
+fn spawn_mode(value: Option<&str>, path: &str, default: SpawnMode) -> Result<SpawnMode, LoadError> {
+    match value {
+        None => Ok(default),
+        Some(value) => parse_spawn_mode(value).ok_or_else(|| LoadError::InvalidSpawnMode {
+            path: path.to_string(),
+            value: value.to_string(),
+        }),
+    }
+}
+
+fn join_path(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{path}/{name}")
+    }
+}